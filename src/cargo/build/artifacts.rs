@@ -0,0 +1,484 @@
+use std::{
+    cmp::Reverse,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use target_lexicon::{Architecture, BinaryFormat, Environment, OperatingSystem, Triple};
+
+use super::BuildOutput;
+use crate::cargo::{
+    message::Artifact,
+    metadata::{Target, TargetKind},
+    Metadata, PackageId, Result,
+};
+
+impl Artifact {
+    /// The name of the package the artifact belongs to.
+    pub fn package_name(&self) -> Option<String> {
+        self.package_id
+            .parse::<PackageId>()
+            .ok()
+            .map(|package_id| package_id.name)
+    }
+
+    /// Returns the file built for the given crate type, E.g. "cdylib" or "rlib".
+    ///
+    /// Binaries, tests and benchmarks are better found through [executable](Artifact::executable).
+    pub fn file(&self, crate_type: &str) -> Option<&Path> {
+        let suffixes: &[&str] = match crate_type {
+            "lib" | "rlib" => &[".rlib"],
+            "dylib" | "cdylib" | "proc-macro" => &[".so", ".dylib", ".dll", ".wasm"],
+            "staticlib" => &[".a", ".lib"],
+            "bin" => return self.executable.as_deref(),
+            _ => return None,
+        };
+
+        self.filenames.iter().map(PathBuf::as_path).find(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+            // On MSVC a "cdylib" also comes with a "foo.dll.lib" import library, which isn't a static library.
+            if crate_type == "staticlib" && name.ends_with(".dll.lib") {
+                return false;
+            }
+
+            suffixes.iter().any(|suffix| name.ends_with(suffix))
+        })
+    }
+}
+
+impl BuildOutput {
+    /// Finds the artifact of a target of a package, E.g. the "lib" of "payload".
+    pub fn artifact(&self, package: &str, target: &str, kind: &TargetKind) -> Option<&Artifact> {
+        self.artifacts().find(|artifact| {
+            artifact.target.name == target
+                && artifact.target.kind.contains(kind)
+                && artifact.package_name().as_deref() == Some(package)
+        })
+    }
+
+    /// Returns the path of every built test executable, from unit tests, integration tests and benchmarks.
+    pub fn test_executables(&self) -> impl Iterator<Item = &Path> {
+        self.artifacts()
+            .filter(|artifact| artifact.profile.test)
+            .filter_map(|artifact| artifact.executable.as_deref())
+    }
+}
+
+/// Where cargo puts the outputs of a build, to find them without building.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactLayout {
+    pub target_directory: PathBuf,
+    /// The target triple passed with `--target`, [None] for the host.
+    pub target: Option<Triple>,
+    /// The name of the profile, E.g. "dev" or "release".
+    pub profile: String,
+}
+
+impl ArtifactLayout {
+    pub fn new<S: Into<String>>(metadata: &Metadata, target: Option<Triple>, profile: S) -> Self {
+        ArtifactLayout {
+            target_directory: metadata.target_directory.clone(),
+            target,
+            profile: profile.into(),
+        }
+    }
+
+    /// The directory the final outputs are copied to, E.g. "target/x86_64-unknown-linux-gnu/debug".
+    pub fn dir(&self) -> PathBuf {
+        let mut dir = self.target_directory.clone();
+
+        if let Some(target) = &self.target {
+            dir.push(target.to_string());
+        }
+
+        // The built-in profiles share directories, custom profiles use their name.
+        dir.push(match self.profile.as_str() {
+            "dev" | "test" => "debug",
+            "bench" => "release",
+            profile => profile,
+        });
+
+        dir
+    }
+
+    /// The directory every unit is built in, with a hash in its file name, E.g. "target/debug/deps".
+    pub fn deps_dir(&self) -> PathBuf {
+        self.dir().join("deps")
+    }
+
+    /// The layout of the host, where build scripts and procedural macros are built.
+    fn host(&self) -> ArtifactLayout {
+        ArtifactLayout {
+            target: None,
+            ..self.clone()
+        }
+    }
+
+    fn triple(&self) -> Triple {
+        self.target.clone().unwrap_or_else(Triple::host)
+    }
+
+    /// Predicts the paths of the files built for a target, after cargo copied them out of the "deps" directory,
+    /// E.g. "target/debug/libfoo.so" for a "cdylib" on Linux.
+    ///
+    /// Tests, benchmarks and build scripts are never copied. Tests and benchmarks can be found with
+    /// [find_hashed](Self::find_hashed), while build scripts are built in their own directory,
+    /// E.g. "target/debug/build/foo-0123456789abcdef/build-script-build", and aren't found by either.
+    pub fn predict(&self, target: &Target) -> Vec<PathBuf> {
+        if target.kind.iter().any(|kind| {
+            matches!(
+                kind,
+                TargetKind::Test | TargetKind::Bench | TargetKind::CustomBuild
+            )
+        }) {
+            return Vec::new();
+        }
+
+        let is_example = target.kind.contains(&TargetKind::Example);
+
+        target
+            .crate_types
+            .iter()
+            .filter_map(|crate_type| {
+                // Procedural macros run in the compiler, so they are built for the host.
+                let layout = if crate_type == "proc-macro" {
+                    self.host()
+                } else {
+                    self.clone()
+                };
+
+                let (prefix, suffix) = file_affixes(&layout.triple(), crate_type)?;
+
+                let name = if crate_type == "bin" {
+                    target.name.clone()
+                } else {
+                    crate_name(&target.name)
+                };
+
+                let dir = if is_example {
+                    layout.dir().join("examples")
+                } else {
+                    layout.dir()
+                };
+
+                Some(dir.join(format!("{prefix}{name}{suffix}")))
+            })
+            .collect()
+    }
+
+    /// Finds the files built for a target in the "deps" directory, or "examples" for examples, from the most to
+    /// the least recently modified.
+    ///
+    /// Their names end with a hash, E.g. "target/debug/deps/foo-0123456789abcdef", so several builds
+    /// of the same target can be found.
+    pub fn find_hashed(&self, target: &Target) -> Result<Vec<PathBuf>> {
+        let name = crate_name(&target.name);
+
+        let affixes: Vec<_> = target
+            .crate_types
+            .iter()
+            .filter_map(|crate_type| {
+                let layout = if crate_type == "proc-macro" {
+                    self.host()
+                } else {
+                    self.clone()
+                };
+
+                let affixes = file_affixes(&layout.triple(), crate_type)?;
+
+                Some((layout, affixes))
+            })
+            .collect();
+
+        let mut found = Vec::new();
+
+        for (layout, (prefix, suffix)) in affixes {
+            let deps_dir = if target.kind.contains(&TargetKind::Example) {
+                layout.dir().join("examples")
+            } else {
+                layout.deps_dir()
+            };
+
+            let entries = match fs::read_dir(&deps_dir) {
+                Ok(entries) => entries,
+                Err(error) if error.kind() == ErrorKind::NotFound => continue,
+                Err(error) => return Err(error.into()),
+            };
+
+            for entry in entries {
+                let entry = entry?;
+                let file_name = entry.file_name();
+                let file_name = file_name.to_string_lossy();
+
+                let hash = file_name
+                    .strip_prefix(prefix)
+                    .and_then(|rest| rest.strip_prefix(name.as_str()))
+                    .and_then(|rest| rest.strip_prefix('-'))
+                    .and_then(|rest| rest.strip_suffix(suffix));
+
+                if hash.is_some_and(is_hash) {
+                    let modified = entry.metadata()?.modified()?;
+                    found.push((modified, entry.path()));
+                }
+            }
+        }
+
+        found.sort_by_key(|(modified, _): &(SystemTime, PathBuf)| Reverse(*modified));
+        found.dedup_by(|a, b| a.1 == b.1);
+
+        Ok(found.into_iter().map(|(_, path)| path).collect())
+    }
+}
+
+/// Library file names use the crate name, with dashes replaced by underscores.
+fn crate_name(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+/// The hash cargo appends to file names is 16 hexadecimal digits.
+fn is_hash(hash: &str) -> bool {
+    hash.len() == 16 && hash.chars().all(|char| char.is_ascii_hexdigit())
+}
+
+/// The prefix and suffix of the file built for a crate type on the given platform.
+fn file_affixes(triple: &Triple, crate_type: &str) -> Option<(&'static str, &'static str)> {
+    let windows = triple.operating_system == OperatingSystem::Windows;
+    let wasm = matches!(
+        triple.architecture,
+        Architecture::Wasm32 | Architecture::Wasm64
+    );
+
+    let affixes = match crate_type {
+        "bin" => match () {
+            _ if windows => ("", ".exe"),
+            _ if triple.operating_system == OperatingSystem::Emscripten => ("", ".js"),
+            _ if wasm => ("", ".wasm"),
+            _ => ("", ""),
+        },
+        "lib" | "rlib" => ("lib", ".rlib"),
+        "dylib" | "cdylib" | "proc-macro" => match triple.binary_format {
+            _ if windows => ("", ".dll"),
+            _ if wasm => ("", ".wasm"),
+            BinaryFormat::Macho => ("lib", ".dylib"),
+            _ => ("lib", ".so"),
+        },
+        "staticlib" => match triple.environment {
+            Environment::Msvc => ("", ".lib"),
+            _ => ("lib", ".a"),
+        },
+        _ => return None,
+    };
+
+    Some(affixes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cargo::{home::temp_dir, message::ArtifactProfile, metadata::Edition};
+    use std::fs::File;
+    use std::time::Duration;
+
+    fn target(name: &str, kind: TargetKind, crate_types: &[&str]) -> Target {
+        Target {
+            kind: vec![kind],
+            crate_types: crate_types
+                .iter()
+                .map(|crate_type| crate_type.to_string())
+                .collect(),
+            name: name.to_string(),
+            src_path: PathBuf::from("/ws/foo/src/lib.rs"),
+            edition: Edition::E2021,
+            required_features: None,
+            doc: true,
+            doctest: true,
+            test: true,
+        }
+    }
+
+    fn layout(target_directory: &Path, target: Option<&str>) -> ArtifactLayout {
+        ArtifactLayout {
+            target_directory: target_directory.to_path_buf(),
+            target: target.map(|target| target.parse().unwrap()),
+            profile: "dev".to_string(),
+        }
+    }
+
+    fn affixes(triple: &str, crate_type: &str) -> Option<(&'static str, &'static str)> {
+        file_affixes(&triple.parse().unwrap(), crate_type)
+    }
+
+    #[test]
+    fn affixes_by_platform() {
+        let linux = "x86_64-unknown-linux-gnu";
+        assert_eq!(affixes(linux, "bin"), Some(("", "")));
+        assert_eq!(affixes(linux, "lib"), Some(("lib", ".rlib")));
+        assert_eq!(affixes(linux, "cdylib"), Some(("lib", ".so")));
+        assert_eq!(affixes(linux, "staticlib"), Some(("lib", ".a")));
+
+        let msvc = "x86_64-pc-windows-msvc";
+        assert_eq!(affixes(msvc, "bin"), Some(("", ".exe")));
+        assert_eq!(affixes(msvc, "rlib"), Some(("lib", ".rlib")));
+        assert_eq!(affixes(msvc, "proc-macro"), Some(("", ".dll")));
+        assert_eq!(affixes(msvc, "staticlib"), Some(("", ".lib")));
+
+        // MinGW uses the Unix naming for static libraries.
+        assert_eq!(
+            affixes("x86_64-pc-windows-gnu", "staticlib"),
+            Some(("lib", ".a"))
+        );
+
+        let macos = "aarch64-apple-darwin";
+        assert_eq!(affixes(macos, "bin"), Some(("", "")));
+        assert_eq!(affixes(macos, "dylib"), Some(("lib", ".dylib")));
+        assert_eq!(affixes(macos, "staticlib"), Some(("lib", ".a")));
+
+        let wasm = "wasm32-unknown-unknown";
+        assert_eq!(affixes(wasm, "bin"), Some(("", ".wasm")));
+        assert_eq!(affixes(wasm, "cdylib"), Some(("", ".wasm")));
+
+        assert_eq!(
+            affixes("wasm32-unknown-emscripten", "bin"),
+            Some(("", ".js"))
+        );
+
+        assert_eq!(affixes(linux, "example"), None);
+    }
+
+    #[test]
+    fn hashes() {
+        assert!(is_hash("0123456789abcdef"));
+        assert!(!is_hash("0123456789abcde"));
+        assert!(!is_hash("0123456789abcdeg"));
+        assert_eq!(crate_name("my-crate"), "my_crate");
+    }
+
+    #[test]
+    fn predictions() {
+        let cross = layout(Path::new("/ws/target"), Some("aarch64-pc-windows-msvc"));
+
+        assert_eq!(
+            cross.predict(&target(
+                "my-lib",
+                TargetKind::Lib,
+                &["lib", "cdylib", "staticlib"]
+            )),
+            [
+                "/ws/target/aarch64-pc-windows-msvc/debug/libmy_lib.rlib",
+                "/ws/target/aarch64-pc-windows-msvc/debug/my_lib.dll",
+                "/ws/target/aarch64-pc-windows-msvc/debug/my_lib.lib",
+            ]
+            .map(PathBuf::from)
+        );
+        assert_eq!(
+            cross.predict(&target("my-app", TargetKind::Bin, &["bin"])),
+            [PathBuf::from(
+                "/ws/target/aarch64-pc-windows-msvc/debug/my-app.exe"
+            )]
+        );
+        assert_eq!(
+            cross.predict(&target("demo", TargetKind::Example, &["bin"])),
+            [PathBuf::from(
+                "/ws/target/aarch64-pc-windows-msvc/debug/examples/demo.exe"
+            )]
+        );
+
+        // Procedural macros are built for the host, outside of the directory of the target.
+        let (prefix, suffix) = file_affixes(&Triple::host(), "proc-macro").unwrap();
+        assert_eq!(
+            cross.predict(&target("my-macro", TargetKind::ProcMacro, &["proc-macro"])),
+            [PathBuf::from(format!(
+                "/ws/target/debug/{prefix}my_macro{suffix}"
+            ))]
+        );
+
+        assert!(cross
+            .predict(&target("it", TargetKind::Test, &["bin"]))
+            .is_empty());
+        assert!(cross
+            .predict(&target(
+                "build-script-build",
+                TargetKind::CustomBuild,
+                &["bin"]
+            ))
+            .is_empty());
+    }
+
+    #[test]
+    fn hashed_files() {
+        let target_directory = temp_dir("artifacts");
+        let layout = layout(&target_directory, Some("x86_64-unknown-linux-gnu"));
+        let deps_dir = layout.deps_dir();
+        fs::create_dir_all(&deps_dir).unwrap();
+
+        let files = [
+            ("foo-0000000000000001", 1),
+            ("foo-0000000000000002", 3),
+            ("libfoo-0000000000000003.rlib", 2),
+            // Not the outputs of the target.
+            ("foo-0000000000000001.d", 4),
+            ("foo-bar-0000000000000004", 4),
+            ("foo-notahash", 4),
+        ];
+
+        for (name, modified) in files {
+            File::create(deps_dir.join(name))
+                .unwrap()
+                .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(modified))
+                .unwrap();
+        }
+
+        let tests = layout.find_hashed(&target("foo", TargetKind::Test, &["bin"]));
+        let libs = layout.find_hashed(&target("foo", TargetKind::Lib, &["lib"]));
+        let examples = layout.find_hashed(&target("foo", TargetKind::Example, &["bin"]));
+
+        fs::remove_dir_all(&target_directory).unwrap();
+
+        assert_eq!(
+            tests.unwrap(),
+            [
+                deps_dir.join("foo-0000000000000002"),
+                deps_dir.join("foo-0000000000000001"),
+            ]
+        );
+        assert_eq!(
+            libs.unwrap(),
+            [deps_dir.join("libfoo-0000000000000003.rlib")]
+        );
+        assert!(examples.unwrap().is_empty());
+    }
+
+    #[test]
+    fn import_libraries() {
+        let artifact = Artifact {
+            package_id: "path+file:///ws/foo#0.1.0".to_string(),
+            manifest_path: PathBuf::from("/ws/foo/Cargo.toml"),
+            target: target("foo", TargetKind::Lib, &["cdylib", "staticlib"]),
+            profile: ArtifactProfile {
+                opt_level: "0".to_string(),
+                debuginfo: None,
+                debug_assertions: true,
+                overflow_checks: true,
+                test: false,
+            },
+            features: Vec::new(),
+            filenames: ["foo.dll", "foo.dll.lib", "foo.lib", "foo.pdb"]
+                .map(|name| PathBuf::from("/ws/target/debug").join(name))
+                .to_vec(),
+            executable: None,
+            fresh: false,
+        };
+
+        assert_eq!(
+            artifact.file("cdylib"),
+            Some(Path::new("/ws/target/debug/foo.dll"))
+        );
+        assert_eq!(
+            artifact.file("staticlib"),
+            Some(Path::new("/ws/target/debug/foo.lib"))
+        );
+        assert_eq!(artifact.package_name().as_deref(), Some("foo"));
+    }
+}
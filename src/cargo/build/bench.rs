@@ -0,0 +1,469 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::BTreeMap, fs, io::ErrorKind, path::Path, time::SystemTime};
+
+use super::{run_error, BenchConfig, BuildOutput};
+use crate::cargo::{Cargo, MetadataConfig, Result};
+
+/// The harness that measured a benchmark.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum BenchHarness {
+    /// The built-in `#[bench]` harness.
+    Libtest,
+    /// The criterion crate.
+    Criterion,
+}
+
+/// The measurement of a single benchmark.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BenchResult {
+    /// The name of the benchmark, E.g. "tests::bench_parse" or "parse/small".
+    pub name: String,
+    pub harness: BenchHarness,
+    /// The time of one iteration, in nanoseconds.
+    pub time: f64,
+    /// How much the time varies, in nanoseconds.
+    ///
+    /// This is the "+/-" range reported by libtest, or the standard deviation for criterion.
+    pub deviation: f64,
+}
+
+impl BenchResult {
+    /// Parses the results printed by the libtest harness, E.g. "test bench_add ... bench: 1,234 ns/iter (+/- 56)".
+    ///
+    /// Other lines are skipped.
+    pub fn parse_libtest(output: &str) -> Vec<BenchResult> {
+        output.lines().filter_map(parse_libtest_line).collect()
+    }
+
+    /// Reads the estimates criterion saved in the given directory, usually "target/criterion".
+    ///
+    /// When `since` is set, only benchmarks measured after it are returned.
+    pub fn read_criterion(dir: &Path, since: Option<SystemTime>) -> Result<Vec<BenchResult>> {
+        let mut results = Vec::new();
+        read_criterion_dir(dir, dir, since, &mut results)?;
+
+        results.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(results)
+    }
+}
+
+fn parse_libtest_line(line: &str) -> Option<BenchResult> {
+    let (name, rest) = line.strip_prefix("test ")?.split_once(" ... bench:")?;
+    let (time, rest) = rest.trim().split_once(" ns/iter")?;
+    let deviation = rest.trim().strip_prefix("(+/- ")?.strip_suffix(')')?;
+
+    Some(BenchResult {
+        name: name.trim().to_string(),
+        harness: BenchHarness::Libtest,
+        time: parse_number(time)?,
+        deviation: parse_number(deviation)?,
+    })
+}
+
+/// Parses a number printed with thousands separators, E.g. "1,234.5".
+fn parse_number(number: &str) -> Option<f64> {
+    number.trim().replace(',', "").parse().ok()
+}
+
+#[derive(Deserialize)]
+struct Estimates {
+    mean: Estimate,
+    slope: Option<Estimate>,
+    std_dev: Estimate,
+}
+
+#[derive(Deserialize)]
+struct Estimate {
+    point_estimate: f64,
+}
+
+fn read_criterion_dir(
+    root: &Path,
+    dir: &Path,
+    since: Option<SystemTime>,
+    results: &mut Vec<BenchResult>,
+) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(error.into()),
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+
+        // Criterion keeps the HTML reports next to the measurements.
+        if !entry.file_type()?.is_dir() || entry.file_name() == "report" {
+            continue;
+        }
+
+        let estimates_path = path.join("new").join("estimates.json");
+
+        let modified = match fs::metadata(&estimates_path) {
+            Ok(metadata) => metadata.modified()?,
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                read_criterion_dir(root, &path, since, results)?;
+                continue;
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        if since.is_some_and(|since| modified < since) {
+            continue;
+        }
+
+        let estimates: Estimates = serde_json::from_slice(&fs::read(&estimates_path)?)?;
+
+        // The full id is only stored in the benchmark description, the directory names are sanitized.
+        let name = fs::read(path.join("new").join("benchmark.json"))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok())
+            .and_then(|benchmark| benchmark.get("full_id")?.as_str().map(String::from))
+            .unwrap_or_else(|| {
+                path.strip_prefix(root)
+                    .unwrap_or(&path)
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            });
+
+        results.push(BenchResult {
+            name,
+            harness: BenchHarness::Criterion,
+            time: estimates.slope.unwrap_or(estimates.mean).point_estimate,
+            deviation: estimates.std_dev.point_estimate,
+        });
+    }
+
+    Ok(())
+}
+
+/// The result of a successful `cargo bench`.
+#[derive(Debug, Clone)]
+pub struct BenchOutput {
+    /// The results of every benchmark that ran, from both libtest and criterion.
+    pub results: Vec<BenchResult>,
+    pub build: BuildOutput,
+}
+
+/// A benchmark measured in both result sets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchChange {
+    pub name: String,
+    /// The baseline time, in nanoseconds.
+    pub baseline: f64,
+    /// The current time, in nanoseconds.
+    pub current: f64,
+}
+
+impl BenchChange {
+    /// The relative change of the time, E.g. 0.1 when 10% slower or -0.5 when twice as fast.
+    pub fn ratio(&self) -> f64 {
+        if self.baseline == 0.0 {
+            if self.current == 0.0 {
+                0.0
+            } else {
+                f64::INFINITY
+            }
+        } else {
+            self.current / self.baseline - 1.0
+        }
+    }
+}
+
+/// The comparison of two sets of benchmark results, matched by name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BenchComparison {
+    pub changes: Vec<BenchChange>,
+    /// Benchmarks only in the current results.
+    pub added: Vec<String>,
+    /// Benchmarks only in the baseline results.
+    pub removed: Vec<String>,
+}
+
+impl BenchComparison {
+    pub fn new(baseline: &[BenchResult], current: &[BenchResult]) -> Self {
+        let mut current: BTreeMap<&str, &BenchResult> = current
+            .iter()
+            .map(|result| (result.name.as_str(), result))
+            .collect();
+
+        let mut comparison = BenchComparison::default();
+
+        for baseline in baseline {
+            match current.remove(baseline.name.as_str()) {
+                Some(current) => comparison.changes.push(BenchChange {
+                    name: baseline.name.clone(),
+                    baseline: baseline.time,
+                    current: current.time,
+                }),
+                None => comparison.removed.push(baseline.name.clone()),
+            }
+        }
+
+        comparison
+            .added
+            .extend(current.into_keys().map(String::from));
+
+        comparison
+    }
+
+    /// Returns the benchmarks that got slower by more than the threshold, E.g. 0.05 for 5%.
+    pub fn regressions(&self, threshold: f64) -> Vec<&BenchChange> {
+        self.changes
+            .iter()
+            .filter(|change| change.ratio() > threshold)
+            .collect()
+    }
+
+    /// Returns the benchmarks that got faster by more than the threshold, E.g. 0.05 for 5%.
+    pub fn improvements(&self, threshold: f64) -> Vec<&BenchChange> {
+        self.changes
+            .iter()
+            .filter(|change| change.ratio() < -threshold)
+            .collect()
+    }
+}
+
+impl Cargo {
+    /// Runs `cargo bench` and collects the results.
+    ///
+    /// Results of the libtest harness are parsed from its output, while criterion results are read
+    /// from the estimates it saves in the "criterion" directory of the target directory.
+    ///
+    /// Build failures are reported as [ParsingError::Build](crate::cargo::ParsingError::Build), while
+    /// benchmarks that panic or exit unsuccessfully are reported as
+    /// [ParsingError::Program](crate::cargo::ParsingError::Program).
+    pub fn bench(&mut self, config: BenchConfig) -> Result<BenchOutput> {
+        let started = SystemTime::now();
+
+        let mut command = self.build_command("bench", &config.build_config());
+
+        if config.no_fail_fast {
+            command.arg("--no-fail-fast");
+        }
+
+        if config.filter.is_some() || !config.args.is_empty() {
+            command.arg("--");
+        }
+
+        if let Some(filter) = &config.filter {
+            command.arg(filter);
+        }
+
+        command.args(&config.args);
+
+        let (output, messages) = self.run_build(&mut command)?;
+
+        if !output.status.success() {
+            return Err(run_error(&messages, output));
+        }
+
+        let mut results = BenchResult::parse_libtest(&String::from_utf8_lossy(&output.stdout));
+
+        let metadata = self.metadata(MetadataConfig {
+            manifest_path: config.manifest_path.clone(),
+            no_deps: true,
+            ..Default::default()
+        })?;
+
+        // The metadata comes from cargo on the host even with a driver, so the path is already a host path.
+        results.extend(BenchResult::read_criterion(
+            &metadata.target_directory.join("criterion"),
+            Some(started),
+        )?);
+
+        Ok(BenchOutput {
+            results,
+            build: BuildOutput {
+                messages,
+                stderr: output.stderr,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cargo::home::temp_dir;
+    use serde_json::json;
+    use std::{fs::File, time::Duration};
+
+    fn result(name: &str, time: f64) -> BenchResult {
+        BenchResult {
+            name: name.to_string(),
+            harness: BenchHarness::Libtest,
+            time,
+            deviation: 0.0,
+        }
+    }
+
+    #[test]
+    fn libtest() {
+        let output = "\n\
+            running 3 tests\n\
+            test tests::it_works ... ignored\n\
+            test tests::bench_add ... bench:       1,234 ns/iter (+/- 56)\n\
+            test tests::bench_fast ... bench:          12.50 ns/iter (+/- 0.30)\n\
+            test tests::bench_slow ... bench: 1,234,567.89 ns/iter (+/- 1,000)\n\
+            \n\
+            test result: ok. 0 passed; 0 failed; 1 ignored; 0 measured; 3 filtered out\n";
+
+        assert_eq!(
+            BenchResult::parse_libtest(output),
+            [
+                BenchResult {
+                    deviation: 56.0,
+                    ..result("tests::bench_add", 1234.0)
+                },
+                BenchResult {
+                    deviation: 0.3,
+                    ..result("tests::bench_fast", 12.5)
+                },
+                BenchResult {
+                    deviation: 1000.0,
+                    ..result("tests::bench_slow", 1234567.89)
+                },
+            ]
+        );
+
+        assert_eq!(parse_libtest_line("test tests::it_works ... ok"), None);
+        assert_eq!(
+            parse_libtest_line("test b ... bench: many ns/iter (+/- 1)"),
+            None
+        );
+    }
+
+    /// Writes the estimates criterion saves for a benchmark, with an optional description.
+    fn write_estimates(dir: &Path, slope: Option<f64>, full_id: Option<&str>) {
+        let new = dir.join("new");
+        fs::create_dir_all(&new).unwrap();
+
+        let estimate = |point_estimate: f64| json!({ "point_estimate": point_estimate });
+        fs::write(
+            new.join("estimates.json"),
+            json!({
+                "mean": estimate(120.0),
+                "median": estimate(110.0),
+                "slope": slope.map(estimate),
+                "std_dev": estimate(4.0),
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        if let Some(full_id) = full_id {
+            fs::write(
+                new.join("benchmark.json"),
+                json!({ "group_id": "parse", "full_id": full_id }).to_string(),
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn criterion() {
+        let root = temp_dir("criterion");
+
+        write_estimates(
+            &root.join("parse/small input"),
+            Some(100.0),
+            Some("parse/small input"),
+        );
+        write_estimates(&root.join("group/sub/case"), None, None);
+        write_estimates(&root.join("report/fake"), Some(1.0), None);
+        write_estimates(&root.join("stale"), Some(1.0), None);
+        fs::create_dir_all(root.join("parse/report")).unwrap();
+        fs::write(root.join("parse/report/index.html"), "").unwrap();
+
+        let since = SystemTime::now() - Duration::from_secs(3600);
+        File::options()
+            .write(true)
+            .open(root.join("stale/new/estimates.json"))
+            .unwrap()
+            .set_modified(since - Duration::from_secs(3600))
+            .unwrap();
+
+        let all = BenchResult::read_criterion(&root, None);
+        let recent = BenchResult::read_criterion(&root, Some(since));
+        let missing = BenchResult::read_criterion(&root.join("missing"), None);
+
+        fs::remove_dir_all(&root).unwrap();
+
+        let criterion = |name: &str, time: f64| BenchResult {
+            name: name.to_string(),
+            harness: BenchHarness::Criterion,
+            time,
+            deviation: 4.0,
+        };
+
+        // The slope is preferred to the mean, and the directory names are used without a description.
+        assert_eq!(
+            recent.unwrap(),
+            [
+                criterion("group/sub/case", 120.0),
+                criterion("parse/small input", 100.0),
+            ]
+        );
+        assert_eq!(
+            all.unwrap()
+                .iter()
+                .map(|result| result.name.as_str())
+                .collect::<Vec<_>>(),
+            ["group/sub/case", "parse/small input", "stale"]
+        );
+        assert!(missing.unwrap().is_empty());
+    }
+
+    #[test]
+    fn comparison() {
+        let baseline = [
+            result("slower", 100.0),
+            result("faster", 100.0),
+            result("same", 100.0),
+            result("removed", 1.0),
+            result("from_zero", 0.0),
+            result("zero", 0.0),
+        ];
+        let current = [
+            result("added", 1.0),
+            result("zero", 0.0),
+            result("from_zero", 5.0),
+            result("same", 102.0),
+            result("faster", 50.0),
+            result("slower", 110.0),
+        ];
+
+        let comparison = BenchComparison::new(&baseline, &current);
+        let names = |changes: Vec<&BenchChange>| -> Vec<String> {
+            changes.iter().map(|change| change.name.clone()).collect()
+        };
+
+        assert_eq!(comparison.added, ["added"]);
+        assert_eq!(comparison.removed, ["removed"]);
+        assert_eq!(comparison.changes.len(), 5);
+
+        assert_eq!(names(comparison.regressions(0.05)), ["slower", "from_zero"]);
+        assert_eq!(names(comparison.improvements(0.05)), ["faster"]);
+
+        let ratio = |name: &str| {
+            comparison
+                .changes
+                .iter()
+                .find(|change| change.name == name)
+                .unwrap()
+                .ratio()
+        };
+
+        assert!((ratio("slower") - 0.1).abs() < 1e-9);
+        assert_eq!(ratio("faster"), -0.5);
+        assert_eq!(ratio("from_zero"), f64::INFINITY);
+        assert_eq!(ratio("zero"), 0.0);
+    }
+}
@@ -0,0 +1,131 @@
+use std::{ffi::OsString, path::PathBuf};
+use target_lexicon::Triple;
+
+use crate::cargo::Features;
+
+#[derive(Debug, Default, Clone)]
+pub struct BuildConfig {
+    /// Packages to build, passed as `-p`. Builds the current package if empty.
+    pub packages: Vec<String>,
+    /// Build every package in the workspace.
+    pub workspace: bool,
+    /// Packages to exclude when building the whole workspace.
+    pub exclude: Vec<String>,
+    /// Targets to build. Builds the default targets if empty.
+    pub targets: Vec<CompileTarget>,
+    pub features: Option<Features>,
+    /// Build with the release profile.
+    pub release: bool,
+    /// Build with the given profile.
+    pub profile: Option<String>,
+    /// Build for the given target triple.
+    pub target: Option<Triple>,
+    pub manifest_path: Option<PathBuf>,
+    /// Build even if the `rust-version` of a package is newer than the toolchain, passed as `--ignore-rust-version`.
+    pub ignore_rust_version: bool,
+    /// Emit a `timing-info` message for every unit, passed as `--timings=json`.
+    ///
+    /// This is unstable and only supported by nightly versions of cargo.
+    pub timings: bool,
+}
+
+/// A target selection passed to the build commands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileTarget {
+    /// The library, `--lib`.
+    Lib,
+    /// A binary, `--bin <name>`.
+    Bin(String),
+    /// Every binary, `--bins`.
+    Bins,
+    /// An example, `--example <name>`.
+    Example(String),
+    /// Every example, `--examples`.
+    Examples,
+    /// An integration test, `--test <name>`.
+    Test(String),
+    /// Every integration test, `--tests`.
+    Tests,
+    /// A benchmark, `--bench <name>`.
+    Bench(String),
+    /// Every benchmark, `--benches`.
+    Benches,
+    /// Every target, `--all-targets`.
+    All,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct RunConfig {
+    /// The package to run, passed as `-p`.
+    pub package: Option<String>,
+    /// The binary or example to run.
+    ///
+    /// Defaults to the `default-run` binary of the package, or its only binary.
+    pub target: Option<RunTarget>,
+    /// Arguments passed to the program.
+    pub args: Vec<OsString>,
+    pub features: Option<Features>,
+    /// Build with the release profile.
+    pub release: bool,
+    /// Build with the given profile.
+    pub profile: Option<String>,
+    /// Build for the given target triple, running the program with its `runner` if configured.
+    pub target_triple: Option<Triple>,
+    pub manifest_path: Option<PathBuf>,
+    /// Pipe the program's stdin, stdout and stderr instead of inheriting them.
+    ///
+    /// The program runs in its own process group either way, so on unix it doesn't receive the signals of
+    /// the terminal, E.g. Ctrl-C, and stops when reading from it.
+    pub piped: bool,
+}
+
+/// The kind of target `cargo run` executes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunTarget {
+    /// A binary, `--bin <name>`.
+    Bin(String),
+    /// An example, `--example <name>`.
+    Example(String),
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct BenchConfig {
+    /// Packages to benchmark, passed as `-p`. Benchmarks the current package if empty.
+    pub packages: Vec<String>,
+    /// Benchmark every package in the workspace.
+    pub workspace: bool,
+    /// Packages to exclude when benchmarking the whole workspace.
+    pub exclude: Vec<String>,
+    /// Targets to benchmark. Runs the default benchmark targets if empty.
+    pub targets: Vec<CompileTarget>,
+    pub features: Option<Features>,
+    /// Build with the given profile instead of "bench".
+    pub profile: Option<String>,
+    /// Build for the given target triple.
+    pub target: Option<Triple>,
+    pub manifest_path: Option<PathBuf>,
+    /// Only run the benchmarks whose name contains the filter.
+    pub filter: Option<String>,
+    /// Arguments passed to the benchmark harness, after the filter.
+    pub args: Vec<OsString>,
+    /// Run every benchmark target even if one of them fails.
+    pub no_fail_fast: bool,
+}
+
+impl BenchConfig {
+    pub(crate) fn build_config(&self) -> BuildConfig {
+        BuildConfig {
+            packages: self.packages.clone(),
+            workspace: self.workspace,
+            exclude: self.exclude.clone(),
+            targets: self.targets.clone(),
+            features: self.features.clone(),
+            release: false,
+            profile: self.profile.clone(),
+            target: self.target.clone(),
+            manifest_path: self.manifest_path.clone(),
+            ignore_rust_version: false,
+            timings: false,
+        }
+    }
+}
@@ -0,0 +1,429 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    path::PathBuf,
+};
+
+use super::BuildConfig;
+use crate::cargo::{metadata::Package, Cargo, Features, MetadataConfig, ParsingError, Result};
+
+/// How the feature combinations are generated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MatrixMode {
+    /// Every combination of features, up to the [depth](FeatureMatrixConfig::depth).
+    #[default]
+    Powerset,
+    /// No features, each feature on its own, and every feature at once.
+    EachFeature,
+}
+
+/// The command run for each feature combination.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MatrixCommand {
+    #[default]
+    Check,
+    Build,
+    Test,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FeatureMatrixConfig {
+    /// The workspace members to run, every member if empty.
+    pub packages: Vec<String>,
+    pub mode: MatrixMode,
+    pub command: MatrixCommand,
+    /// The maximum number of features enabled together, not counting the [always](Self::always) enabled ones.
+    pub depth: Option<usize>,
+    /// Features that are never enabled.
+    pub exclude: Vec<String>,
+    /// Features that are only enabled together, counting as a single feature.
+    ///
+    /// Like [always](Self::always), each package only uses the features it defines.
+    pub groups: Vec<Vec<String>>,
+    /// Features enabled in every combination.
+    ///
+    /// Each package only enables the ones it defines, but every feature has to be defined by one of the
+    /// selected packages.
+    pub always: Vec<String>,
+    pub manifest_path: Option<PathBuf>,
+}
+
+/// A set of features of a package, enabled without the default features.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeatureCombination {
+    /// The name of the package.
+    pub package: String,
+    pub features: Vec<String>,
+}
+
+/// A feature combination that failed.
+#[derive(Debug, Clone)]
+pub struct MatrixFailure {
+    pub combination: FeatureCombination,
+    /// Whether the combination compiled but its tests failed, only with [MatrixCommand::Test].
+    pub tests_failed: bool,
+    /// The errors reported by the compiler, empty if the tests failed.
+    pub diagnostics: Vec<String>,
+    /// The output of the tests, empty if the combination failed to compile.
+    pub stdout: Vec<u8>,
+    /// The stderr output of cargo.
+    pub stderr: Vec<u8>,
+}
+
+/// The result of running every feature combination.
+#[derive(Debug, Clone, Default)]
+pub struct MatrixReport {
+    /// Every combination that ran.
+    pub combinations: Vec<FeatureCombination>,
+    pub failures: Vec<MatrixFailure>,
+}
+
+impl MatrixReport {
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl FeatureMatrixConfig {
+    /// Generates the feature combinations of a package.
+    ///
+    /// Features include the optional dependencies that don't have an explicit feature. Combinations that end up
+    /// enabling the same features, E.g. because one feature enables another, are only generated once.
+    /// Features of the [groups](Self::groups) and [always](Self::always) the package doesn't define are left out.
+    pub fn combinations(&self, package: &Package) -> Vec<Vec<String>> {
+        let features = package_features(package);
+        let defined = |feature: &&String| features.contains_key(feature.as_str());
+
+        let grouped: HashSet<&str> = self.groups.iter().flatten().map(String::as_str).collect();
+        let always: Vec<String> = self.always.iter().filter(defined).cloned().collect();
+
+        // Each unit is either a group or a single feature, and is enabled as a whole.
+        let mut units: Vec<Vec<String>> = self
+            .groups
+            .iter()
+            .filter(|group| !group.iter().any(|feature| self.exclude.contains(feature)))
+            .map(|group| group.iter().filter(defined).cloned().collect::<Vec<_>>())
+            .filter(|group| !group.is_empty())
+            .collect();
+
+        units.extend(
+            features
+                .keys()
+                .filter(|feature| {
+                    !grouped.contains(feature.as_str())
+                        && !self.exclude.contains(feature)
+                        && !always.contains(feature)
+                })
+                .map(|feature| vec![feature.clone()]),
+        );
+
+        let selections: Vec<Vec<usize>> = match self.mode {
+            MatrixMode::Powerset => powerset(units.len(), self.depth.unwrap_or(units.len())),
+            MatrixMode::EachFeature => {
+                let mut selections = vec![Vec::new()];
+                selections.extend((0..units.len()).map(|unit| vec![unit]));
+                selections.push((0..units.len()).collect());
+                selections
+            }
+        };
+
+        let mut seen = HashSet::new();
+        let mut combinations = Vec::new();
+
+        for selection in selections {
+            let mut combination: Vec<String> = always.clone();
+            combination.extend(
+                selection
+                    .iter()
+                    .flat_map(|&unit| units[unit].iter().cloned()),
+            );
+
+            if seen.insert(enabled_features(&features, &combination)) {
+                combinations.push(combination);
+            }
+        }
+
+        combinations
+    }
+}
+
+/// The features of a package, including the implicit features of optional dependencies.
+fn package_features(package: &Package) -> BTreeMap<String, Vec<String>> {
+    let mut features: BTreeMap<String, Vec<String>> = package
+        .features
+        .iter()
+        .map(|(name, values)| (name.clone(), values.clone()))
+        .collect();
+
+    // An optional dependency only gets an implicit feature when no feature refers to it with "dep:".
+    let explicit: HashSet<&str> = package
+        .features
+        .values()
+        .flatten()
+        .filter_map(|value| value.strip_prefix("dep:"))
+        .collect();
+
+    for dependency in package
+        .dependencies
+        .iter()
+        .filter(|dependency| dependency.optional)
+    {
+        let name = dependency.rename.as_ref().unwrap_or(&dependency.name);
+
+        if !explicit.contains(name.as_str()) {
+            features
+                .entry(name.clone())
+                .or_insert_with(|| vec![format!("dep:{name}")]);
+        }
+    }
+
+    features
+}
+
+/// Every feature enabled by a combination, following the features each one enables.
+fn enabled_features(
+    features: &BTreeMap<String, Vec<String>>,
+    combination: &[String],
+) -> BTreeSet<String> {
+    let mut enabled = BTreeSet::new();
+    let mut pending: Vec<&str> = combination.iter().map(String::as_str).collect();
+
+    while let Some(feature) = pending.pop() {
+        if !enabled.insert(feature.to_string()) {
+            continue;
+        }
+
+        // Values like "dep:name" and "name/feature" enable dependencies, which only matter through their feature,
+        // while "name?/feature" doesn't enable the dependency at all.
+        for value in features.get(feature).into_iter().flatten() {
+            let value = value.split('/').next().unwrap_or(value);
+
+            if !value.ends_with('?') && !value.starts_with("dep:") && features.contains_key(value) {
+                pending.push(value);
+            }
+        }
+    }
+
+    enabled
+}
+
+/// Every subset of `0..len` with at most `depth` elements, from the smallest to the largest.
+fn powerset(len: usize, depth: usize) -> Vec<Vec<usize>> {
+    let mut subsets = vec![Vec::new()];
+    let mut previous = vec![Vec::new()];
+
+    for _ in 0..depth.min(len) {
+        // Extending each subset of the previous size with a larger element avoids duplicates.
+        previous = previous
+            .iter()
+            .flat_map(|subset: &Vec<usize>| {
+                let start = subset.last().map_or(0, |last| last + 1);
+                (start..len).map(move |element| {
+                    let mut subset = subset.clone();
+                    subset.push(element);
+                    subset
+                })
+            })
+            .collect();
+
+        subsets.extend(previous.iter().cloned());
+    }
+
+    subsets
+}
+
+impl Cargo {
+    /// Generates the feature combinations of every selected workspace member.
+    ///
+    /// Fails if a feature of the [groups](FeatureMatrixConfig::groups) or [always](FeatureMatrixConfig::always)
+    /// isn't defined by any selected member, which is usually a typo.
+    pub fn feature_combinations(
+        &mut self,
+        config: &FeatureMatrixConfig,
+    ) -> Result<Vec<FeatureCombination>> {
+        let metadata = self.metadata(MetadataConfig {
+            manifest_path: config.manifest_path.clone(),
+            no_deps: true,
+            ..Default::default()
+        })?;
+
+        let packages: Vec<&Package> = metadata
+            .workspace_packages()
+            .filter(|package| config.packages.is_empty() || config.packages.contains(&package.name))
+            .collect();
+
+        let package_features: Vec<_> = packages
+            .iter()
+            .map(|package| package_features(package))
+            .collect();
+
+        if let Some(unknown) =
+            config
+                .groups
+                .iter()
+                .flatten()
+                .chain(&config.always)
+                .find(|feature| {
+                    !package_features
+                        .iter()
+                        .any(|features| features.contains_key(feature.as_str()))
+                })
+        {
+            return Err(ParsingError::Selection(format!(
+                "feature `{unknown}` is not defined by any selected package"
+            )));
+        }
+
+        Ok(packages
+            .into_iter()
+            .flat_map(|package| {
+                config
+                    .combinations(package)
+                    .into_iter()
+                    .map(|features| FeatureCombination {
+                        package: package.name.clone(),
+                        features,
+                    })
+            })
+            .collect())
+    }
+
+    /// Runs the [command](FeatureMatrixConfig::command) for every feature combination,
+    /// collecting the combinations that fail to compile or whose tests fail.
+    pub fn feature_matrix(&mut self, config: FeatureMatrixConfig) -> Result<MatrixReport> {
+        let mut report = MatrixReport::default();
+
+        for combination in self.feature_combinations(&config)? {
+            let build_config = BuildConfig {
+                packages: vec![combination.package.clone()],
+                features: Some(Features::OnlyFeatures(combination.features.clone())),
+                manifest_path: config.manifest_path.clone(),
+                ..Default::default()
+            };
+
+            let result = match config.command {
+                MatrixCommand::Check => self.check(build_config),
+                MatrixCommand::Build => self.build(build_config),
+                MatrixCommand::Test => self.test(build_config),
+            };
+
+            match result {
+                Ok(_) => {}
+                Err(ParsingError::Build {
+                    diagnostics,
+                    stderr,
+                }) => report.failures.push(MatrixFailure {
+                    combination: combination.clone(),
+                    tests_failed: false,
+                    diagnostics,
+                    stdout: Vec::new(),
+                    stderr,
+                }),
+                Err(ParsingError::Program { stdout, stderr, .. }) => {
+                    report.failures.push(MatrixFailure {
+                        combination: combination.clone(),
+                        tests_failed: true,
+                        diagnostics: Vec::new(),
+                        stdout,
+                        stderr,
+                    })
+                }
+                Err(error) => return Err(error),
+            }
+
+            report.combinations.push(combination);
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn package(features: &[(&str, &[&str])]) -> Package {
+        Package {
+            features: features
+                .iter()
+                .map(|(name, enables)| {
+                    let enables = enables.iter().map(ToString::to_string).collect();
+                    (name.to_string(), enables)
+                })
+                .collect(),
+            ..Package::fixture("a")
+        }
+    }
+
+    #[test]
+    fn undefined_features() {
+        let package = package(&[("std", &[]), ("serde", &[]), ("json", &["serde"])]);
+        let config = FeatureMatrixConfig {
+            mode: MatrixMode::EachFeature,
+            groups: vec![vec!["serde".to_string(), "derive".to_string()]],
+            always: vec!["std".to_string(), "alloc".to_string()],
+            ..Default::default()
+        };
+
+        // "derive" and "alloc" belong to another package, and enabling every feature is the same as "json".
+        assert_eq!(
+            config.combinations(&package),
+            [vec!["std"], vec!["std", "serde"], vec!["std", "json"]]
+        );
+    }
+
+    #[test]
+    fn test_failures() {
+        let dir = crate::cargo::home::temp_dir("feature-matrix");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"matrix\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+             [features]\nfailing = []\nbroken = []\n\n[workspace]\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("src").join("lib.rs"),
+            "#[cfg(feature = \"broken\")]\ncompile_error!(\"broken\");\n\n\
+             #[test]\nfn failing() {\n    assert!(!cfg!(feature = \"failing\"));\n}\n",
+        )
+        .unwrap();
+
+        let report = Cargo::new().feature_matrix(FeatureMatrixConfig {
+            mode: MatrixMode::EachFeature,
+            command: MatrixCommand::Test,
+            manifest_path: Some(dir.join("Cargo.toml")),
+            ..Default::default()
+        });
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        let report = report.unwrap();
+        let failures: Vec<(Vec<&str>, bool)> = report
+            .failures
+            .iter()
+            .map(|failure| {
+                let features = failure.combination.features.iter().map(String::as_str);
+                (features.collect(), failure.tests_failed)
+            })
+            .collect();
+
+        // Every feature at once doesn't compile either.
+        assert_eq!(report.combinations.len(), 4);
+        assert_eq!(
+            failures,
+            [
+                (vec!["broken"], false),
+                (vec!["failing"], true),
+                (vec!["broken", "failing"], false),
+            ]
+        );
+
+        let broken = &report.failures[0];
+        assert!(broken.diagnostics[0].contains("broken"));
+
+        let failing = &report.failures[1];
+        assert!(failing.diagnostics.is_empty());
+        assert!(String::from_utf8_lossy(&failing.stdout).contains("test failing ... FAILED"));
+    }
+}
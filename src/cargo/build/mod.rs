@@ -0,0 +1,282 @@
+use std::{
+    path::Path,
+    process::{Command, Output},
+    slice,
+};
+
+use super::{
+    message::{Artifact, Diagnostic, DiagnosticLevel, Message},
+    process::Stream,
+    Cargo, ParsingError, Result, TimingReport, UnitGraph,
+};
+
+mod artifacts;
+mod bench;
+mod config;
+mod matrix;
+mod observer;
+mod run;
+pub use artifacts::ArtifactLayout;
+pub use bench::{BenchChange, BenchComparison, BenchHarness, BenchOutput, BenchResult};
+pub use config::{BenchConfig, BuildConfig, CompileTarget, RunConfig, RunTarget};
+pub use matrix::{
+    FeatureCombination, FeatureMatrixConfig, MatrixCommand, MatrixFailure, MatrixMode, MatrixReport,
+};
+pub use observer::BuildObserver;
+pub(crate) use observer::SharedObserver;
+pub(crate) use run::select_package;
+pub use run::RunHandle;
+
+/// The result of a successful build.
+#[derive(Debug, Clone)]
+pub struct BuildOutput {
+    /// Every JSON message emitted by cargo.
+    pub messages: Vec<Message>,
+    /// The stderr output of cargo, with progress and status lines.
+    pub stderr: Vec<u8>,
+}
+
+impl BuildOutput {
+    /// Returns every built or fresh target.
+    pub fn artifacts(&self) -> impl Iterator<Item = &Artifact> {
+        self.messages.iter().filter_map(|message| match message {
+            Message::CompilerArtifact(artifact) => Some(artifact),
+            _ => None,
+        })
+    }
+
+    /// Returns the path of every built executable.
+    pub fn executables(&self) -> impl Iterator<Item = &Path> {
+        self.artifacts()
+            .filter_map(|artifact| artifact.executable.as_deref())
+    }
+
+    /// Collects the `timing-info` messages of a build with [timings](BuildConfig::timings) enabled,
+    /// using the unit graph of the same build to find the dependencies between units.
+    pub fn timings(&self, graph: UnitGraph) -> TimingReport {
+        TimingReport::new(&self.messages, graph)
+    }
+
+    /// Returns every diagnostic emitted by the compiler.
+    pub fn diagnostics(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.messages.iter().filter_map(|message| match message {
+            Message::CompilerMessage(message) => Some(&message.message),
+            _ => None,
+        })
+    }
+}
+
+impl Cargo {
+    /// Creates a command for one of the build commands, such as "build", with the given config.
+    pub(crate) fn build_command(&self, subcommand: &str, config: &BuildConfig) -> Command {
+        let mut command = self.driver_command([subcommand, "--message-format", "json"]);
+        build_args(&mut command, config);
+        command
+    }
+
+    /// Runs a build command, collecting its messages and reporting them to the [observer](Cargo::observer).
+    pub(crate) fn run_build(&self, command: &mut Command) -> Result<(Output, Vec<Message>)> {
+        let mut messages = Vec::new();
+
+        let output = self.wait_lines(command, |stream, line| match stream {
+            Stream::Stdout => {
+                if let Some(mut message) = Message::parse_line(line) {
+                    self.driver.map_messages(slice::from_mut(&mut message));
+
+                    if let Some(observer) = &self.observer {
+                        observer.message(&message);
+                    }
+
+                    messages.push(message);
+                }
+            }
+            Stream::Stderr => {
+                if let Some(observer) = &self.observer {
+                    observer.status(line);
+                }
+            }
+        })?;
+
+        Ok((output, messages))
+    }
+
+    /// Runs one of the build commands, collecting its messages.
+    fn compile(&mut self, subcommand: &str, config: &BuildConfig) -> Result<BuildOutput> {
+        let (output, messages) = self.run_compile(subcommand, config)?;
+
+        if output.status.success() {
+            Ok(BuildOutput {
+                messages,
+                stderr: output.stderr,
+            })
+        } else {
+            Err(build_error(&messages, output.stderr))
+        }
+    }
+
+    /// Runs one of the build commands, reporting the total number of units to the observer first if it wants it.
+    fn run_compile(
+        &mut self,
+        subcommand: &str,
+        config: &BuildConfig,
+    ) -> Result<(Output, Vec<Message>)> {
+        if let Some(observer) = self
+            .observer
+            .clone()
+            .filter(|observer| observer.0.wants_total_units())
+        {
+            // Stable versions of cargo can't print the unit graph, the total is then unknown.
+            if let Ok(graph) = self.subcommand_unit_graph(subcommand, config) {
+                observer.0.total_units(graph.units.len());
+            }
+        }
+
+        let mut command = self.build_command(subcommand, config);
+        self.run_build(&mut command)
+    }
+
+    /// Runs `cargo build`.
+    pub fn build(&mut self, config: BuildConfig) -> Result<BuildOutput> {
+        self.compile("build", &config)
+    }
+
+    /// Runs `cargo check`.
+    pub fn check(&mut self, config: BuildConfig) -> Result<BuildOutput> {
+        self.compile("check", &config)
+    }
+
+    /// Runs `cargo test`.
+    ///
+    /// The output of the tests is not parsed, lines that aren't JSON messages are skipped. Failing to compile is
+    /// reported as [ParsingError::Build], while tests that ran but failed are reported as [ParsingError::Program].
+    pub fn test(&mut self, config: BuildConfig) -> Result<BuildOutput> {
+        let (output, messages) = self.run_compile("test", &config)?;
+
+        if output.status.success() {
+            Ok(BuildOutput {
+                messages,
+                stderr: output.stderr,
+            })
+        } else {
+            Err(run_error(&messages, output))
+        }
+    }
+
+    /// Returns the units `cargo build` would build with the given config, without building them.
+    ///
+    /// This is unstable and only supported by nightly versions of cargo.
+    pub fn unit_graph(&mut self, config: &BuildConfig) -> Result<UnitGraph> {
+        self.subcommand_unit_graph("build", config)
+    }
+
+    fn subcommand_unit_graph(
+        &mut self,
+        subcommand: &str,
+        config: &BuildConfig,
+    ) -> Result<UnitGraph> {
+        let mut command = self.build_command(subcommand, config);
+        command.args(["-Zunstable-options", "--unit-graph"]);
+
+        UnitGraph::parse(self.exec(&mut command)?)
+    }
+}
+
+/// Passes the selection and compilation options of the config, shared by the build commands and `cargo run`.
+fn build_args(command: &mut Command, config: &BuildConfig) {
+    for package in &config.packages {
+        command.arg("--package").arg(package);
+    }
+
+    if config.workspace {
+        command.arg("--workspace");
+    }
+
+    for package in &config.exclude {
+        command.arg("--exclude").arg(package);
+    }
+
+    for target in &config.targets {
+        match target {
+            CompileTarget::Lib => command.arg("--lib"),
+            CompileTarget::Bin(name) => command.arg("--bin").arg(name),
+            CompileTarget::Bins => command.arg("--bins"),
+            CompileTarget::Example(name) => command.arg("--example").arg(name),
+            CompileTarget::Examples => command.arg("--examples"),
+            CompileTarget::Test(name) => command.arg("--test").arg(name),
+            CompileTarget::Tests => command.arg("--tests"),
+            CompileTarget::Bench(name) => command.arg("--bench").arg(name),
+            CompileTarget::Benches => command.arg("--benches"),
+            CompileTarget::All => command.arg("--all-targets"),
+        };
+    }
+
+    if let Some(features) = &config.features {
+        features.apply(command);
+    }
+
+    if config.release {
+        command.arg("--release");
+    }
+
+    if let Some(profile) = &config.profile {
+        command.arg("--profile").arg(profile);
+    }
+
+    if let Some(target) = &config.target {
+        command.arg("--target").arg(target.to_string());
+    }
+
+    if let Some(manifest_path) = &config.manifest_path {
+        command.arg("--manifest-path").arg(manifest_path);
+    }
+
+    if config.ignore_rust_version {
+        command.arg("--ignore-rust-version");
+    }
+
+    if config.timings {
+        command.args(["-Zunstable-options", "--timings=json"]);
+    }
+}
+
+/// Creates the error of a command that runs what it built, like `cargo test`: a [ParsingError::Program] if the
+/// build finished, otherwise a [ParsingError::Build].
+fn run_error(messages: &[Message], output: Output) -> ParsingError {
+    // The tests or benchmarks only run once the build finished.
+    let built = messages
+        .iter()
+        .any(|message| matches!(message, Message::BuildFinished(finished) if finished.success));
+
+    if built {
+        ParsingError::Program {
+            status: output.status,
+            stdout: output.stdout,
+            stderr: output.stderr,
+        }
+    } else {
+        build_error(messages, output.stderr)
+    }
+}
+
+/// Creates a [ParsingError::Build] from the errors reported by the compiler.
+fn build_error(messages: &[Message], stderr: Vec<u8>) -> ParsingError {
+    let diagnostics = messages
+        .iter()
+        .filter_map(|message| match message {
+            Message::CompilerMessage(message)
+                if matches!(
+                    message.message.level,
+                    DiagnosticLevel::Error | DiagnosticLevel::Ice
+                ) =>
+            {
+                message.message.rendered.clone()
+            }
+            _ => None,
+        })
+        .collect();
+
+    ParsingError::Build {
+        diagnostics,
+        stderr,
+    }
+}
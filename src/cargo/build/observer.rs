@@ -0,0 +1,314 @@
+use std::{fmt, sync::Arc};
+
+use crate::cargo::message::{
+    Artifact, BuildFinished, BuildScriptExecuted, CompilerMessage, Message,
+};
+
+/// Receives the progress of a build as it happens, E.g. to drive a progress bar.
+///
+/// Every method does nothing by default, and is called on the thread running the build.
+pub trait BuildObserver: Send + Sync {
+    /// Whether to report the [total units](Self::total_units), disabled by default.
+    ///
+    /// The unit graph is then computed before every build, which runs cargo one more time.
+    fn wants_total_units(&self) -> bool {
+        false
+    }
+
+    /// The number of units the build will go through, fresh ones included.
+    ///
+    /// This is only called when [wants_total_units](Self::wants_total_units) returns true and the unit
+    /// graph is available, which requires a nightly version of cargo.
+    fn total_units(&self, _total: usize) {}
+
+    /// A unit started compiling or checking, with the name and version of its package,
+    /// E.g. "serde v1.0.163" or "foo v0.1.0 (/path/to/foo)".
+    fn compiling(&self, _package: &str) {}
+
+    /// A unit finished compiling, or was found to be fresh.
+    fn unit(&self, _artifact: &Artifact) {}
+
+    /// A build script was executed.
+    fn build_script(&self, _script: &BuildScriptExecuted) {}
+
+    /// The compiler emitted a diagnostic, E.g. a warning.
+    fn diagnostic(&self, _message: &CompilerMessage) {}
+
+    /// Cargo is waiting for another process to release a lock, E.g. "file lock on build directory".
+    fn blocking(&self, _lock: &str) {}
+
+    /// The build finished, successfully or not.
+    fn finished(&self, _finished: &BuildFinished) {}
+}
+
+/// An observer shared by the clones of [Cargo](crate::cargo::Cargo).
+#[derive(Clone)]
+pub(crate) struct SharedObserver(pub(crate) Arc<dyn BuildObserver>);
+
+impl fmt::Debug for SharedObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BuildObserver")
+    }
+}
+
+impl SharedObserver {
+    /// Reports a JSON message emitted by cargo.
+    pub(crate) fn message(&self, message: &Message) {
+        match message {
+            Message::CompilerArtifact(artifact) => self.0.unit(artifact),
+            Message::CompilerMessage(message) => self.0.diagnostic(message),
+            Message::BuildScriptExecuted(script) => self.0.build_script(script),
+            Message::BuildFinished(finished) => self.0.finished(finished),
+            Message::TimingInfo(_) | Message::Unknown => {}
+        }
+    }
+
+    /// Reports a status line cargo printed to stderr, E.g. "   Compiling serde v1.0.163".
+    pub(crate) fn status(&self, line: &[u8]) {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim();
+
+        if let Some(package) = line
+            .strip_prefix("Compiling ")
+            .or_else(|| line.strip_prefix("Checking "))
+        {
+            self.0.compiling(package);
+        } else if let Some(lock) = line.strip_prefix("Blocking waiting for ") {
+            self.0.blocking(lock);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use std::sync::Mutex;
+
+    /// Records every call it receives.
+    #[derive(Default)]
+    struct Recorder {
+        wants_total_units: bool,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl Recorder {
+        fn record(&self, call: String) {
+            self.calls.lock().unwrap().push(call);
+        }
+
+        fn take(&self) -> Vec<String> {
+            std::mem::take(&mut self.calls.lock().unwrap())
+        }
+    }
+
+    impl BuildObserver for Recorder {
+        fn wants_total_units(&self) -> bool {
+            self.wants_total_units
+        }
+
+        fn total_units(&self, total: usize) {
+            self.record(format!("total_units {total}"));
+        }
+
+        fn compiling(&self, package: &str) {
+            self.record(format!("compiling {package}"));
+        }
+
+        fn unit(&self, artifact: &Artifact) {
+            self.record(format!("unit {}", artifact.target.name));
+        }
+
+        fn build_script(&self, script: &BuildScriptExecuted) {
+            self.record(format!("build_script {}", script.package_id));
+        }
+
+        fn diagnostic(&self, message: &CompilerMessage) {
+            self.record(format!("diagnostic {}", message.message.message));
+        }
+
+        fn blocking(&self, lock: &str) {
+            self.record(format!("blocking {lock}"));
+        }
+
+        fn finished(&self, finished: &BuildFinished) {
+            self.record(format!("finished {}", finished.success));
+        }
+    }
+
+    fn observer() -> (Arc<Recorder>, SharedObserver) {
+        let recorder = Arc::new(Recorder::default());
+        (recorder.clone(), SharedObserver(recorder))
+    }
+
+    #[test]
+    fn status_lines() {
+        let (recorder, observer) = observer();
+
+        for line in [
+            "   Compiling serde v1.0.163",
+            "    Checking foo v0.1.0 (/p)",
+            "    Blocking waiting for file lock on build directory",
+            "    Finished `dev` profile [unoptimized + debuginfo] target(s) in 0.50s",
+            "     Running `target/debug/foo`",
+            "warning: unused variable: `x`",
+            "",
+        ] {
+            observer.status(line.as_bytes());
+        }
+
+        assert_eq!(
+            recorder.take(),
+            [
+                "compiling serde v1.0.163",
+                "compiling foo v0.1.0 (/p)",
+                "blocking file lock on build directory",
+            ]
+        );
+    }
+
+    #[test]
+    fn messages() {
+        let (recorder, observer) = observer();
+
+        let package_id = "path+file:///ws#app@0.1.0";
+        let target = json!({
+            "kind": ["bin"],
+            "crate_types": ["bin"],
+            "name": "app",
+            "src_path": "/ws/src/main.rs",
+            "edition": "2021",
+            "required-features": null,
+            "doc": true,
+            "doctest": false,
+            "test": true,
+        });
+
+        let messages: [Value; 6] = [
+            json!({
+                "reason": "compiler-artifact",
+                "package_id": package_id,
+                "manifest_path": "/ws/Cargo.toml",
+                "target": target,
+                "profile": {
+                    "opt_level": "0",
+                    "debuginfo": 2,
+                    "debug_assertions": true,
+                    "overflow_checks": true,
+                    "test": false,
+                },
+                "features": [],
+                "filenames": ["/ws/target/debug/app"],
+                "executable": "/ws/target/debug/app",
+                "fresh": false,
+            }),
+            json!({
+                "reason": "compiler-message",
+                "package_id": package_id,
+                "manifest_path": "/ws/Cargo.toml",
+                "target": target,
+                "message": {
+                    "message": "unused variable: `x`",
+                    "code": null,
+                    "level": "warning",
+                    "spans": [],
+                    "rendered": "warning: unused variable: `x`",
+                },
+            }),
+            json!({
+                "reason": "build-script-executed",
+                "package_id": package_id,
+                "linked_libs": [],
+                "linked_paths": [],
+                "cfgs": [],
+                "env": [],
+                "out_dir": "/ws/target/debug/build/app-0123456789abcdef/out",
+            }),
+            json!({
+                "reason": "timing-info",
+                "package_id": package_id,
+                "target": target,
+                "mode": "build",
+                "duration": 0.5,
+                "rmeta_time": null,
+            }),
+            json!({ "reason": "some-future-message" }),
+            json!({ "reason": "build-finished", "success": true }),
+        ];
+
+        for message in messages {
+            let message = Message::parse_line(message.to_string().as_bytes()).unwrap();
+            observer.message(&message);
+        }
+
+        assert_eq!(
+            recorder.take(),
+            [
+                "unit app".to_string(),
+                "diagnostic unused variable: `x`".to_string(),
+                format!("build_script {package_id}"),
+                "finished true".to_string(),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn total_units() {
+        use crate::cargo::{home::temp_dir, BuildConfig, Cargo};
+        use std::{fs, os::unix::fs::PermissionsExt};
+
+        let dir = temp_dir("observer");
+        let log = dir.join("log");
+        let stub = dir.join("cargo");
+
+        fs::create_dir_all(&dir).unwrap();
+
+        // Records its arguments, and prints an empty unit graph or the end of a build.
+        fs::write(
+            &stub,
+            format!(
+                r#"#!/bin/sh
+echo "$@" >> '{}'
+case "$*" in
+  *--unit-graph*) echo '{{"version":1,"units":[],"roots":[]}}' ;;
+  *) echo '{{"reason":"build-finished","success":true}}' ;;
+esac
+"#,
+                log.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let build = |wants_total_units: bool| {
+            let recorder = Arc::new(Recorder {
+                wants_total_units,
+                ..Default::default()
+            });
+
+            let mut cargo = Cargo::new();
+            cargo.path(&stub).observer(Some(recorder.clone()));
+            cargo.build(BuildConfig::default()).unwrap();
+
+            let invocations = fs::read_to_string(&log).unwrap().lines().count();
+            fs::remove_file(&log).unwrap();
+
+            (recorder.take(), invocations)
+        };
+
+        let without = build(false);
+        let with = build(true);
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(without, (vec!["finished true".to_string()], 1));
+        assert_eq!(
+            with,
+            (
+                vec!["total_units 0".to_string(), "finished true".to_string()],
+                2
+            )
+        );
+    }
+}
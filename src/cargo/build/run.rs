@@ -0,0 +1,404 @@
+use std::{
+    env,
+    path::Path,
+    process::{Child, ChildStderr, ChildStdin, ChildStdout, ExitStatus, Output, Stdio},
+};
+
+use super::{build_args, BuildConfig, CompileTarget, RunConfig, RunTarget};
+use crate::cargo::{
+    metadata::{Package, TargetKind},
+    process, Cargo, Metadata, MetadataConfig, ParsingError, Result,
+};
+
+/// A handle to a program started by [Cargo::run].
+///
+/// The program runs in its own process group, and dropping the handle before it exited kills it along
+/// with the processes it started.
+#[derive(Debug)]
+pub struct RunHandle {
+    child: Child,
+    exited: bool,
+}
+
+impl RunHandle {
+    /// The OS-assigned process identifier of the program.
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Takes the stdin of the program, if it was piped.
+    pub fn stdin(&mut self) -> Option<ChildStdin> {
+        self.child.stdin.take()
+    }
+
+    /// Takes the stdout of the program, if it was piped.
+    pub fn stdout(&mut self) -> Option<ChildStdout> {
+        self.child.stdout.take()
+    }
+
+    /// Takes the stderr of the program, if it was piped.
+    pub fn stderr(&mut self) -> Option<ChildStderr> {
+        self.child.stderr.take()
+    }
+
+    /// Waits for the program to exit, failing with [ParsingError::Program] if it was unsuccessful.
+    pub fn wait(&mut self) -> Result<()> {
+        let status = self.child.wait()?;
+        self.exited = true;
+        check_status(status, Vec::new(), Vec::new()).map(|_| ())
+    }
+
+    /// Checks if the program exited without blocking,
+    /// failing with [ParsingError::Program] if it was unsuccessful.
+    pub fn try_wait(&mut self) -> Result<Option<()>> {
+        match self.child.try_wait()? {
+            Some(status) => {
+                self.exited = true;
+                check_status(status, Vec::new(), Vec::new()).map(|_| Some(()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Waits for the program to exit and collects its output, if it was piped.
+    pub fn wait_with_output(mut self) -> Result<Output> {
+        let Output {
+            status,
+            stdout,
+            stderr,
+        } = process::wait_output(&mut self.child, None, None)?;
+        self.exited = true;
+
+        check_status(status, stdout, stderr)
+    }
+
+    /// Kills the program, along with the processes it started.
+    pub fn kill(&mut self) -> Result<()> {
+        if self.exited {
+            return Ok(());
+        }
+
+        Ok(process::kill_group(&mut self.child)?)
+    }
+}
+
+impl Drop for RunHandle {
+    fn drop(&mut self) {
+        if !self.exited {
+            let _ = process::kill_group(&mut self.child);
+            let _ = self.child.wait();
+        }
+    }
+}
+
+fn check_status(status: ExitStatus, stdout: Vec<u8>, stderr: Vec<u8>) -> Result<Output> {
+    if status.success() {
+        Ok(Output {
+            status,
+            stdout,
+            stderr,
+        })
+    } else {
+        Err(ParsingError::Program {
+            status,
+            stdout,
+            stderr,
+        })
+    }
+}
+
+impl Cargo {
+    /// Builds and starts a binary or example with `cargo run`.
+    ///
+    /// The program is built first, so build failures are reported as [ParsingError::Build] and the build
+    /// output never mixes with the program's own stdio. It is then started by `cargo run --quiet`, which
+    /// applies the `runner` of the target, the `CARGO_*` environment variables and the library search path.
+    /// Failures of the program are reported as [ParsingError::Program] by the returned handle.
+    pub fn run(&mut self, config: RunConfig) -> Result<RunHandle> {
+        let target = match &config.target {
+            Some(target) => target.clone(),
+            None => self.default_run_target(&config)?,
+        };
+
+        let build_config = BuildConfig {
+            packages: config.package.iter().cloned().collect(),
+            targets: vec![match target {
+                RunTarget::Bin(name) => CompileTarget::Bin(name),
+                RunTarget::Example(name) => CompileTarget::Example(name),
+            }],
+            features: config.features.clone(),
+            release: config.release,
+            profile: config.profile.clone(),
+            target: config.target_triple.clone(),
+            manifest_path: config.manifest_path.clone(),
+            ..Default::default()
+        };
+
+        self.build(build_config.clone())?;
+
+        // The program is fresh, so cargo only runs it.
+        let mut command = self.driver_command(["run", "--quiet"]);
+        build_args(&mut command, &build_config);
+        command.arg("--").args(&config.args);
+
+        if config.piped {
+            command
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped());
+        }
+
+        process::set_process_group(&mut command);
+
+        Ok(RunHandle {
+            child: command.spawn()?,
+            exited: false,
+        })
+    }
+
+    /// Picks the binary `cargo run` would run when no target is given.
+    fn default_run_target(&mut self, config: &RunConfig) -> Result<RunTarget> {
+        let metadata = self.metadata(MetadataConfig {
+            manifest_path: config.manifest_path.clone(),
+            no_deps: true,
+            ..Default::default()
+        })?;
+
+        let package = select_package(
+            &metadata,
+            config.package.as_deref(),
+            config.manifest_path.as_deref(),
+        )
+        .map_err(|error| match error {
+            ParsingError::Selection(message) => ParsingError::Run(message),
+            error => error,
+        })?;
+
+        if let Some(default_run) = &package.default_run {
+            return Ok(RunTarget::Bin(default_run.clone()));
+        }
+
+        let mut bins = package
+            .targets
+            .iter()
+            .filter(|target| target.kind.contains(&TargetKind::Bin));
+
+        match (bins.next(), bins.next()) {
+            (Some(bin), None) => Ok(RunTarget::Bin(bin.name.clone())),
+            (None, _) => Err(ParsingError::Run(format!(
+                "package `{}` has no binaries",
+                package.name
+            ))),
+            (Some(_), Some(_)) => Err(ParsingError::Run(format!(
+                "package `{}` has multiple binaries and no `default-run`",
+                package.name
+            ))),
+        }
+    }
+}
+
+/// Finds the workspace package with the given name, or the current one if [None].
+pub(crate) fn select_package<'a>(
+    metadata: &'a Metadata,
+    name: Option<&str>,
+    manifest_path: Option<&Path>,
+) -> Result<&'a Package> {
+    match name {
+        Some(name) => metadata
+            .workspace_packages()
+            .find(|package| package.name == name)
+            .ok_or_else(|| ParsingError::Selection(format!("package `{name}` not found"))),
+        None => current_package(metadata, manifest_path),
+    }
+}
+
+/// Finds the package cargo would consider the current one,
+/// from the manifest path or the current directory.
+fn current_package<'a>(
+    metadata: &'a Metadata,
+    manifest_path: Option<&Path>,
+) -> Result<&'a Package> {
+    let dir = match manifest_path {
+        Some(manifest_path) => manifest_path
+            .canonicalize()?
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default(),
+        None => env::current_dir()?,
+    };
+
+    // The package whose directory is the closest ancestor of the current directory.
+    let closest = metadata
+        .workspace_packages()
+        .filter(|package| {
+            package
+                .manifest_path
+                .parent()
+                .is_some_and(|package_dir| dir.starts_with(package_dir))
+        })
+        .max_by_key(|package| package.manifest_path.components().count());
+
+    if let Some(package) = closest {
+        return Ok(package);
+    }
+
+    let mut packages = metadata.workspace_packages();
+    match (packages.next(), packages.next()) {
+        (Some(package), None) => Ok(package),
+        _ => Err(ParsingError::Selection(
+            "could not determine the current package, specify one".to_string(),
+        )),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::cargo::home::temp_dir;
+    use serde_json::json;
+    use std::{fs, os::unix::fs::PermissionsExt, path::PathBuf, thread, time::Duration};
+
+    /// A cargo that records its subcommands, fails to build if a "fail" file exists, and otherwise runs
+    /// a program printing its arguments, or sleeping in a child process when given "forever".
+    fn stub(dir: &Path) -> PathBuf {
+        let stub = dir.join("cargo");
+
+        fs::create_dir_all(dir).unwrap();
+        fs::write(
+            dir.join("error.json"),
+            json!({
+                "reason": "compiler-message",
+                "package_id": "path+file:///ws#app@0.1.0",
+                "manifest_path": "/ws/Cargo.toml",
+                "target": {
+                    "kind": ["bin"],
+                    "crate_types": ["bin"],
+                    "name": "app",
+                    "src_path": "/ws/src/main.rs",
+                    "edition": "2021",
+                    "required-features": null,
+                    "doc": true,
+                    "doctest": false,
+                    "test": true,
+                },
+                "message": {
+                    "message": "mismatched types",
+                    "code": null,
+                    "level": "error",
+                    "spans": [],
+                    "rendered": "error[E0308]: mismatched types",
+                },
+            })
+            .to_string()
+                + "\n",
+        )
+        .unwrap();
+        fs::write(
+            &stub,
+            format!(
+                r#"#!/bin/sh
+dir='{}'
+echo "$1" >> "$dir/log"
+case "$1" in
+  build)
+    if [ -f "$dir/fail" ]; then cat "$dir/error.json"; exit 101; fi
+    echo '{{"reason":"build-finished","success":true}}'
+    ;;
+  run)
+    while [ "$1" != "--" ]; do shift; done
+    shift
+    if [ "$1" = "forever" ]; then
+      sleep 30 &
+      echo $! > "$dir/sleep.pid"
+      wait
+    fi
+    echo "$@"
+    ;;
+esac
+"#,
+                dir.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+
+        stub
+    }
+
+    fn config(args: &[&str]) -> RunConfig {
+        RunConfig {
+            target: Some(RunTarget::Bin("app".to_string())),
+            args: args.iter().map(Into::into).collect(),
+            piped: true,
+            ..Default::default()
+        }
+    }
+
+    /// Whether a process exited, killed processes can stay zombies until their parent reaps them.
+    fn is_gone(pid: &str) -> bool {
+        fs::read_to_string(format!("/proc/{pid}/stat")).map_or(true, |stat| {
+            stat.rsplit(") ")
+                .next()
+                .is_some_and(|stat| stat.starts_with('Z'))
+        })
+    }
+
+    #[test]
+    fn builds_then_runs() {
+        let dir = temp_dir("run");
+        let mut cargo = Cargo::new();
+        cargo.path(stub(&dir));
+
+        let output = cargo
+            .run(config(&["hello", "world"]))
+            .unwrap()
+            .wait_with_output();
+        let log = fs::read_to_string(dir.join("log"));
+
+        fs::write(dir.join("fail"), "").unwrap();
+        let error = cargo.run(config(&[]));
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(output.unwrap().stdout, b"hello world\n");
+        assert_eq!(log.unwrap(), "build\nrun\n");
+        assert!(matches!(
+            error,
+            Err(ParsingError::Build { diagnostics, .. })
+                if diagnostics == ["error[E0308]: mismatched types"]
+        ));
+    }
+
+    #[test]
+    fn drop_kills_group() {
+        let dir = temp_dir("run");
+        let mut cargo = Cargo::new();
+        cargo.path(stub(&dir));
+
+        let handle = cargo.run(config(&["forever"])).unwrap();
+        let pid_file = dir.join("sleep.pid");
+
+        let mut pid = String::new();
+        for _ in 0..500 {
+            pid = fs::read_to_string(&pid_file).unwrap_or_default();
+            if pid.ends_with('\n') {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let pid = pid.trim().to_string();
+        assert!(!pid.is_empty() && !is_gone(&pid));
+
+        drop(handle);
+
+        // The program started by cargo is killed along with cargo.
+        let killed = (0..500).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            is_gone(&pid)
+        });
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(killed);
+    }
+}
@@ -0,0 +1,507 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    fmt::{self, Display},
+    io::{self, Stdout, Write},
+    path::{Path, PathBuf},
+};
+use target_lexicon::Triple;
+
+use super::{Cargo, ParsingError, Result, Version};
+
+/// The environment cargo provides to build scripts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildEnv {
+    /// The directory in which all output and intermediate artifacts should be placed.
+    pub out_dir: PathBuf,
+    /// The directory containing the manifest of the package being built.
+    pub manifest_dir: PathBuf,
+    /// The target triple that is being compiled for.
+    pub target: Triple,
+    /// The host triple of the rust compiler.
+    pub host: Triple,
+    /// "release" for release builds, "debug" for other builds.
+    pub profile: String,
+    /// The optimization level, E.g. "0", "3" or "s".
+    pub opt_level: String,
+    /// Whether the profile has debug information enabled.
+    pub debug: bool,
+    /// The features enabled on the package, in the form of their `CARGO_FEATURE_*` variables.
+    ///
+    /// Cargo uppercases the names and replaces dashes with underscores, so the original names can't be recovered,
+    /// E.g. both "foo-bar" and "foo_bar" are stored as "FOO_BAR". Use [has_feature](Self::has_feature) to look them up.
+    pub features: BTreeSet<String>,
+    /// The configuration options of the target being compiled for.
+    pub cfg: CfgSet,
+    /// The flags passed to rustc, from `CARGO_ENCODED_RUSTFLAGS`.
+    pub rustflags: Vec<String>,
+    /// Metadata set by the build scripts of dependencies with a `links` key, E.g. "DEP_Z_INCLUDE" is stored as "Z_INCLUDE".
+    pub dep_metadata: BTreeMap<String, String>,
+}
+
+impl BuildEnv {
+    /// Reads the build script environment of the current process.
+    pub fn from_env() -> Result<Self> {
+        Self::from_vars(env::vars())
+    }
+
+    /// Reads the build script environment from the given variables.
+    pub fn from_vars<I>(vars: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let vars: BTreeMap<String, String> = vars.into_iter().collect();
+
+        let var = |key: &'static str| vars.get(key).ok_or(ParsingError::Env(key));
+
+        let mut features = BTreeSet::new();
+        let mut cfg = CfgSet::default();
+        let mut dep_metadata = BTreeMap::new();
+
+        for (key, value) in &vars {
+            if let Some(feature) = key.strip_prefix("CARGO_FEATURE_") {
+                features.insert(feature.to_string());
+            } else if let Some(name) = key.strip_prefix("CARGO_CFG_") {
+                cfg.insert_env(&name.to_lowercase(), value);
+            } else if let Some(key) = key.strip_prefix("DEP_") {
+                dep_metadata.insert(key.to_string(), value.clone());
+            }
+        }
+
+        let rustflags = vars
+            .get("CARGO_ENCODED_RUSTFLAGS")
+            .filter(|flags| !flags.is_empty())
+            .map(|flags| flags.split('\x1f').map(str::to_string).collect())
+            .unwrap_or_default();
+
+        Ok(BuildEnv {
+            out_dir: var("OUT_DIR")?.into(),
+            manifest_dir: var("CARGO_MANIFEST_DIR")?.into(),
+            target: var("TARGET")?.parse()?,
+            host: var("HOST")?.parse()?,
+            profile: var("PROFILE")?.clone(),
+            opt_level: vars.get("OPT_LEVEL").cloned().unwrap_or_default(),
+            debug: vars.get("DEBUG").is_some_and(|debug| debug != "false"),
+            features,
+            cfg,
+            rustflags,
+            dep_metadata,
+        })
+    }
+
+    /// Whether the given feature is enabled, with the name as written in the manifest.
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.features.contains(&env_name(feature))
+    }
+
+    /// Returns the metadata key set by the build script of the dependency with the given `links` value.
+    pub fn dep(&self, links: &str, key: &str) -> Option<&str> {
+        let key = format!("{}_{}", env_name(links), env_name(key));
+        self.dep_metadata.get(&key).map(String::as_str)
+    }
+}
+
+/// A single configuration option, E.g. `unix` or `target_os = "linux"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Cfg {
+    /// A name only option, E.g. `unix`.
+    Name(String),
+    /// A key-value option, E.g. `target_os = "linux"`.
+    KeyPair(String, String),
+}
+
+impl Display for Cfg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cfg::Name(name) => write!(f, "{name}"),
+            Cfg::KeyPair(key, value) => write!(f, "{key}=\"{value}\""),
+        }
+    }
+}
+
+/// The set of configuration options of a target.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CfgSet(pub BTreeSet<Cfg>);
+
+impl CfgSet {
+    fn insert_env(&mut self, name: &str, value: &str) {
+        if value.is_empty() {
+            self.0.insert(Cfg::Name(name.to_string()));
+        } else {
+            // Options with multiple values, such as "target_feature", are joined with commas.
+            for value in value.split(',') {
+                self.0
+                    .insert(Cfg::KeyPair(name.to_string(), value.to_string()));
+            }
+        }
+    }
+
+    /// Whether the set contains the given option.
+    pub fn contains(&self, cfg: &Cfg) -> bool {
+        self.0.contains(cfg)
+    }
+
+    /// Whether the set contains the given name only option, E.g. "unix".
+    pub fn has_name(&self, name: &str) -> bool {
+        self.0.contains(&Cfg::Name(name.to_string()))
+    }
+
+    /// Returns every value of the given key, E.g. every enabled "target_feature".
+    pub fn values<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> {
+        self.0.iter().filter_map(move |cfg| match cfg {
+            Cfg::KeyPair(k, value) if k == key => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Returns the value of the given key, E.g. "linux" for "target_os".
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.0.iter().find_map(|cfg| match cfg {
+            Cfg::KeyPair(k, value) if k == key => Some(value.as_str()),
+            _ => None,
+        })
+    }
+}
+
+/// The syntax used to emit directives to cargo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DirectiveSyntax {
+    /// The `cargo:KEY=VALUE` syntax understood by every version of cargo.
+    Legacy,
+    /// The `cargo::KEY=VALUE` syntax introduced in cargo 1.77.
+    Namespaced,
+}
+
+/// The kind of a library linked with `rustc-link-lib`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LinkKind {
+    Static,
+    Dylib,
+    Framework,
+}
+
+impl Display for LinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkKind::Static => write!(f, "static"),
+            LinkKind::Dylib => write!(f, "dylib"),
+            LinkKind::Framework => write!(f, "framework"),
+        }
+    }
+}
+
+/// Emits instructions to cargo from a build script.
+///
+/// The syntax is chosen from the version of cargo running the build script,
+/// so the same build script works with both older and newer versions of cargo.
+#[derive(Debug)]
+pub struct Directives<W = Stdout> {
+    out: W,
+    syntax: DirectiveSyntax,
+    check_cfg: bool,
+    error: bool,
+}
+
+impl Directives<Stdout> {
+    /// Writes directives to stdout, detecting the syntax from the cargo running the build script.
+    ///
+    /// Falls back to [DirectiveSyntax::Legacy] if the version can't be detected.
+    pub fn detect() -> Self {
+        match Cargo::new().version() {
+            Ok(version) => Self::for_version(io::stdout(), &version),
+            Err(_) => Self::new(io::stdout(), DirectiveSyntax::Legacy),
+        }
+    }
+}
+
+impl<W: Write> Directives<W> {
+    /// Writes directives with the given syntax.
+    pub fn new(out: W, syntax: DirectiveSyntax) -> Self {
+        Directives {
+            out,
+            syntax,
+            check_cfg: false,
+            error: false,
+        }
+    }
+
+    /// Writes directives with the syntax supported by the given version of cargo.
+    pub fn for_version(out: W, version: &Version) -> Self {
+        let capabilities = version.capabilities();
+
+        Directives {
+            out,
+            syntax: if capabilities.namespaced_directives {
+                DirectiveSyntax::Namespaced
+            } else {
+                DirectiveSyntax::Legacy
+            },
+            check_cfg: capabilities.check_cfg_directive,
+            error: capabilities.error_directive,
+        }
+    }
+
+    /// The syntax used by these directives.
+    pub fn syntax(&self) -> DirectiveSyntax {
+        self.syntax
+    }
+
+    fn emit(&mut self, key: &str, value: impl Display) -> Result<()> {
+        let prefix = match self.syntax {
+            DirectiveSyntax::Legacy => "cargo:",
+            DirectiveSyntax::Namespaced => "cargo::",
+        };
+
+        writeln!(self.out, "{prefix}{key}={value}")?;
+        Ok(())
+    }
+
+    /// Reruns the build script if the file or directory at the given path changes.
+    pub fn rerun_if_changed<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.emit("rerun-if-changed", path.as_ref().display())
+    }
+
+    /// Reruns the build script if the given environment variable changes.
+    pub fn rerun_if_env_changed(&mut self, var: &str) -> Result<()> {
+        self.emit("rerun-if-env-changed", var)
+    }
+
+    /// Links the given library.
+    pub fn rustc_link_lib(&mut self, kind: Option<LinkKind>, name: &str) -> Result<()> {
+        match kind {
+            Some(kind) => self.emit("rustc-link-lib", format_args!("{kind}={name}")),
+            None => self.emit("rustc-link-lib", name),
+        }
+    }
+
+    /// Adds a directory to the library search path.
+    pub fn rustc_link_search<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.emit("rustc-link-search", path.as_ref().display())
+    }
+
+    /// Enables a configuration option, E.g. `#[cfg(name)]` or `#[cfg(name = "value")]`.
+    ///
+    /// The option is also declared with `rustc-check-cfg` when supported, so it's not reported as unexpected.
+    pub fn rustc_cfg(&mut self, name: &str, value: Option<&str>) -> Result<()> {
+        if self.check_cfg {
+            match value {
+                Some(value) => self.emit(
+                    "rustc-check-cfg",
+                    format_args!("cfg({name}, values(\"{value}\"))"),
+                )?,
+                None => self.emit("rustc-check-cfg", format_args!("cfg({name})"))?,
+            }
+        }
+
+        match value {
+            Some(value) => self.emit("rustc-cfg", format_args!("{name}=\"{value}\"")),
+            None => self.emit("rustc-cfg", name),
+        }
+    }
+
+    /// Declares a configuration option as expected without enabling it.
+    ///
+    /// Does nothing if the version of cargo doesn't support `rustc-check-cfg`.
+    pub fn rustc_check_cfg(&mut self, name: &str, values: &[&str]) -> Result<()> {
+        if !self.check_cfg {
+            return Ok(());
+        }
+
+        if values.is_empty() {
+            self.emit("rustc-check-cfg", format_args!("cfg({name})"))
+        } else {
+            let values: Vec<String> = values.iter().map(|value| format!("\"{value}\"")).collect();
+            self.emit(
+                "rustc-check-cfg",
+                format_args!("cfg({name}, values({}))", values.join(", ")),
+            )
+        }
+    }
+
+    /// Sets an environment variable available through `env!` when compiling the package.
+    pub fn rustc_env(&mut self, key: &str, value: &str) -> Result<()> {
+        self.emit("rustc-env", format_args!("{key}={value}"))
+    }
+
+    /// Displays a warning, one for each line of the message.
+    pub fn warning(&mut self, message: &str) -> Result<()> {
+        for line in message.lines() {
+            self.emit("warning", line)?;
+        }
+
+        Ok(())
+    }
+
+    /// Displays an error and fails the build once the build script finishes.
+    ///
+    /// Older versions of cargo don't support errors, so the message is displayed as a warning instead
+    /// and the build script has to exit with a failure itself.
+    pub fn error(&mut self, message: &str) -> Result<()> {
+        for line in message.lines() {
+            if self.error {
+                self.emit("error", line)?;
+            } else {
+                self.emit("warning", format_args!("error: {line}"))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets metadata available to dependents through `DEP_<LINKS>_<KEY>` environment variables.
+    pub fn metadata(&mut self, key: &str, value: &str) -> Result<()> {
+        match self.syntax {
+            DirectiveSyntax::Legacy => self.emit(key, value),
+            DirectiveSyntax::Namespaced => self.emit("metadata", format_args!("{key}={value}")),
+        }
+    }
+
+    /// Consumes the directives, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// Converts a name into the form used in environment variables.
+fn env_name(name: &str) -> String {
+    name.to_uppercase().replace('-', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(extra: &[(&str, &str)]) -> Vec<(String, String)> {
+        [
+            ("OUT_DIR", "/target/debug/build/app-1234/out"),
+            ("CARGO_MANIFEST_DIR", "/app"),
+            ("TARGET", "x86_64-unknown-linux-gnu"),
+            ("HOST", "x86_64-unknown-linux-gnu"),
+            ("PROFILE", "debug"),
+        ]
+        .iter()
+        .chain(extra)
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn features() {
+        let env = BuildEnv::from_vars(vars(&[
+            ("CARGO_FEATURE_FOO_BAR", "1"),
+            ("CARGO_FEATURE_STD", "1"),
+        ]))
+        .unwrap();
+
+        assert!(env.has_feature("foo_bar"));
+        assert!(env.has_feature("foo-bar"));
+        assert!(env.has_feature("std"));
+        assert!(!env.has_feature("alloc"));
+    }
+
+    #[test]
+    fn cfg_and_dep_metadata() {
+        let env = BuildEnv::from_vars(vars(&[
+            ("CARGO_CFG_UNIX", ""),
+            ("CARGO_CFG_TARGET_FEATURE", "sse,sse2"),
+            ("DEP_Z_INCLUDE", "/z/include"),
+        ]))
+        .unwrap();
+
+        assert!(env.cfg.contains(&Cfg::Name("unix".to_string())));
+        assert!(env.cfg.contains(&Cfg::KeyPair(
+            "target_feature".to_string(),
+            "sse2".to_string()
+        )));
+        assert_eq!(env.dep("z", "include"), Some("/z/include"));
+    }
+
+    #[test]
+    fn missing_variable() {
+        assert!(matches!(
+            BuildEnv::from_vars(Vec::new()),
+            Err(ParsingError::Env("OUT_DIR"))
+        ));
+    }
+
+    /// Emits one of every directive with the syntax of the given version of cargo.
+    fn directives(release: &str) -> String {
+        let version: Version = format!("cargo {release}").parse().unwrap();
+        let mut directives = Directives::for_version(Vec::new(), &version);
+
+        directives.rerun_if_changed("src/lib.c").unwrap();
+        directives
+            .rustc_link_lib(Some(LinkKind::Static), "z")
+            .unwrap();
+        directives.rustc_cfg("has_z", None).unwrap();
+        directives.rustc_cfg("z_version", Some("1.3")).unwrap();
+        directives.rustc_check_cfg("z_static", &[]).unwrap();
+        directives
+            .rustc_check_cfg("z_backend", &["zlib", "zlib-ng"])
+            .unwrap();
+        directives.warning("first\nsecond").unwrap();
+        directives.error("missing zlib").unwrap();
+        directives.metadata("include", "/z/include").unwrap();
+
+        String::from_utf8(directives.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn legacy_directives() {
+        assert_eq!(
+            directives("1.76.0"),
+            "cargo:rerun-if-changed=src/lib.c\n\
+             cargo:rustc-link-lib=static=z\n\
+             cargo:rustc-cfg=has_z\n\
+             cargo:rustc-cfg=z_version=\"1.3\"\n\
+             cargo:warning=first\n\
+             cargo:warning=second\n\
+             cargo:warning=error: missing zlib\n\
+             cargo:include=/z/include\n"
+        );
+    }
+
+    #[test]
+    fn namespaced_directives() {
+        // Namespaced, but without `rustc-check-cfg` nor `error`.
+        assert_eq!(
+            directives("1.77.0"),
+            "cargo::rerun-if-changed=src/lib.c\n\
+             cargo::rustc-link-lib=static=z\n\
+             cargo::rustc-cfg=has_z\n\
+             cargo::rustc-cfg=z_version=\"1.3\"\n\
+             cargo::warning=first\n\
+             cargo::warning=second\n\
+             cargo::warning=error: missing zlib\n\
+             cargo::metadata=include=/z/include\n"
+        );
+
+        // `rustc-check-cfg` from 1.80, with errors still displayed as warnings.
+        assert_eq!(
+            directives("1.80.0"),
+            "cargo::rerun-if-changed=src/lib.c\n\
+             cargo::rustc-link-lib=static=z\n\
+             cargo::rustc-check-cfg=cfg(has_z)\n\
+             cargo::rustc-cfg=has_z\n\
+             cargo::rustc-check-cfg=cfg(z_version, values(\"1.3\"))\n\
+             cargo::rustc-cfg=z_version=\"1.3\"\n\
+             cargo::rustc-check-cfg=cfg(z_static)\n\
+             cargo::rustc-check-cfg=cfg(z_backend, values(\"zlib\", \"zlib-ng\"))\n\
+             cargo::warning=first\n\
+             cargo::warning=second\n\
+             cargo::warning=error: missing zlib\n\
+             cargo::metadata=include=/z/include\n"
+        );
+
+        assert_eq!(
+            directives("1.84.0")
+                .lines()
+                .filter(|line| line.contains("zlib"))
+                .collect::<Vec<_>>(),
+            [
+                "cargo::rustc-check-cfg=cfg(z_backend, values(\"zlib\", \"zlib-ng\"))",
+                "cargo::error=missing zlib"
+            ]
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use target_lexicon::Triple;
+
+use super::{Cargo, MetadataConfig, Result};
+
+#[derive(Debug, Default, Clone)]
+pub struct CleanConfig {
+    /// Packages to clean, passed as `-p`. Removes the whole target directory if empty.
+    pub packages: Vec<String>,
+    /// Only clean the artifacts of the release profile.
+    pub release: bool,
+    /// Only clean the artifacts of the given profile.
+    pub profile: Option<String>,
+    /// Only clean the artifacts of the given target triple.
+    pub target: Option<Triple>,
+    /// Only clean the documentation, passed as `--doc`.
+    pub doc: bool,
+    pub manifest_path: Option<PathBuf>,
+}
+
+/// What [clean](Cargo::clean) removed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CleanOutput {
+    /// The number of files removed.
+    pub files: u64,
+    /// The size of the removed files.
+    ///
+    /// Cargo rounds the size in its summary, E.g. "10.4MiB", so it's only exact for versions of cargo
+    /// that don't print one and the removed files are counted instead.
+    pub bytes: u64,
+}
+
+impl Cargo {
+    /// Runs `cargo clean`, returning how many files and bytes were removed.
+    pub fn clean(&mut self, config: CleanConfig) -> Result<CleanOutput> {
+        // Cargo only prints a summary of what it removed since 1.75, before that the target directory is
+        // compared instead.
+        let snapshot = if self.capabilities()?.clean_summary {
+            None
+        } else {
+            let target_directory = self
+                .metadata(MetadataConfig {
+                    no_deps: true,
+                    manifest_path: config.manifest_path.clone(),
+                    ..Default::default()
+                })?
+                .target_directory;
+            let files = files(&target_directory)?;
+
+            Some((target_directory, files))
+        };
+
+        let mut command = self.command(["clean"]);
+
+        for package in &config.packages {
+            command.arg("--package").arg(package);
+        }
+
+        if config.release {
+            command.arg("--release");
+        }
+
+        if let Some(profile) = &config.profile {
+            command.arg("--profile").arg(profile);
+        }
+
+        if let Some(target) = &config.target {
+            command.arg("--target").arg(target.to_string());
+        }
+
+        if config.doc {
+            command.arg("--doc");
+        }
+
+        if let Some(manifest_path) = &config.manifest_path {
+            command.arg("--manifest-path").arg(manifest_path);
+        }
+
+        let output = self.output(&mut command)?;
+
+        match snapshot {
+            Some((target_directory, before)) => {
+                let after = files(&target_directory)?;
+                let mut removed = CleanOutput::default();
+
+                for (_, size) in before.iter().filter(|(path, _)| !after.contains_key(*path)) {
+                    removed.files += 1;
+                    removed.bytes += size;
+                }
+
+                Ok(removed)
+            }
+            None => Ok(std::str::from_utf8(&output.stderr)?
+                .lines()
+                .find_map(summary)
+                .unwrap_or_default()),
+        }
+    }
+}
+
+/// Parses a summary like "     Removed 21 files, 10.4MiB total", the size is left out when nothing was removed.
+fn summary(line: &str) -> Option<CleanOutput> {
+    let rest = line.trim().strip_prefix("Removed ")?;
+    let (files, rest) = rest.split_once(' ')?;
+    let files = files.parse().ok()?;
+
+    let bytes = match rest.split_once(", ") {
+        Some((_, size)) => bytes(size.strip_suffix(" total")?)?,
+        None => 0,
+    };
+
+    Some(CleanOutput { files, bytes })
+}
+
+/// Parses a size like "10.4MiB" or "300B".
+fn bytes(size: &str) -> Option<u64> {
+    let split = size.find(|char: char| char.is_ascii_alphabetic())?;
+    let (value, unit) = size.split_at(split);
+    let value: f64 = value.parse().ok()?;
+
+    let exponent = ["B", "KiB", "MiB", "GiB", "TiB"]
+        .iter()
+        .position(|known| *known == unit)?;
+
+    Some((value * 1024f64.powi(exponent as i32)).round() as u64)
+}
+
+/// The size of every file in a directory, by path.
+fn files(dir: &Path) -> Result<BTreeMap<PathBuf, u64>> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => continue,
+            Err(error) => return Err(error.into()),
+        };
+
+        for entry in entries {
+            let entry = entry?;
+
+            // Symlinks aren't followed, cargo removes the link itself.
+            if entry.file_type()?.is_dir() {
+                pending.push(entry.path());
+            } else {
+                files.insert(entry.path(), entry.metadata()?.len());
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summaries() {
+        assert_eq!(
+            summary("     Removed 21 files, 10.4MiB total\n"),
+            Some(CleanOutput {
+                files: 21,
+                bytes: 10905190,
+            })
+        );
+        assert_eq!(
+            summary("     Removed 1 file, 300B total"),
+            Some(CleanOutput {
+                files: 1,
+                bytes: 300,
+            })
+        );
+        assert_eq!(
+            summary("     Removed 0 files"),
+            Some(CleanOutput { files: 0, bytes: 0 })
+        );
+
+        assert_eq!(summary("    Finished `dev` profile"), None);
+        assert_eq!(summary("     Removed many files"), None);
+        assert_eq!(summary("     Removed 2 files, 1.5PiB total"), None);
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(bytes("300B"), Some(300));
+        assert_eq!(bytes("1.5KiB"), Some(1536));
+        assert_eq!(bytes("2GiB"), Some(2 * 1024 * 1024 * 1024));
+        assert_eq!(bytes("1TiB"), Some(1 << 40));
+
+        assert_eq!(bytes("300"), None);
+        assert_eq!(bytes("KiB"), None);
+        assert_eq!(bytes("1.5kB"), None);
+    }
+}
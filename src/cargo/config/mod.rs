@@ -0,0 +1,607 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    home::{cargo_home, normalize},
+    ParsingError, Result,
+};
+
+mod value;
+use value::RawValue;
+pub use value::{Definition, Value};
+
+/// Cargo's configuration, merged from every `.cargo/config.toml` file and `CARGO_*` environment variable
+/// that applies to a directory.
+///
+/// Files closer to the directory take precedence over the ones in its parents and `CARGO_HOME`,
+/// environment variables take precedence over every file.
+#[derive(Debug, Clone)]
+pub struct CargoConfig {
+    /// The configuration files that were loaded, from highest to lowest precedence.
+    pub files: Vec<PathBuf>,
+    /// The `[build]` table.
+    pub build: BuildConfig,
+    /// The `[alias]` table.
+    pub alias: BTreeMap<String, Vec<Value<String>>>,
+    /// The `[registries]` table.
+    pub registries: BTreeMap<String, RegistryConfig>,
+    /// The `[net]` table.
+    pub net: NetConfig,
+    /// The `[env]` table, environment variables set for build scripts, rustc invocations and `cargo run`.
+    pub env: BTreeMap<String, Value<EnvValue>>,
+    root: Option<RawValue>,
+    vars: BTreeMap<String, String>,
+}
+
+/// The `[build]` table.
+#[derive(Debug, Clone, Default)]
+pub struct BuildConfig {
+    /// The default target platforms to build for.
+    pub target: Option<Vec<Value<String>>>,
+    /// Extra flags to pass to every rustc invocation.
+    pub rustflags: Option<Vec<Value<String>>>,
+    /// The directory where all compiler output is placed.
+    pub target_dir: Option<Value<PathBuf>>,
+    /// The number of parallel jobs.
+    pub jobs: Option<Value<i64>>,
+}
+
+/// A `[target.<triple>]` table.
+#[derive(Debug, Clone, Default)]
+pub struct TargetConfig {
+    /// The linker to use for this target.
+    pub linker: Option<Value<PathBuf>>,
+    /// The command used to run executables built for this target, followed by its arguments.
+    pub runner: Option<Vec<Value<String>>>,
+    /// Extra flags to pass to rustc for this target.
+    pub rustflags: Option<Vec<Value<String>>>,
+}
+
+/// A `[registries.<name>]` table.
+#[derive(Debug, Clone, Default)]
+pub struct RegistryConfig {
+    /// The url of the registry index.
+    pub index: Option<Value<String>>,
+    /// The authentication token of the registry.
+    pub token: Option<Value<String>>,
+}
+
+/// The `[net]` table.
+#[derive(Debug, Clone, Default)]
+pub struct NetConfig {
+    /// Whether cargo should avoid accessing the network.
+    pub offline: Option<Value<bool>>,
+}
+
+/// A value of the `[env]` table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvValue {
+    /// The value of the environment variable.
+    pub value: String,
+    /// Whether the value overrides a variable already set in the environment.
+    pub force: bool,
+    /// Whether the value is a path relative to the directory containing the `.cargo` directory.
+    pub relative: bool,
+}
+
+impl CargoConfig {
+    /// Discovers the configuration that applies to the given directory,
+    /// using the environment of the current process.
+    pub fn discover<P: AsRef<Path>>(cwd: P) -> Result<Self> {
+        Self::discover_with(cwd, cargo_home(), env::vars())
+    }
+
+    /// Discovers the configuration that applies to the given directory,
+    /// using the given cargo home and environment variables.
+    pub fn discover_with<P, I>(cwd: P, cargo_home: Option<PathBuf>, vars: I) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let files = config_files(cwd.as_ref(), cargo_home.as_deref())?;
+
+        // Merge the files from lowest to highest precedence.
+        let mut root: Option<RawValue> = None;
+        for path in files.iter().rev() {
+            let definition = Definition::Path(path.clone());
+            let value: toml::Value = toml::from_str(&fs::read_to_string(path)?)?;
+
+            let value = RawValue::from_toml("", value, &definition)?;
+
+            match &mut root {
+                Some(root) => root.merge(value),
+                None => root = Some(value),
+            }
+        }
+
+        let vars: BTreeMap<String, String> = vars
+            .into_iter()
+            .filter(|(key, _)| key.starts_with("CARGO_"))
+            .collect();
+
+        let mut config = CargoConfig {
+            files,
+            build: BuildConfig::default(),
+            alias: BTreeMap::new(),
+            registries: BTreeMap::new(),
+            net: NetConfig::default(),
+            env: BTreeMap::new(),
+            root,
+            vars,
+        };
+
+        config.build = BuildConfig {
+            target: config
+                .env_list("CARGO_BUILD_TARGET", |value| vec![value.to_string()])
+                .or(config.get(&["build", "target"], RawValue::as_string_or_list)?),
+            rustflags: config
+                .env_list("CARGO_BUILD_RUSTFLAGS", split_whitespace)
+                .or(config.get(&["build", "rustflags"], RawValue::as_string_list)?),
+            target_dir: config
+                .env_value("CARGO_TARGET_DIR", PathBuf::from)
+                .or(config.env_value("CARGO_BUILD_TARGET_DIR", PathBuf::from))
+                .or(config.get(&["build", "target-dir"], as_path)?),
+            jobs: config
+                .parsed_env_value("CARGO_BUILD_JOBS")?
+                .or(config.get(&["build", "jobs"], as_integer)?),
+        };
+
+        config.net = NetConfig {
+            offline: config
+                .parsed_env_value("CARGO_NET_OFFLINE")?
+                .or(config.get(&["net", "offline"], RawValue::as_bool)?),
+        };
+
+        let mut alias = BTreeMap::new();
+        for (name, value) in config.table(&["alias"]).into_iter().flatten() {
+            let value = value
+                .as_string_list()
+                .ok_or_else(|| invalid(&format!("alias.{name}"), value.definition()))?;
+
+            alias.insert(name.clone(), value);
+        }
+
+        for (key, value) in &config.vars {
+            if let Some(name) = key.strip_prefix("CARGO_ALIAS_") {
+                alias.insert(
+                    name.to_lowercase(),
+                    split_whitespace(value)
+                        .into_iter()
+                        .map(|value| env_definition(key, value))
+                        .collect(),
+                );
+            }
+        }
+
+        let mut registries = BTreeMap::new();
+        for name in config
+            .table(&["registries"])
+            .into_iter()
+            .flat_map(BTreeMap::keys)
+        {
+            let registry = RegistryConfig {
+                index: config.get(&["registries", name, "index"], RawValue::as_string)?,
+                token: config.get(&["registries", name, "token"], RawValue::as_string)?,
+            };
+
+            registries.insert(name.clone(), registry);
+        }
+
+        for (key, value) in &config.vars {
+            let Some(rest) = key.strip_prefix("CARGO_REGISTRIES_") else {
+                continue;
+            };
+
+            let (name, field) = if let Some(name) = rest.strip_suffix("_INDEX") {
+                (name, "index")
+            } else if let Some(name) = rest.strip_suffix("_TOKEN") {
+                (name, "token")
+            } else {
+                continue;
+            };
+
+            // Registry names in environment variables are uppercased and have dashes replaced with underscores.
+            let name = registries
+                .keys()
+                .find(|registry| env_key(registry) == name)
+                .cloned()
+                .unwrap_or_else(|| name.to_lowercase());
+
+            let registry: &mut RegistryConfig = registries.entry(name).or_default();
+            let value = Some(env_definition(key, value.clone()));
+
+            match field {
+                "index" => registry.index = value,
+                _ => registry.token = value,
+            }
+        }
+
+        let mut env = BTreeMap::new();
+        if let Some(vars) = config.table(&["env"]) {
+            for (name, value) in vars {
+                let env_value = match value {
+                    RawValue::String(value, definition) => Value {
+                        value: EnvValue {
+                            value: value.clone(),
+                            force: false,
+                            relative: false,
+                        },
+                        definition: definition.clone(),
+                    },
+                    RawValue::Table(..) => {
+                        let key = format!("env.{name}");
+                        let value = value
+                            .get("value")
+                            .and_then(RawValue::as_string)
+                            .ok_or_else(|| invalid(&key, value.definition()))?;
+                        let flag = |flag| {
+                            config
+                                .get(&["env", name, flag], RawValue::as_bool)
+                                .map(|value| value.is_some_and(|value| value.value))
+                        };
+
+                        Value {
+                            value: EnvValue {
+                                value: value.value,
+                                force: flag("force")?,
+                                relative: flag("relative")?,
+                            },
+                            definition: value.definition,
+                        }
+                    }
+                    _ => return Err(invalid(&format!("env.{name}"), value.definition())),
+                };
+
+                env.insert(name.clone(), env_value);
+            }
+        }
+
+        config.alias = alias;
+        config.registries = registries;
+        config.env = env;
+
+        Ok(config)
+    }
+
+    /// Returns the `[target.<triple>]` table for the given target triple,
+    /// including the `CARGO_TARGET_<TRIPLE>_*` environment variables.
+    pub fn target(&self, triple: &str) -> Result<TargetConfig> {
+        let prefix = format!("CARGO_TARGET_{}", env_key(triple));
+
+        Ok(TargetConfig {
+            linker: self
+                .env_value(&format!("{prefix}_LINKER"), PathBuf::from)
+                .or(self.get(&["target", triple, "linker"], as_path)?),
+            runner: self
+                .env_list(&format!("{prefix}_RUNNER"), split_whitespace)
+                .or(self.get(&["target", triple, "runner"], RawValue::as_string_list)?),
+            rustflags: self
+                .env_list(&format!("{prefix}_RUSTFLAGS"), split_whitespace)
+                .or(self.get(&["target", triple, "rustflags"], RawValue::as_string_list)?),
+        })
+    }
+
+    /// Returns the target triples that have a `[target.<triple>]` table in the configuration files.
+    ///
+    /// Tables using `cfg()` expressions are included as written.
+    pub fn targets(&self) -> impl Iterator<Item = &str> {
+        self.table(&["target"])
+            .into_iter()
+            .flat_map(|targets| targets.keys().map(String::as_str))
+    }
+
+    /// Returns the target directory, resolved against the given current directory.
+    pub fn target_dir(&self, cwd: &Path) -> Option<PathBuf> {
+        self.build
+            .target_dir
+            .as_ref()
+            .map(|target_dir| target_dir.resolve_path(cwd))
+    }
+
+    fn table(&self, path: &[&str]) -> Option<&BTreeMap<String, RawValue>> {
+        self.root.as_ref()?.get_path(path)?.as_table()
+    }
+
+    /// Looks up a value in the configuration files, failing if it has the wrong type.
+    fn get<T>(&self, path: &[&str], f: impl Fn(&RawValue) -> Option<T>) -> Result<Option<T>> {
+        let Some(value) = self.root.as_ref().and_then(|root| root.get_path(path)) else {
+            return Ok(None);
+        };
+
+        f(value)
+            .map(Some)
+            .ok_or_else(|| invalid(&path.join("."), value.definition()))
+    }
+
+    fn env_value<T>(&self, key: &str, f: impl FnOnce(String) -> T) -> Option<Value<T>> {
+        self.vars
+            .get(key)
+            .map(|value| env_definition(key, f(value.clone())))
+    }
+
+    /// Like [env_value](Self::env_value) for lists, where every item is defined by the variable.
+    fn env_list(
+        &self,
+        key: &str,
+        f: impl FnOnce(&str) -> Vec<String>,
+    ) -> Option<Vec<Value<String>>> {
+        self.vars.get(key).map(|value| {
+            f(value)
+                .into_iter()
+                .map(|value| env_definition(key, value))
+                .collect()
+        })
+    }
+
+    fn parsed_env_value<T: std::str::FromStr>(&self, key: &str) -> Result<Option<Value<T>>> {
+        self.vars
+            .get(key)
+            .map(|value| {
+                value
+                    .parse()
+                    .map(|value| env_definition(key, value))
+                    .map_err(|_| invalid(key, &Definition::Environment(key.to_string())))
+            })
+            .transpose()
+    }
+}
+
+/// Returns the configuration files that apply to the given directory, from highest to lowest precedence.
+fn config_files(cwd: &Path, cargo_home: Option<&Path>) -> Result<Vec<PathBuf>> {
+    let mut visited = HashSet::new();
+    let mut files = Vec::new();
+
+    // Relative directories like "." have to be made absolute to reach the configuration of their parents.
+    let cwd = normalize(&env::current_dir()?.join(cwd));
+
+    let dirs = cwd
+        .ancestors()
+        .map(|dir| dir.join(".cargo"))
+        .chain(cargo_home.map(Path::to_path_buf));
+
+    for dir in dirs {
+        if !visited.insert(dir.clone()) {
+            continue;
+        }
+
+        // Cargo prefers the file without extension when both exist.
+        let legacy = dir.join("config");
+        let path = if legacy.is_file() {
+            legacy
+        } else {
+            dir.join("config.toml")
+        };
+
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+
+    Ok(files)
+}
+
+/// Converts a config key into the form used in environment variables, E.g. "x86_64-unknown-linux-gnu" to "X86_64_UNKNOWN_LINUX_GNU".
+fn env_key(key: &str) -> String {
+    key.chars()
+        .map(|c| match c {
+            '-' | '.' => '_',
+            c => c.to_ascii_uppercase(),
+        })
+        .collect()
+}
+
+fn env_definition<T>(key: &str, value: T) -> Value<T> {
+    Value {
+        value,
+        definition: Definition::Environment(key.to_string()),
+    }
+}
+
+fn split_whitespace(value: &str) -> Vec<String> {
+    value.split_whitespace().map(str::to_string).collect()
+}
+
+fn as_path(value: &RawValue) -> Option<Value<PathBuf>> {
+    value.as_string().map(|value| Value {
+        value: PathBuf::from(value.value),
+        definition: value.definition,
+    })
+}
+
+fn as_integer(value: &RawValue) -> Option<Value<i64>> {
+    match value {
+        RawValue::Integer(value, definition) => Some(Value {
+            value: *value,
+            definition: definition.clone(),
+        }),
+        _ => None,
+    }
+}
+
+fn invalid(key: &str, definition: &Definition) -> ParsingError {
+    ParsingError::Config(format!("invalid value for `{key}` in {definition}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cargo::home::temp_dir;
+
+    /// A workspace with a member, and a cargo home outside of it.
+    struct Tree {
+        root: PathBuf,
+    }
+
+    impl Tree {
+        fn new(files: &[(&str, &str)]) -> Self {
+            let root = temp_dir("config");
+
+            for (path, content) in files {
+                let path = root.join(path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, content).unwrap();
+            }
+
+            Tree { root }
+        }
+
+        fn discover(&self, dir: &str, vars: &[(&str, &str)]) -> Result<CargoConfig> {
+            CargoConfig::discover_with(
+                self.root.join(dir),
+                Some(self.root.join("home")),
+                vars.iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string())),
+            )
+        }
+    }
+
+    impl Drop for Tree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    fn tree() -> Tree {
+        Tree::new(&[
+            (
+                "home/config.toml",
+                "[build]\njobs = 1\nrustflags = [\"-Chome\"]\n\n[alias]\nb = \"build\"\n",
+            ),
+            (
+                "ws/.cargo/config.toml",
+                "[build]\njobs = 2\nrustflags = [\"-Cws\"]\ntarget-dir = \"out\"\n",
+            ),
+            ("ws/app/.cargo/config", "[build]\njobs = 3\n"),
+            ("ws/app/.cargo/config.toml", "[build]\njobs = 99\n"),
+            ("ws/app/src/.keep", ""),
+        ])
+    }
+
+    #[test]
+    fn precedence() {
+        let tree = tree();
+        let config = tree.discover("ws/app/src", &[]).unwrap();
+
+        assert_eq!(
+            config.files,
+            [
+                tree.root.join("ws/app/.cargo/config"),
+                tree.root.join("ws/.cargo/config.toml"),
+                tree.root.join("home/config.toml"),
+            ]
+        );
+
+        let jobs = config.build.jobs.clone().unwrap();
+        assert_eq!(jobs.value, 3);
+        assert_eq!(
+            jobs.definition,
+            Definition::Path(tree.root.join("ws/app/.cargo/config"))
+        );
+
+        // Lists are joined, with every item remembering its own file.
+        let rustflags = config.build.rustflags.clone().unwrap();
+        assert_eq!(
+            rustflags,
+            [
+                Value {
+                    value: "-Chome".to_string(),
+                    definition: Definition::Path(tree.root.join("home/config.toml")),
+                },
+                Value {
+                    value: "-Cws".to_string(),
+                    definition: Definition::Path(tree.root.join("ws/.cargo/config.toml")),
+                },
+            ]
+        );
+
+        assert_eq!(
+            config.target_dir(&tree.root.join("ws/app")),
+            Some(tree.root.join("ws/out"))
+        );
+        assert_eq!(config.alias["b"][0].value, "build");
+    }
+
+    #[test]
+    fn environment() {
+        let tree = tree();
+        let config = tree
+            .discover(
+                "ws/app",
+                &[
+                    ("CARGO_BUILD_JOBS", "8"),
+                    ("CARGO_TARGET_X86_64_UNKNOWN_LINUX_GNU_RUNNER", "qemu -L /"),
+                    ("CARGO_ALIAS_T", "test --all"),
+                    ("HOME", "/ignored"),
+                ],
+            )
+            .unwrap();
+
+        let jobs = config.build.jobs.clone().unwrap();
+        assert_eq!(jobs.value, 8);
+        assert_eq!(
+            jobs.definition,
+            Definition::Environment("CARGO_BUILD_JOBS".to_string())
+        );
+
+        let runner: Vec<String> = config
+            .target("x86_64-unknown-linux-gnu")
+            .unwrap()
+            .runner
+            .unwrap()
+            .into_iter()
+            .map(|value| value.value)
+            .collect();
+        assert_eq!(runner, ["qemu", "-L", "/"]);
+        assert_eq!(config.alias["t"].len(), 2);
+
+        assert!(matches!(
+            tree.discover("ws/app", &[("CARGO_BUILD_JOBS", "many")]),
+            Err(ParsingError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn relative_directory() {
+        let tree = tree();
+
+        // A path relative to the current directory, going through the filesystem root.
+        let cwd = env::current_dir().unwrap();
+        let mut relative = PathBuf::new();
+        for _ in cwd.components().skip(1) {
+            relative.push("..");
+        }
+        relative.push(
+            tree.root
+                .join("ws/app/src/../src")
+                .strip_prefix("/")
+                .unwrap(),
+        );
+
+        let config =
+            CargoConfig::discover_with(relative, Some(tree.root.join("home")), []).unwrap();
+
+        assert_eq!(config.files.len(), 3);
+        assert_eq!(config.build.jobs.unwrap().value, 3);
+    }
+
+    #[test]
+    fn unsupported_values() {
+        let tree = Tree::new(&[("ws/.cargo/config.toml", "[build]\njobs = 1.5\n")]);
+        assert!(matches!(
+            tree.discover("ws", &[]),
+            Err(ParsingError::Config(message)) if message.contains("build.jobs")
+        ));
+
+        let tree = Tree::new(&[(
+            "ws/.cargo/config.toml",
+            "[build]\nrustflags = [\"-C\", 1]\n",
+        )]);
+        assert!(matches!(
+            tree.discover("ws", &[]),
+            Err(ParsingError::Config(message)) if message.contains("build.rustflags")
+        ));
+    }
+}
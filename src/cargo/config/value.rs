@@ -0,0 +1,220 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+};
+
+use crate::cargo::{ParsingError, Result};
+
+/// Where a configuration value was defined.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Definition {
+    /// Defined in the configuration file at this path.
+    Path(PathBuf),
+    /// Defined by the given environment variable.
+    Environment(String),
+}
+
+impl Definition {
+    /// The directory relative paths in this definition are resolved against.
+    ///
+    /// For files this is the parent of the `.cargo` directory, for environment variables it's the given current directory.
+    pub fn root<'a>(&'a self, cwd: &'a Path) -> &'a Path {
+        match self {
+            Definition::Path(path) => path.parent().and_then(Path::parent).unwrap_or(cwd),
+            Definition::Environment(_) => cwd,
+        }
+    }
+}
+
+impl Display for Definition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Definition::Path(path) => write!(f, "{}", path.display()),
+            Definition::Environment(key) => write!(f, "environment variable `{key}`"),
+        }
+    }
+}
+
+/// A configuration value together with where it was defined.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Value<T> {
+    /// The value itself.
+    pub value: T,
+    /// Where the value was defined.
+    pub definition: Definition,
+}
+
+impl Value<PathBuf> {
+    /// Resolves a relative path against the directory of its definition.
+    pub fn resolve_path(&self, cwd: &Path) -> PathBuf {
+        self.definition.root(cwd).join(&self.value)
+    }
+}
+
+/// An untyped configuration value where every leaf remembers its definition.
+#[derive(Debug, Clone)]
+pub(crate) enum RawValue {
+    String(String, Definition),
+    Integer(i64, Definition),
+    Boolean(bool, Definition),
+    List(Vec<(String, Definition)>, Definition),
+    Table(BTreeMap<String, RawValue>, Definition),
+}
+
+impl RawValue {
+    /// Converts a parsed file, failing on the types cargo doesn't support, E.g. floats.
+    pub(crate) fn from_toml(
+        key: &str,
+        value: toml::Value,
+        definition: &Definition,
+    ) -> Result<Self> {
+        let invalid = |kind: &str| {
+            ParsingError::Config(format!(
+                "unsupported {kind} value for `{key}` in {definition}"
+            ))
+        };
+
+        Ok(match value {
+            toml::Value::String(value) => RawValue::String(value, definition.clone()),
+            toml::Value::Integer(value) => RawValue::Integer(value, definition.clone()),
+            toml::Value::Boolean(value) => RawValue::Boolean(value, definition.clone()),
+            toml::Value::Array(values) => RawValue::List(
+                values
+                    .into_iter()
+                    .map(|value| match value {
+                        toml::Value::String(value) => Ok((value, definition.clone())),
+                        value => Err(invalid(&format!("{} array item", value.type_str()))),
+                    })
+                    .collect::<Result<_>>()?,
+                definition.clone(),
+            ),
+            toml::Value::Table(table) => RawValue::Table(
+                table
+                    .into_iter()
+                    .map(|(name, value)| {
+                        let key = if key.is_empty() {
+                            name.clone()
+                        } else {
+                            format!("{key}.{name}")
+                        };
+                        let value = Self::from_toml(&key, value, definition)?;
+
+                        Ok((name, value))
+                    })
+                    .collect::<Result<_>>()?,
+                definition.clone(),
+            ),
+            toml::Value::Float(_) | toml::Value::Datetime(_) => {
+                return Err(invalid(value.type_str()))
+            }
+        })
+    }
+
+    /// Merges a value with higher precedence into this one.
+    ///
+    /// Tables are merged recursively, lists are joined with the higher precedence items placed last
+    /// and everything else is replaced.
+    pub(crate) fn merge(&mut self, other: RawValue) {
+        match (self, other) {
+            (RawValue::Table(table, _), RawValue::Table(other, _)) => {
+                for (key, value) in other {
+                    match table.get_mut(&key) {
+                        Some(existing) => existing.merge(value),
+                        None => {
+                            table.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (RawValue::List(list, definition), RawValue::List(other, other_definition)) => {
+                list.extend(other);
+                *definition = other_definition;
+            }
+            (this, other) => *this = other,
+        }
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<&RawValue> {
+        match self {
+            RawValue::Table(table, _) => table.get(key),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn get_path(&self, path: &[&str]) -> Option<&RawValue> {
+        path.iter().try_fold(self, |value, key| value.get(key))
+    }
+
+    pub(crate) fn definition(&self) -> &Definition {
+        match self {
+            RawValue::String(_, definition)
+            | RawValue::Integer(_, definition)
+            | RawValue::Boolean(_, definition)
+            | RawValue::List(_, definition)
+            | RawValue::Table(_, definition) => definition,
+        }
+    }
+
+    pub(crate) fn as_string(&self) -> Option<Value<String>> {
+        match self {
+            RawValue::String(value, definition) => Some(Value {
+                value: value.clone(),
+                definition: definition.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_bool(&self) -> Option<Value<bool>> {
+        match self {
+            RawValue::Boolean(value, definition) => Some(Value {
+                value: *value,
+                definition: definition.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Reads either a list of strings or a single string split on whitespace.
+    ///
+    /// Every item keeps its own definition, lists are joined across files when merged.
+    pub(crate) fn as_string_list(&self) -> Option<Vec<Value<String>>> {
+        match self {
+            RawValue::String(value, definition) => Some(
+                value
+                    .split_whitespace()
+                    .map(|value| Value {
+                        value: value.to_string(),
+                        definition: definition.clone(),
+                    })
+                    .collect(),
+            ),
+            RawValue::List(values, _) => Some(
+                values
+                    .iter()
+                    .map(|(value, definition)| Value {
+                        value: value.clone(),
+                        definition: definition.clone(),
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Reads either a list of strings or a single string as a one element list.
+    pub(crate) fn as_string_or_list(&self) -> Option<Vec<Value<String>>> {
+        match self {
+            RawValue::String(..) => self.as_string().map(|value| vec![value]),
+            _ => self.as_string_list(),
+        }
+    }
+
+    pub(crate) fn as_table(&self) -> Option<&BTreeMap<String, RawValue>> {
+        match self {
+            RawValue::Table(table, _) => Some(table),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+use target_lexicon::Triple;
+
+use crate::cargo::Features;
+
+#[derive(Debug, Clone)]
+pub struct DocConfig {
+    /// The package to document, passed as `-p`. Documents the current package if [None].
+    pub package: Option<String>,
+    /// Document the given binary instead of the library.
+    pub bin: Option<String>,
+    pub features: Option<Features>,
+    /// Document for the given target triple.
+    pub target: Option<Triple>,
+    /// Include private items in the output.
+    pub document_private_items: bool,
+    pub manifest_path: Option<PathBuf>,
+    /// The toolchain to document with when no [toolchain](crate::cargo::Cargo::toolchain) is set,
+    /// [NIGHTLY](Self::NIGHTLY) by default.
+    pub toolchain: String,
+}
+
+impl DocConfig {
+    /// A nightly toolchain known to emit a supported [format version](super::Crate::FORMAT_VERSIONS).
+    pub const NIGHTLY: &'static str = "nightly-2026-05-20";
+}
+
+impl Default for DocConfig {
+    fn default() -> Self {
+        DocConfig {
+            package: None,
+            bin: None,
+            features: None,
+            target: None,
+            document_private_items: false,
+            manifest_path: None,
+            toolchain: DocConfig::NIGHTLY.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiDiffConfig {
+    /// The package to compare, passed as `-p`. Compares the current package if [None].
+    pub package: Option<String>,
+    /// The version to compare against.
+    pub baseline: ApiSource,
+    /// The version being checked, usually the working tree.
+    pub current: ApiSource,
+    pub features: Option<Features>,
+    pub manifest_path: Option<PathBuf>,
+    /// The toolchain to document both versions with, see [DocConfig::toolchain].
+    pub toolchain: String,
+}
+
+impl ApiDiffConfig {
+    /// Compares the working tree against the given version.
+    pub fn new(baseline: ApiSource) -> Self {
+        ApiDiffConfig {
+            package: None,
+            baseline,
+            current: ApiSource::WorkingTree,
+            features: None,
+            manifest_path: None,
+            toolchain: DocConfig::NIGHTLY.to_string(),
+        }
+    }
+}
+
+/// Where a version of a package comes from.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum ApiSource {
+    /// The package as it is on disk.
+    #[default]
+    WorkingTree,
+    /// The package at a git revision, E.g. "v1.2.0" or "HEAD~1", checked out in a temporary worktree.
+    GitRevision(String),
+    /// A `.crate` file produced by `cargo package`, or downloaded from a registry.
+    #[cfg(feature = "archive")]
+    CrateFile(PathBuf),
+}
@@ -0,0 +1,845 @@
+use semver::Version;
+use serde_json::{Map, Value};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{self, Display, Formatter},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+#[cfg(feature = "archive")]
+use crate::cargo::package::CrateArchive;
+
+use super::{ApiDiffConfig, ApiSource, Crate, DocConfig, Item, ParsingError, Result};
+use crate::cargo::{build::select_package, home::temp_dir, Cargo, MetadataConfig};
+
+/// Keys of the kind specific data that only hold ids of other items, which differ between builds.
+const ID_KEYS: [&str; 6] = [
+    "id",
+    "impls",
+    "items",
+    "variants",
+    "fields",
+    "implementations",
+];
+
+/// How much a change breaks users of the API, from the least to the most breaking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Impact {
+    /// Nothing changed in the public API.
+    Patch,
+    /// Items were added.
+    Minor,
+    /// Items were removed or changed.
+    Major,
+}
+
+impl Impact {
+    /// The most breaking change allowed when going from one version to another.
+    ///
+    /// Like cargo, the minor version of "0.y.z" versions is treated as the major one,
+    /// and every "0.0.z" release is considered breaking.
+    pub fn allowed_by(old: &Version, new: &Version) -> Impact {
+        let breaking = if old.major > 0 {
+            new.major > old.major
+        } else if old.minor > 0 {
+            new.major > 0 || new.minor > old.minor
+        } else {
+            new.major > 0 || new.minor > 0 || new.patch > old.patch
+        };
+
+        if breaking {
+            Impact::Major
+        } else if old.major > 0 && new.minor > old.minor {
+            Impact::Minor
+        } else if old.major == 0 && new.patch > old.patch {
+            // "0.y.z" versions have no separate minor version, so additions are allowed in patch releases.
+            Impact::Minor
+        } else {
+            Impact::Patch
+        }
+    }
+}
+
+impl Display for Impact {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Impact::Patch => "patch",
+            Impact::Minor => "minor",
+            Impact::Major => "major",
+        })
+    }
+}
+
+/// An item of the public API.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiItem {
+    /// The path the item is reachable at, E.g. "payload::cargo::Cargo::new".
+    ///
+    /// Trait implementations of types are named after the trait, E.g. "payload::cargo::Cargo::<impl Default>".
+    pub path: String,
+    /// The kind of the item, E.g. "function".
+    pub kind: String,
+    /// The kind specific data of the item, without ids of other items.
+    pub signature: Value,
+    /// Whether adding the item breaks users of its parent: a trait item implementors have to provide,
+    /// a variant of an enum that can be matched exhaustively, or a field of a struct or variant that can
+    /// be built with a struct expression.
+    pub required: bool,
+}
+
+/// An item whose kind or signature changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiChange {
+    pub path: String,
+    pub old: ApiItem,
+    pub new: ApiItem,
+}
+
+/// The differences between the public API of two versions of a crate.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApiDiff {
+    pub added: Vec<ApiItem>,
+    pub removed: Vec<ApiItem>,
+    pub changed: Vec<ApiChange>,
+}
+
+impl ApiDiff {
+    /// Compares the public items of two versions of a crate.
+    pub fn new(old: &Crate, new: &Crate) -> Self {
+        let old_items = api_items(old);
+        let mut new_items = api_items(new);
+        let mut diff = ApiDiff::default();
+
+        for (path, old_item) in old_items {
+            match new_items.remove(&path) {
+                Some(new_item)
+                    if new_item.kind != old_item.kind
+                        || comparable_signature(&new_item) != comparable_signature(&old_item)
+                        || requirement_breaks(&old_item, &new_item) =>
+                {
+                    diff.changed.push(ApiChange {
+                        path,
+                        old: old_item,
+                        new: new_item,
+                    })
+                }
+                Some(_) => {}
+                None => diff.removed.push(old_item),
+            }
+        }
+
+        diff.added.extend(new_items.into_values());
+
+        diff
+    }
+
+    /// Returns true if the public API didn't change.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// The kind of release the changes require.
+    ///
+    /// Removing or changing an item is breaking, and so is adding a [required](ApiItem::required) item
+    /// to an existing trait, enum, struct or variant. Other additions only require a minor release.
+    pub fn impact(&self) -> Impact {
+        if !self.removed.is_empty() || !self.changed.is_empty() {
+            return Impact::Major;
+        }
+
+        let new_parents: Vec<&str> = self
+            .added
+            .iter()
+            .filter(|item| matches!(item.kind.as_str(), "trait" | "enum" | "struct" | "variant"))
+            .map(|item| item.path.as_str())
+            .collect();
+
+        let breaks_parent = self.added.iter().any(|item| {
+            item.required
+                && item
+                    .path
+                    .rsplit_once("::")
+                    .is_some_and(|(parent, _)| !new_parents.contains(&parent))
+        });
+
+        if breaks_parent {
+            Impact::Major
+        } else if !self.added.is_empty() {
+            Impact::Minor
+        } else {
+            Impact::Patch
+        }
+    }
+}
+
+/// The result of comparing two versions of a package.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiReport {
+    /// The name of the package.
+    pub package: String,
+    pub baseline_version: Version,
+    pub current_version: Version,
+    pub diff: ApiDiff,
+}
+
+impl ApiReport {
+    /// The kind of release the changes require.
+    pub fn required_impact(&self) -> Impact {
+        self.diff.impact()
+    }
+
+    /// The most breaking change allowed by the version bump.
+    pub fn allowed_impact(&self) -> Impact {
+        Impact::allowed_by(&self.baseline_version, &self.current_version)
+    }
+
+    /// Fails if the changes are more breaking than the version bump allows.
+    pub fn check(&self) -> Result<()> {
+        let required = self.required_impact();
+        let allowed = self.allowed_impact();
+
+        if required > allowed {
+            Err(ParsingError::SemverViolation { required, allowed })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Cargo {
+    /// Compares the public API of two versions of a package, using the rustdoc JSON output.
+    ///
+    /// Both versions are documented with [rustdoc_json](Self::rustdoc_json), so this needs a nightly toolchain.
+    pub fn api_diff(&mut self, config: ApiDiffConfig) -> Result<ApiReport> {
+        if config.baseline == config.current {
+            return Err(ParsingError::Selection(format!(
+                "the baseline and the current version are both {:?}",
+                config.baseline
+            )));
+        }
+
+        let metadata = self.metadata(MetadataConfig {
+            manifest_path: config.manifest_path.clone(),
+            no_deps: true,
+            ..Default::default()
+        })?;
+
+        let package = select_package(
+            &metadata,
+            config.package.as_deref(),
+            config.manifest_path.as_deref(),
+        )?;
+
+        let baseline = self.api_crate(
+            &config.baseline,
+            &package.name,
+            &package.manifest_path,
+            &config,
+        )?;
+        let current = self.api_crate(
+            &config.current,
+            &package.name,
+            &package.manifest_path,
+            &config,
+        )?;
+
+        Ok(ApiReport {
+            package: package.name.clone(),
+            baseline_version: crate_version(&baseline)?,
+            current_version: crate_version(&current)?,
+            diff: ApiDiff::new(&baseline, &current),
+        })
+    }
+
+    /// Documents the given version of a package.
+    fn api_crate(
+        &mut self,
+        source: &ApiSource,
+        package: &str,
+        manifest_path: &Path,
+        config: &ApiDiffConfig,
+    ) -> Result<Crate> {
+        let doc_config = |manifest_path: PathBuf| DocConfig {
+            package: Some(package.to_string()),
+            features: config.features.clone(),
+            manifest_path: Some(manifest_path),
+            toolchain: config.toolchain.clone(),
+            ..Default::default()
+        };
+
+        match source {
+            ApiSource::WorkingTree => self.rustdoc_json(&doc_config(manifest_path.to_path_buf())),
+            ApiSource::GitRevision(revision) => {
+                let package_dir = manifest_path.parent().unwrap_or(manifest_path);
+
+                let mut toplevel = Command::new("git");
+                toplevel
+                    .arg("-C")
+                    .arg(package_dir)
+                    .args(["rev-parse", "--show-toplevel"]);
+                let toplevel =
+                    PathBuf::from(std::str::from_utf8(&self.exec(&mut toplevel)?)?.trim_end());
+
+                let relative_manifest = manifest_path.strip_prefix(&toplevel).map_err(|_| {
+                    ParsingError::Selection(format!(
+                        "package `{package}` is not inside a git repository"
+                    ))
+                })?;
+
+                let worktree = temp_dir("worktree");
+
+                let mut add = Command::new("git");
+                add.arg("-C")
+                    .arg(&toplevel)
+                    .args(["worktree", "add", "--detach"])
+                    .arg(&worktree)
+                    .arg(revision);
+                self.exec(&mut add)?;
+
+                let krate = self.rustdoc_json(&doc_config(worktree.join(relative_manifest)));
+
+                let mut remove = Command::new("git");
+                remove
+                    .arg("-C")
+                    .arg(&toplevel)
+                    .args(["worktree", "remove", "--force"])
+                    .arg(&worktree);
+                let removed = self.exec(&mut remove);
+
+                let krate = krate?;
+                removed?;
+
+                Ok(krate)
+            }
+            #[cfg(feature = "archive")]
+            ApiSource::CrateFile(path) => {
+                let dir = temp_dir("crate");
+                let root = CrateArchive::open(path)?.extract(&dir)?;
+
+                let krate = self.rustdoc_json(&doc_config(root.join("Cargo.toml")));
+                let removed = std::fs::remove_dir_all(&dir);
+
+                let krate = krate?;
+                removed?;
+
+                Ok(krate)
+            }
+        }
+    }
+}
+
+fn crate_version(krate: &Crate) -> Result<Version> {
+    Ok(krate
+        .crate_version
+        .as_deref()
+        .ok_or_else(|| ParsingError::Rustdoc("the crate has no version".to_string()))?
+        .parse()?)
+}
+
+/// Collects the public items of a crate by path, together with the trait implementations of public types.
+fn api_items(krate: &Crate) -> BTreeMap<String, ApiItem> {
+    let mut items = BTreeMap::new();
+    let mut traits = HashSet::new();
+    let mut exhaustive = HashSet::new();
+
+    // Sorted by path, so traits, enums and structs come before their members.
+    for public_item in krate.public_items() {
+        let path = public_item.path_string();
+        let item = public_item.item;
+
+        if matches!(item.kind(), Some("struct" | "enum" | "union")) {
+            for trait_impl in krate.items(item.ids("impls")) {
+                if let Some(name) = trait_impl_name(trait_impl) {
+                    let path = format!("{path}::<impl {name}>");
+
+                    items.entry(path.clone()).or_insert_with(|| ApiItem {
+                        path,
+                        kind: "impl".to_string(),
+                        signature: signature(trait_impl),
+                        required: false,
+                    });
+                }
+            }
+        }
+
+        let kind = item.kind().unwrap_or_default().to_string();
+        let signature = signature(item);
+        let parent = &public_item.path[..public_item.path.len().saturating_sub(1)];
+        let required = (traits.contains(parent) && is_required(&kind, &signature))
+            || (exhaustive.contains(parent) && matches!(kind.as_str(), "variant" | "struct_field"));
+
+        if kind == "trait" {
+            traits.insert(public_item.path.clone());
+        } else if is_exhaustive(item) {
+            exhaustive.insert(public_item.path.clone());
+        }
+
+        items.entry(path.clone()).or_insert(ApiItem {
+            path,
+            kind,
+            signature,
+            required,
+        });
+    }
+
+    items
+}
+
+/// Whether users can match every variant of an enum, or build a struct or variant with a struct expression.
+fn is_exhaustive(item: &Item) -> bool {
+    if item.is_non_exhaustive() {
+        return false;
+    }
+
+    match (item.kind(), item.data()) {
+        (Some("enum"), _) => true,
+        (Some("struct" | "variant"), Some(data)) => {
+            // Older versions of the format keep the fields next to the kind instead of inside it.
+            let plain = data
+                .pointer("/kind/plain")
+                .or_else(|| data.pointer("/kind/struct"))
+                .unwrap_or(data);
+            let stripped = ["has_stripped_fields", "fields_stripped"]
+                .iter()
+                .any(|key| plain.get(key) == Some(&Value::Bool(true)));
+            let private_tuple_field = data
+                .pointer("/kind/tuple")
+                .and_then(Value::as_array)
+                .is_some_and(|fields| fields.iter().any(Value::is_null));
+
+            !stripped && !private_tuple_field
+        }
+        _ => false,
+    }
+}
+
+/// The name of the trait implemented by an impl, [None] for inherent and blanket impls.
+fn trait_impl_name(item: &Item) -> Option<String> {
+    let data = item.data()?;
+
+    if !data.get("blanket_impl").is_none_or(Value::is_null) {
+        return None;
+    }
+
+    let implemented = data
+        .get("trait")
+        .filter(|implemented| !implemented.is_null())?;
+    let name = implemented.get("path")?.as_str()?;
+
+    match implemented.get("args").filter(|args| !args.is_null()) {
+        Some(args) => Some(format!("{name}{}", normalize(args))),
+        None => Some(name.to_string()),
+    }
+}
+
+fn signature(item: &Item) -> Value {
+    item.data().map(normalize).unwrap_or_default()
+}
+
+/// Strips the ids of other items, so the same item compares equal between builds.
+fn normalize(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .filter(|(key, _)| !ID_KEYS.contains(&key.as_str()))
+                .map(|(key, value)| match (key.as_str(), value) {
+                    // The fields of tuple structs and variants are ids, or null when private.
+                    ("tuple", Value::Array(fields)) if fields.iter().all(is_id_or_null) => (
+                        key.clone(),
+                        Value::Array(
+                            fields
+                                .iter()
+                                .map(|field| Value::Bool(!field.is_null()))
+                                .collect(),
+                        ),
+                    ),
+                    _ => (key.clone(), normalize(value)),
+                })
+                .collect::<Map<_, _>>(),
+        ),
+        Value::Array(values) => Value::Array(values.iter().map(normalize).collect()),
+        value => value.clone(),
+    }
+}
+
+fn is_id_or_null(value: &Value) -> bool {
+    value.is_null() || value.is_number() || value.is_string()
+}
+
+/// The signature of an item without whether a function has a body, which only matters for trait
+/// items and is covered by [ApiItem::required].
+fn comparable_signature(item: &ApiItem) -> Value {
+    let mut signature = item.signature.clone();
+
+    if item.kind == "function" {
+        if let Some(signature) = signature.as_object_mut() {
+            signature.remove("has_body");
+        }
+    }
+
+    signature
+}
+
+/// Whether a change to [ApiItem::required] breaks users.
+///
+/// Variants and fields that are no longer required, E.g. because their enum became `#[non_exhaustive]`,
+/// can no longer be matched or built by users. Trait items that become required have to be provided
+/// by implementors, while giving them a default is fine.
+fn requirement_breaks(old: &ApiItem, new: &ApiItem) -> bool {
+    match old.kind.as_str() {
+        "variant" | "struct_field" => old.required && !new.required,
+        _ => !old.required && new.required,
+    }
+}
+
+/// Whether a trait item has to be provided by implementors of the trait.
+fn is_required(kind: &str, signature: &Value) -> bool {
+    let missing = |key: &str| signature.get(key).is_none_or(Value::is_null);
+
+    match kind {
+        "function" => signature.get("has_body") == Some(&Value::Bool(false)),
+        "assoc_const" => missing("value"),
+        "assoc_type" => missing("type"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn item(id: u32, name: &str, attrs: Value, inner: Value) -> Value {
+        json!({
+            "id": id,
+            "crate_id": 0,
+            "name": name,
+            "span": null,
+            "visibility": "public",
+            "docs": null,
+            "links": {},
+            "attrs": attrs,
+            "deprecation": null,
+            "inner": inner,
+        })
+    }
+
+    /// A crate whose root module contains the first of the given items, the others being its members.
+    fn krate(items: Vec<Value>) -> Crate {
+        let mut index = Map::new();
+        let module_items: Vec<&Value> = items.first().map(|item| &item["id"]).into_iter().collect();
+        let root = item(
+            0,
+            "lib",
+            json!([]),
+            json!({"module": {"is_crate": true, "items": module_items, "is_stripped": false}}),
+        );
+
+        for item in items.into_iter().chain([root]) {
+            index.insert(item["id"].to_string(), item);
+        }
+
+        serde_json::from_value(json!({
+            "root": 0,
+            "crate_version": "1.0.0",
+            "includes_private": false,
+            "index": index,
+            "paths": {},
+            "external_crates": {},
+            "format_version": 57,
+        }))
+        .unwrap()
+    }
+
+    fn enum_crate(attrs: Value, variants: &[&str]) -> Crate {
+        let ids: Vec<u32> = (2..).take(variants.len()).collect();
+        let mut items = vec![item(
+            1,
+            "Enum",
+            attrs,
+            json!({"enum": {"generics": {}, "has_stripped_variants": false, "variants": ids, "impls": []}}),
+        )];
+
+        for (id, name) in ids.iter().zip(variants) {
+            items.push(item(
+                *id,
+                name,
+                json!([]),
+                json!({"variant": {"kind": "plain"}}),
+            ));
+        }
+
+        krate(items)
+    }
+
+    fn struct_crate(attrs: Value, fields: &[&str], has_stripped_fields: bool) -> Crate {
+        let ids: Vec<u32> = (2..).take(fields.len()).collect();
+        let mut items = vec![item(
+            1,
+            "Struct",
+            attrs,
+            json!({"struct": {
+                "kind": {"plain": {"fields": ids, "has_stripped_fields": has_stripped_fields}},
+                "generics": {},
+                "impls": [],
+            }}),
+        )];
+
+        for (id, name) in ids.iter().zip(fields) {
+            items.push(item(
+                *id,
+                name,
+                json!([]),
+                json!({"struct_field": {"primitive": "u8"}}),
+            ));
+        }
+
+        krate(items)
+    }
+
+    /// A struct like `pub struct Tuple(pub u8, pub u16)`, with the given field types.
+    fn tuple_struct_crate(types: &[&str]) -> Crate {
+        let ids: Vec<u32> = (2..).take(types.len()).collect();
+        let mut items = vec![item(
+            1,
+            "Tuple",
+            json!([]),
+            json!({"struct": {"kind": {"tuple": ids}, "generics": {}, "impls": []}}),
+        )];
+
+        for (index, (id, primitive)) in ids.iter().zip(types).enumerate() {
+            items.push(item(
+                *id,
+                &index.to_string(),
+                json!([]),
+                json!({"struct_field": {"primitive": primitive}}),
+            ));
+        }
+
+        krate(items)
+    }
+
+    /// An enum with a single variant "A" with the given fields, a tuple variant if they are named by
+    /// their position.
+    fn variant_crate(enum_attrs: Value, variant_attrs: Value, fields: &[(&str, &str)]) -> Crate {
+        let ids: Vec<u32> = (3..).take(fields.len()).collect();
+        let kind = if fields.iter().all(|(name, _)| name.parse::<usize>().is_ok()) {
+            json!({"tuple": ids})
+        } else {
+            json!({"struct": {"fields": ids, "has_stripped_fields": false}})
+        };
+
+        let mut items = vec![
+            item(
+                1,
+                "Enum",
+                enum_attrs,
+                json!({"enum": {"generics": {}, "has_stripped_variants": false, "variants": [2], "impls": []}}),
+            ),
+            item(2, "A", variant_attrs, json!({"variant": {"kind": kind}})),
+        ];
+
+        for (id, (name, primitive)) in ids.iter().zip(fields) {
+            items.push(item(
+                *id,
+                name,
+                json!([]),
+                json!({"struct_field": {"primitive": primitive}}),
+            ));
+        }
+
+        krate(items)
+    }
+
+    fn trait_crate(methods: &[(&str, bool)]) -> Crate {
+        let ids: Vec<u32> = (2..).take(methods.len()).collect();
+        let mut items = vec![item(
+            1,
+            "Trait",
+            json!([]),
+            json!({"trait": {"items": ids, "generics": {}, "bounds": [], "implementations": []}}),
+        )];
+
+        for (id, (name, has_body)) in ids.iter().zip(methods) {
+            items.push(item(
+                *id,
+                name,
+                json!([]),
+                json!({"function": {"has_body": has_body}}),
+            ));
+        }
+
+        krate(items)
+    }
+
+    fn impact(old: &Crate, new: &Crate) -> Impact {
+        ApiDiff::new(old, new).impact()
+    }
+
+    #[test]
+    fn variants() {
+        let exhaustive = |variants: &[&str]| enum_crate(json!([]), variants);
+        let non_exhaustive = |variants: &[&str]| enum_crate(json!(["non_exhaustive"]), variants);
+
+        assert_eq!(
+            impact(&exhaustive(&["A"]), &exhaustive(&["A"])),
+            Impact::Patch
+        );
+        assert_eq!(
+            impact(&exhaustive(&["A"]), &exhaustive(&["A", "B"])),
+            Impact::Major
+        );
+        assert_eq!(
+            impact(&non_exhaustive(&["A"]), &non_exhaustive(&["A", "B"])),
+            Impact::Minor
+        );
+
+        // Older versions of the format keep the attribute as written.
+        let old_format = enum_crate(json!(["#[non_exhaustive]"]), &["A", "B"]);
+        assert_eq!(impact(&non_exhaustive(&["A"]), &old_format), Impact::Minor);
+
+        // Marking an enum `#[non_exhaustive]` is breaking, removing the attribute isn't.
+        assert_eq!(
+            impact(&exhaustive(&["A"]), &non_exhaustive(&["A"])),
+            Impact::Major
+        );
+        assert_eq!(
+            impact(&non_exhaustive(&["A"]), &exhaustive(&["A"])),
+            Impact::Patch
+        );
+
+        // The variants of a new enum are new too.
+        assert_eq!(
+            impact(&krate(Vec::new()), &exhaustive(&["A"])),
+            Impact::Minor
+        );
+    }
+
+    #[test]
+    fn fields() {
+        let public = |fields: &[&str]| struct_crate(json!([]), fields, false);
+        let private = |fields: &[&str]| struct_crate(json!([]), fields, true);
+        let non_exhaustive =
+            |fields: &[&str]| struct_crate(json!(["non_exhaustive"]), fields, false);
+
+        assert_eq!(impact(&public(&["a"]), &public(&["a", "b"])), Impact::Major);
+        assert_eq!(
+            impact(&private(&["a"]), &private(&["a", "b"])),
+            Impact::Minor
+        );
+        assert_eq!(
+            impact(&non_exhaustive(&["a"]), &non_exhaustive(&["a", "b"])),
+            Impact::Minor
+        );
+
+        // Adding a private field to a struct whose fields were all public is breaking too.
+        assert_eq!(impact(&public(&["a"]), &private(&["a"])), Impact::Major);
+    }
+
+    #[test]
+    fn tuple_fields() {
+        let meters = |types: &[&str]| tuple_struct_crate(types);
+
+        assert_eq!(impact(&meters(&["u32"]), &meters(&["u32"])), Impact::Patch);
+        assert_eq!(impact(&meters(&["u32"]), &meters(&["u64"])), Impact::Major);
+        assert_eq!(
+            impact(&meters(&["u32"]), &meters(&["u32", "u8"])),
+            Impact::Major
+        );
+
+        let tuple = |types: &[&str]| {
+            let fields: Vec<(String, &str)> = types
+                .iter()
+                .enumerate()
+                .map(|(index, primitive)| (index.to_string(), *primitive))
+                .collect();
+            let fields: Vec<(&str, &str)> = fields
+                .iter()
+                .map(|(name, primitive)| (name.as_str(), *primitive))
+                .collect();
+
+            variant_crate(json!([]), json!([]), &fields)
+        };
+
+        assert_eq!(impact(&tuple(&["u8"]), &tuple(&["u8"])), Impact::Patch);
+        assert_eq!(impact(&tuple(&["u8"]), &tuple(&["u16"])), Impact::Major);
+    }
+
+    #[test]
+    fn variant_fields() {
+        let exhaustive = |fields: &[(&str, &str)]| variant_crate(json!([]), json!([]), fields);
+
+        assert_eq!(
+            impact(&exhaustive(&[("x", "u8")]), &exhaustive(&[("x", "u8")])),
+            Impact::Patch
+        );
+        assert_eq!(
+            impact(&exhaustive(&[("x", "u8")]), &exhaustive(&[("x", "u16")])),
+            Impact::Major
+        );
+        assert_eq!(
+            impact(
+                &exhaustive(&[("x", "u8")]),
+                &exhaustive(&[("x", "u8"), ("y", "u8")])
+            ),
+            Impact::Major
+        );
+
+        // A `#[non_exhaustive]` enum still lets users build and match its variants with every field.
+        let non_exhaustive_enum =
+            |fields: &[(&str, &str)]| variant_crate(json!(["non_exhaustive"]), json!([]), fields);
+        assert_eq!(
+            impact(
+                &non_exhaustive_enum(&[("x", "u8")]),
+                &non_exhaustive_enum(&[("x", "u8"), ("y", "u8")])
+            ),
+            Impact::Major
+        );
+
+        let non_exhaustive_variant =
+            |fields: &[(&str, &str)]| variant_crate(json!([]), json!(["non_exhaustive"]), fields);
+        assert_eq!(
+            impact(
+                &non_exhaustive_variant(&[("x", "u8")]),
+                &non_exhaustive_variant(&[("x", "u8"), ("y", "u8")])
+            ),
+            Impact::Minor
+        );
+    }
+
+    #[test]
+    fn trait_items() {
+        let required = trait_crate(&[("a", false)]);
+
+        assert_eq!(
+            impact(&required, &trait_crate(&[("a", false), ("b", true)])),
+            Impact::Minor
+        );
+        assert_eq!(
+            impact(&required, &trait_crate(&[("a", false), ("b", false)])),
+            Impact::Major
+        );
+        assert_eq!(impact(&krate(Vec::new()), &required), Impact::Minor);
+
+        // Giving a required method a default body doesn't break implementors, removing it does.
+        let provided = trait_crate(&[("a", true)]);
+        assert_eq!(impact(&required, &provided), Impact::Patch);
+        assert_eq!(impact(&provided, &required), Impact::Major);
+    }
+
+    #[test]
+    fn allowed_by() {
+        let allowed =
+            |old: &str, new: &str| Impact::allowed_by(&old.parse().unwrap(), &new.parse().unwrap());
+
+        assert_eq!(allowed("1.2.3", "1.2.4"), Impact::Patch);
+        assert_eq!(allowed("1.2.3", "1.3.0"), Impact::Minor);
+        assert_eq!(allowed("1.2.3", "2.0.0"), Impact::Major);
+        assert_eq!(allowed("0.2.3", "0.2.4"), Impact::Minor);
+        assert_eq!(allowed("0.2.3", "0.3.0"), Impact::Major);
+        assert_eq!(allowed("0.0.3", "0.0.4"), Impact::Major);
+        assert_eq!(allowed("1.2.3", "1.2.3"), Impact::Patch);
+    }
+}
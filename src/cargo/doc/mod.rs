@@ -0,0 +1,156 @@
+use serde::Deserialize;
+use std::{fs, ops::RangeInclusive, path::PathBuf};
+
+use super::{
+    build::select_package,
+    metadata::{Package, TargetKind},
+    Cargo, MetadataConfig, ParsingError, Result,
+};
+
+mod config;
+mod diff;
+mod model;
+
+pub use config::{ApiDiffConfig, ApiSource, DocConfig};
+pub use diff::{ApiChange, ApiDiff, ApiItem, ApiReport, Impact};
+pub use model::{
+    Crate, Deprecation, ExternalCrate, Id, Item, ItemSummary, PublicItem, Span, Visibility,
+};
+
+/// Only the version of the format is read first, the rest of the layout depends on it.
+#[derive(Deserialize)]
+struct FormatVersion {
+    format_version: u32,
+}
+
+impl Crate {
+    /// The versions of the format the model understands, from the rename of `import` items to `use`
+    /// to the latest known version.
+    pub const FORMAT_VERSIONS: RangeInclusive<u32> = 34..=57;
+
+    /// Parses the documentation from the JSON emitted by rustdoc.
+    ///
+    /// Fails with [ParsingError::Rustdoc] if the format version isn't in [FORMAT_VERSIONS](Self::FORMAT_VERSIONS).
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let FormatVersion { format_version } = serde_json::from_slice(bytes)?;
+
+        if !Self::FORMAT_VERSIONS.contains(&format_version) {
+            return Err(ParsingError::Rustdoc(format!(
+                "format version {format_version} is not supported, only versions {} to {} are, \
+                 document with the `{}` toolchain instead",
+                Self::FORMAT_VERSIONS.start(),
+                Self::FORMAT_VERSIONS.end(),
+                DocConfig::NIGHTLY
+            )));
+        }
+
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+impl Cargo {
+    /// Runs `cargo rustdoc` with the JSON output format and returns the path of the generated file.
+    ///
+    /// The JSON output is unstable, so the [pinned nightly](DocConfig::toolchain) toolchain is used unless
+    /// another [toolchain](Cargo::toolchain) is set.
+    pub fn rustdoc_json_path(&mut self, config: &DocConfig) -> Result<PathBuf> {
+        let metadata = self.metadata(MetadataConfig {
+            manifest_path: config.manifest_path.clone(),
+            no_deps: true,
+            ..Default::default()
+        })?;
+
+        let package = select_package(
+            &metadata,
+            config.package.as_deref(),
+            config.manifest_path.as_deref(),
+        )?;
+
+        let target_name = doc_target_name(package, config.bin.as_deref())?;
+
+        let toolchain = self.toolchain.as_deref().unwrap_or(&config.toolchain);
+        let mut command = self.toolchain_command(Some(toolchain), ["rustdoc"]);
+
+        command.arg("--package").arg(&package.name);
+
+        match &config.bin {
+            Some(bin) => command.arg("--bin").arg(bin),
+            None => command.arg("--lib"),
+        };
+
+        if let Some(features) = &config.features {
+            features.apply(&mut command);
+        }
+
+        if let Some(target) = &config.target {
+            command.arg("--target").arg(target.to_string());
+        }
+
+        if let Some(manifest_path) = &config.manifest_path {
+            command.arg("--manifest-path").arg(manifest_path);
+        }
+
+        command.args(["--", "-Zunstable-options", "--output-format", "json"]);
+
+        if config.document_private_items {
+            command.arg("--document-private-items");
+        }
+
+        self.exec(&mut command)?;
+
+        let mut doc_dir = metadata.target_directory.clone();
+        if let Some(target) = &config.target {
+            doc_dir.push(target.to_string());
+        }
+        doc_dir.push("doc");
+
+        Ok(doc_dir.join(format!("{}.json", target_name.replace('-', "_"))))
+    }
+
+    /// Runs `cargo rustdoc` with the JSON output format and parses the result.
+    pub fn rustdoc_json(&mut self, config: &DocConfig) -> Result<Crate> {
+        let path = self.rustdoc_json_path(config)?;
+
+        Crate::from_slice(&fs::read(path)?)
+    }
+
+    /// Like [rustdoc_json](Self::rustdoc_json) but parses the result with the `rustdoc-types` crate.
+    ///
+    /// The format version of `rustdoc-types` has to match the one of the toolchain.
+    #[cfg(feature = "rustdoc-types")]
+    pub fn rustdoc_types(&mut self, config: &DocConfig) -> Result<rustdoc_types::Crate> {
+        let path = self.rustdoc_json_path(config)?;
+
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+}
+
+/// The name of the target that gets documented, used for the name of the output file.
+fn doc_target_name<'a>(package: &'a Package, bin: Option<&str>) -> Result<&'a str> {
+    let target = match bin {
+        Some(bin) => package
+            .targets
+            .iter()
+            .find(|target| target.name == bin && target.kind.contains(&TargetKind::Bin)),
+        None => package.targets.iter().find(|target| {
+            target.kind.iter().any(|kind| {
+                matches!(
+                    kind,
+                    TargetKind::Lib
+                        | TargetKind::Rlib
+                        | TargetKind::Dylib
+                        | TargetKind::Cdylib
+                        | TargetKind::Staticlib
+                        | TargetKind::ProcMacro
+                )
+            })
+        }),
+    };
+
+    target.map(|target| target.name.as_str()).ok_or_else(|| {
+        ParsingError::Selection(match bin {
+            Some(bin) => format!("binary `{bin}` not found in package `{}`", package.name),
+            None => format!("package `{}` has no library", package.name),
+        })
+    })
+}
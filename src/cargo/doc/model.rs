@@ -0,0 +1,547 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+    path::PathBuf,
+};
+
+/// The documentation of a crate, as emitted by `rustdoc --output-format json`.
+///
+/// The format is unstable, so only the parts that rarely change are typed.
+/// The kind specific data of each item is kept as raw JSON in [Item::inner].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Crate {
+    /// The id of the root module of the crate.
+    pub root: Id,
+    /// The version of the crate, if any was given to rustdoc.
+    pub crate_version: Option<String>,
+    /// Whether private items were documented.
+    pub includes_private: bool,
+    /// Every item of the crate, by id.
+    pub index: HashMap<Id, Item>,
+    /// The paths of items, including items from other crates.
+    pub paths: HashMap<Id, ItemSummary>,
+    /// The crates referenced by items, by crate id.
+    pub external_crates: HashMap<u32, ExternalCrate>,
+    /// The version of the format, incremented on every change.
+    pub format_version: u32,
+}
+
+/// The id of an [Item].
+///
+/// Older versions of the format use strings such as "0:12:345" while newer ones use integers,
+/// both are stored as strings.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(from = "RawId")]
+pub struct Id(pub String);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawId {
+    Integer(u64),
+    String(String),
+}
+
+impl From<RawId> for Id {
+    fn from(id: RawId) -> Self {
+        match id {
+            RawId::Integer(id) => Id(id.to_string()),
+            RawId::String(id) => Id(id),
+        }
+    }
+}
+
+impl Display for Id {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A documented item.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Item {
+    pub id: Id,
+    /// The crate the item belongs to, 0 for the documented crate.
+    pub crate_id: u32,
+    /// The name of the item, [None] for items such as impls.
+    pub name: Option<String>,
+    /// Where the item is defined, [None] for items generated by the compiler.
+    pub span: Option<Span>,
+    pub visibility: Visibility,
+    /// The documentation of the item.
+    pub docs: Option<String>,
+    /// Intra doc links in the documentation, with the id of the item they point to.
+    #[serde(default)]
+    pub links: HashMap<String, Id>,
+    /// The attributes of the item, whose representation depends on the format version.
+    #[serde(default)]
+    pub attrs: Value,
+    pub deprecation: Option<Deprecation>,
+    /// The kind of the item and its kind specific data, E.g. `{"function": {...}}`.
+    pub inner: Value,
+}
+
+impl Item {
+    /// The kind of the item, E.g. "module", "struct" or "function".
+    pub fn kind(&self) -> Option<&str> {
+        self.inner.as_object()?.keys().next().map(String::as_str)
+    }
+
+    /// The kind specific data of the item.
+    pub fn data(&self) -> Option<&Value> {
+        self.inner.as_object()?.values().next()
+    }
+
+    /// Whether the item is declared `pub`.
+    pub fn is_public(&self) -> bool {
+        self.visibility == Visibility::Public
+    }
+
+    /// Whether the item is marked `#[non_exhaustive]`.
+    pub fn is_non_exhaustive(&self) -> bool {
+        self.attrs
+            .as_array()
+            .into_iter()
+            .flatten()
+            .any(|attr| match attr {
+                // E.g. "#[non_exhaustive]" in older versions of the format and "non_exhaustive" in newer ones.
+                Value::String(attr) => {
+                    matches!(attr.as_str(), "non_exhaustive" | "#[non_exhaustive]")
+                        || attr.contains("NonExhaustive")
+                }
+                Value::Object(attr) => attr.contains_key("non_exhaustive"),
+                _ => false,
+            })
+    }
+
+    /// The ids of the fields of a struct or variant, E.g. `{"kind": {"tuple": [12, null]}}`.
+    ///
+    /// Private fields of tuple structs are null and left out.
+    pub(super) fn fields(&self) -> Vec<Id> {
+        let kind = self.data().and_then(|data| data.get("kind"));
+
+        ids(kind.and_then(|kind| {
+            kind.pointer("/plain/fields")
+                .or_else(|| kind.pointer("/struct/fields"))
+                .or_else(|| kind.get("tuple"))
+        }))
+    }
+
+    /// The ids in the given array of the kind specific data, E.g. "items" for modules.
+    pub(super) fn ids(&self, field: &str) -> Vec<Id> {
+        ids(self.data().and_then(|data| data.get(field)))
+    }
+}
+
+pub(super) fn ids(value: Option<&Value>) -> Vec<Id> {
+    value
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|id| serde_json::from_value(id.clone()).ok())
+        .collect()
+}
+
+/// The visibility of an [Item].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    /// Declared `pub`.
+    Public,
+    /// The implied visibility of items such as enum variants and trait items.
+    Default,
+    /// Declared `pub(crate)`.
+    Crate,
+    /// Declared `pub(in path)`.
+    Restricted { parent: Id, path: String },
+}
+
+/// A location in the source code.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Span {
+    /// The file the span is in, relative to the directory rustdoc was run in.
+    pub filename: PathBuf,
+    /// The line and column the span starts at, the line starting at 1.
+    pub begin: (usize, usize),
+    /// The line and column the span ends at, the line starting at 1.
+    pub end: (usize, usize),
+}
+
+/// The `#[deprecated]` attribute of an [Item].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Deprecation {
+    pub since: Option<String>,
+    pub note: Option<String>,
+}
+
+/// The path of an item, possibly from another crate.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ItemSummary {
+    pub crate_id: u32,
+    /// The full path of the item, E.g. `["std", "vec", "Vec"]`.
+    pub path: Vec<String>,
+    /// The kind of the item, E.g. "struct".
+    pub kind: String,
+}
+
+/// A crate referenced by the documented crate.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExternalCrate {
+    pub name: String,
+    /// The url the documentation of the crate is hosted at.
+    pub html_root_url: Option<String>,
+}
+
+/// An item reachable from outside the crate, with the path it's reachable at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicItem<'a> {
+    /// The path of the item, starting with the crate name, E.g. `["payload", "cargo", "Cargo"]`.
+    pub path: Vec<String>,
+    pub item: &'a Item,
+}
+
+impl PublicItem<'_> {
+    /// The path of the item joined with "::".
+    pub fn path_string(&self) -> String {
+        self.path.join("::")
+    }
+}
+
+impl Crate {
+    /// The name of the crate, from the root module.
+    pub fn name(&self) -> Option<&str> {
+        self.index.get(&self.root)?.name.as_deref()
+    }
+
+    /// Returns every item reachable from outside the crate, sorted by path.
+    ///
+    /// Re-exports are followed, so items can appear once for every path they're reachable at.
+    /// Besides module items this includes enum variants and their fields, public struct fields,
+    /// trait items and public items of inherent impls.
+    pub fn public_items(&self) -> Vec<PublicItem<'_>> {
+        let mut items = Vec::new();
+
+        if let Some(root) = self.index.get(&self.root) {
+            let path = root.name.iter().cloned().collect();
+            self.walk_module(root, path, &mut HashSet::new(), &mut items);
+        }
+
+        items.sort_by(|a, b| a.path.cmp(&b.path));
+
+        items
+    }
+
+    fn walk_module<'a>(
+        &'a self,
+        module: &'a Item,
+        path: Vec<String>,
+        visited: &mut HashSet<Id>,
+        items: &mut Vec<PublicItem<'a>>,
+    ) {
+        // Glob re-exports can form cycles.
+        if !visited.insert(module.id.clone()) {
+            return;
+        }
+
+        for id in module.ids("items") {
+            let Some(item) = self.index.get(&id) else {
+                continue;
+            };
+
+            if !item.is_public() {
+                continue;
+            }
+
+            if item.kind() != Some("use") {
+                if let Some(name) = &item.name {
+                    self.push_item(item, child_path(&path, name), visited, items);
+                }
+
+                continue;
+            }
+
+            let Some(data) = item.data() else {
+                continue;
+            };
+
+            let target = data
+                .get("id")
+                .and_then(|id| serde_json::from_value::<Id>(id.clone()).ok())
+                .and_then(|id| self.index.get(&id));
+            let is_glob = data.get("is_glob").and_then(Value::as_bool) == Some(true);
+
+            match target {
+                Some(target) if is_glob => {
+                    if target.kind() == Some("module") {
+                        self.walk_module(target, path.clone(), visited, items);
+                    }
+                }
+                Some(target) => {
+                    if let Some(name) = data.get("name").and_then(Value::as_str) {
+                        self.push_item(target, child_path(&path, name), visited, items);
+                    }
+                }
+                // A re-export of an item from another crate.
+                None => {
+                    if let Some(name) = data.get("name").and_then(Value::as_str) {
+                        items.push(PublicItem {
+                            path: child_path(&path, name),
+                            item,
+                        });
+                    }
+                }
+            }
+        }
+
+        visited.remove(&module.id);
+    }
+
+    fn push_item<'a>(
+        &'a self,
+        item: &'a Item,
+        path: Vec<String>,
+        visited: &mut HashSet<Id>,
+        items: &mut Vec<PublicItem<'a>>,
+    ) {
+        items.push(PublicItem {
+            path: path.clone(),
+            item,
+        });
+
+        let mut members = Vec::new();
+
+        match item.kind() {
+            Some("module") => return self.walk_module(item, path, visited, items),
+            Some("enum") => members.extend(self.items(item.ids("variants"))),
+            Some("trait") => members.extend(self.items(item.ids("items"))),
+            Some("struct") => {
+                members.extend(self.items(item.fields()).filter(|field| field.is_public()))
+            }
+            _ => {}
+        }
+
+        if matches!(item.kind(), Some("struct" | "enum" | "union")) {
+            let inherent_impls = self.items(item.ids("impls")).filter(|item| {
+                item.data()
+                    .is_some_and(|data| data.get("trait").is_none_or(Value::is_null))
+            });
+
+            for inherent_impl in inherent_impls {
+                members.extend(
+                    self.items(inherent_impl.ids("items"))
+                        .filter(|item| item.is_public()),
+                );
+            }
+        }
+
+        for member in members {
+            if let Some(name) = &member.name {
+                let path = child_path(&path, name);
+
+                // The fields of variants are as public as the enum, E.g. "Enum::Variant::0".
+                if member.kind() == Some("variant") {
+                    for field in self.items(member.fields()) {
+                        if let Some(name) = &field.name {
+                            items.push(PublicItem {
+                                path: child_path(&path, name),
+                                item: field,
+                            });
+                        }
+                    }
+                }
+
+                items.push(PublicItem { path, item: member });
+            }
+        }
+    }
+
+    pub(super) fn items(&self, ids: Vec<Id>) -> impl Iterator<Item = &Item> {
+        ids.into_iter().filter_map(|id| self.index.get(&id))
+    }
+}
+
+fn child_path(path: &[String], name: &str) -> Vec<String> {
+    let mut path = path.to_vec();
+    path.push(name.to_string());
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn item(id: u32, name: Option<&str>, visibility: Value, inner: Value) -> (String, Value) {
+        (
+            id.to_string(),
+            json!({
+                "id": id,
+                "crate_id": 0,
+                "name": name,
+                "span": null,
+                "visibility": visibility,
+                "docs": null,
+                "links": {},
+                "attrs": [],
+                "deprecation": null,
+                "inner": inner,
+            }),
+        )
+    }
+
+    fn module(items: &[u32]) -> Value {
+        json!({ "module": { "is_crate": false, "items": items, "is_stripped": false } })
+    }
+
+    fn reexport(name: &str, id: u32, is_glob: bool) -> Value {
+        json!({ "use": { "source": name, "name": name, "id": id, "is_glob": is_glob } })
+    }
+
+    fn function() -> Value {
+        json!({ "function": {} })
+    }
+
+    /// A crate like:
+    ///
+    /// ```ignore
+    /// pub mod inner {
+    ///     pub struct Thing { pub public: u8, private: u8 }
+    ///     impl Thing { pub fn new() -> Self; fn helper(&self); }
+    ///     impl Display for Thing { fn fmt(..); }
+    ///     pub mod cycle { pub use crate::inner::*; }
+    ///     pub(crate) fn private_fn();
+    /// }
+    /// pub use inner::Thing as Alias;
+    /// pub use inner::*;
+    /// pub use std::vec::Vec;
+    /// mod hidden { pub fn unreachable(); }
+    /// ```
+    fn krate() -> Crate {
+        let index: serde_json::Map<String, Value> = [
+            item(0, Some("demo"), json!("public"), module(&[1, 2, 3, 4, 10])),
+            item(1, Some("inner"), json!("public"), module(&[5, 6, 7])),
+            item(2, None, json!("public"), reexport("Alias", 5, false)),
+            item(3, None, json!("public"), reexport("inner", 1, true)),
+            item(4, None, json!("public"), reexport("Vec", 100, false)),
+            item(
+                5,
+                Some("Thing"),
+                json!("public"),
+                json!({ "struct": {
+                    "kind": { "plain": { "fields": [8, 9], "has_stripped_fields": false } },
+                    "generics": { "params": [], "where_predicates": [] },
+                    "impls": [11, 12],
+                } }),
+            ),
+            item(6, Some("cycle"), json!("public"), module(&[16])),
+            item(7, Some("private_fn"), json!("crate"), function()),
+            item(
+                8,
+                Some("public"),
+                json!("public"),
+                json!({ "struct_field": {} }),
+            ),
+            item(
+                9,
+                Some("private"),
+                json!("crate"),
+                json!({ "struct_field": {} }),
+            ),
+            item(10, Some("hidden"), json!("crate"), module(&[17])),
+            item(
+                11,
+                None,
+                json!("default"),
+                json!({ "impl": { "trait": null, "items": [13, 14] } }),
+            ),
+            item(
+                12,
+                None,
+                json!("default"),
+                json!({ "impl": { "trait": { "path": "Display", "id": 200 }, "items": [15] } }),
+            ),
+            item(13, Some("new"), json!("public"), function()),
+            item(14, Some("helper"), json!("crate"), function()),
+            item(15, Some("fmt"), json!("default"), function()),
+            item(16, None, json!("public"), reexport("inner", 1, true)),
+            item(17, Some("unreachable"), json!("public"), function()),
+        ]
+        .into_iter()
+        .collect();
+
+        serde_json::from_value(json!({
+            "root": 0,
+            "crate_version": "0.1.0",
+            "includes_private": false,
+            "index": index,
+            "paths": {},
+            "external_crates": {},
+            "format_version": 57,
+        }))
+        .unwrap()
+    }
+
+    fn paths(krate: &Crate) -> Vec<String> {
+        krate
+            .public_items()
+            .iter()
+            .map(PublicItem::path_string)
+            .collect()
+    }
+
+    #[test]
+    fn public_items() {
+        let krate = krate();
+        let paths = paths(&krate);
+
+        // Renamed and glob re-exports add paths, and the glob re-export of "inner" inside of itself stops
+        // the walk. Private fields and functions, the items of trait impls and private modules are left out.
+        assert_eq!(
+            paths,
+            [
+                "demo::Alias",
+                "demo::Alias::new",
+                "demo::Alias::public",
+                "demo::Thing",
+                "demo::Thing::new",
+                "demo::Thing::public",
+                "demo::Vec",
+                "demo::cycle",
+                "demo::inner",
+                "demo::inner::Thing",
+                "demo::inner::Thing::new",
+                "demo::inner::Thing::public",
+                "demo::inner::cycle",
+            ]
+        );
+
+        assert_eq!(
+            krate
+                .public_items()
+                .iter()
+                .find(|item| item.path_string() == "demo::Alias")
+                .unwrap()
+                .item
+                .name
+                .as_deref(),
+            Some("Thing")
+        );
+    }
+
+    #[test]
+    fn format_versions() {
+        let mut json = serde_json::to_value(krate()).unwrap();
+        assert!(Crate::from_slice(json.to_string().as_bytes()).is_ok());
+
+        for version in [33, 58] {
+            json["format_version"] = json!(version);
+
+            assert!(matches!(
+                Crate::from_slice(json.to_string().as_bytes()),
+                Err(crate::cargo::ParsingError::Rustdoc(message))
+                    if message.contains(crate::cargo::DocConfig::NIGHTLY)
+            ));
+        }
+    }
+}
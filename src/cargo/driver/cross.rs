@@ -0,0 +1,322 @@
+use std::path::{Path, PathBuf};
+
+use which::which;
+
+use super::Wrapper;
+
+/// Runs cargo commands through [cross](https://github.com/cross-rs/cross), which builds inside a container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cross {
+    /// Path to the `cross` executable, or any wrapper accepting the same arguments.
+    pub path: PathBuf,
+    /// Paths inside the container and the host paths mounted at them.
+    pub mounts: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Cross {
+    /// Uses the `cross` executable found with [which](which::which), with the workspace mounted at "/project"
+    /// and the target directory at "/target" like cross does by default.
+    pub fn new<R: Into<PathBuf>, T: Into<PathBuf>>(workspace_root: R, target_dir: T) -> Self {
+        Cross {
+            path: which("cross").unwrap_or_else(|_| PathBuf::from("cross")),
+            mounts: vec![
+                (PathBuf::from("/project"), workspace_root.into()),
+                (PathBuf::from("/target"), target_dir.into()),
+            ],
+        }
+    }
+
+    /// Uses the workspace root and target directory from the metadata.
+    #[cfg(feature = "json")]
+    pub fn for_metadata(metadata: &crate::cargo::Metadata) -> Self {
+        Self::new(&metadata.workspace_root, &metadata.target_directory)
+    }
+
+    /// Sets the path to the `cross` executable.
+    pub fn path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Adds a mount of a host path inside the container, E.g. when the image mounts the workspace elsewhere
+    /// or to map the "/cargo" directory cross mounts the cargo home at.
+    pub fn mount<C: Into<PathBuf>, H: Into<PathBuf>>(mut self, container: C, host: H) -> Self {
+        self.mounts.push((container.into(), host.into()));
+        self
+    }
+
+    /// Removes every mount, for images that mount the host paths at the same path.
+    pub fn without_mounts(mut self) -> Self {
+        self.mounts.clear();
+        self
+    }
+
+    /// Maps a path inside the container to the host path it's mounted from, using the most specific mount.
+    ///
+    /// Paths outside of every mount are returned unchanged.
+    pub fn map_path(&self, path: &Path) -> PathBuf {
+        self.mounts
+            .iter()
+            .filter_map(|(container, host)| {
+                let relative = path.strip_prefix(container).ok()?;
+                Some((container.components().count(), host.join(relative)))
+            })
+            .max_by_key(|(depth, _)| *depth)
+            .map_or_else(|| path.to_path_buf(), |(_, host)| host)
+    }
+}
+
+impl Wrapper for Cross {
+    fn program(&self) -> &Path {
+        &self.path
+    }
+
+    fn map_path(&self, path: &Path) -> PathBuf {
+        Cross::map_path(self, path)
+    }
+}
+
+#[cfg(feature = "toml")]
+pub use config::{CrossConfig, CrossEnv, CrossTarget, PreBuild};
+
+#[cfg(feature = "toml")]
+mod config {
+    use serde::Deserialize;
+    use std::{
+        collections::BTreeMap,
+        env, fs,
+        path::{Path, PathBuf},
+        str::FromStr,
+    };
+
+    use crate::cargo::{ParsingError, Result};
+
+    /// The configuration of cross, read from `Cross.toml`.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+    pub struct CrossConfig {
+        /// The `[build]` table, applying to every target.
+        #[serde(default)]
+        pub build: CrossTarget,
+        /// The `[target.<triple>]` tables.
+        #[serde(default, rename = "target")]
+        pub targets: BTreeMap<String, CrossTarget>,
+    }
+
+    /// The settings of the `[build]` or a `[target.<triple>]` table.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    pub struct CrossTarget {
+        /// The container image to build in, E.g. "ghcr.io/cross-rs/aarch64-unknown-linux-gnu:main".
+        #[serde(default, deserialize_with = "image")]
+        pub image: Option<String>,
+        /// Commands run in the image before building, or a script to run.
+        pub pre_build: Option<PreBuild>,
+        /// Environment variables forwarded to the container.
+        #[serde(default)]
+        pub env: CrossEnv,
+        /// The target to build for when none is given, only used in the `[build]` table.
+        pub default_target: Option<String>,
+        /// The runner used to execute binaries, E.g. "qemu-user".
+        pub runner: Option<String>,
+        /// Whether to build the standard library with xargo.
+        pub xargo: Option<bool>,
+        /// Whether to build the standard library with `-Zbuild-std`.
+        pub build_std: Option<bool>,
+    }
+
+    /// The `env` table of [CrossTarget].
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+    pub struct CrossEnv {
+        /// Environment variables forwarded from the host, or set, when in the form "NAME=value".
+        #[serde(default)]
+        pub passthrough: Vec<String>,
+        /// Environment variables holding paths that are mounted into the container.
+        #[serde(default)]
+        pub volumes: Vec<String>,
+    }
+
+    /// Commands run in the image before building.
+    #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+    #[serde(untagged)]
+    pub enum PreBuild {
+        /// Commands run one after the other, E.g. "apt-get install libssl-dev".
+        Commands(Vec<String>),
+        /// A script copied into the image and run.
+        Script(PathBuf),
+    }
+
+    /// Accepts the image either as a string or as a table with a name.
+    fn image<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<String>, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Image {
+            Name(String),
+            Table { name: String },
+        }
+
+        Ok(
+            Option::<Image>::deserialize(deserializer)?.map(|image| match image {
+                Image::Name(name) | Image::Table { name } => name,
+            }),
+        )
+    }
+
+    impl FromStr for CrossConfig {
+        type Err = ParsingError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Ok(toml::from_str(s)?)
+        }
+    }
+
+    impl CrossConfig {
+        pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+            fs::read_to_string(path)?.parse()
+        }
+
+        /// Reads the configuration of a workspace, from the "CROSS_CONFIG" environment variable
+        /// or the `Cross.toml` in the workspace root.
+        ///
+        /// A workspace without one has the default configuration.
+        pub fn discover<P: AsRef<Path>>(workspace_root: P) -> Result<Self> {
+            let path = env::var_os("CROSS_CONFIG")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| workspace_root.as_ref().join("Cross.toml"));
+
+            match Self::open(path) {
+                Err(ParsingError::Io(error)) if error.kind() == std::io::ErrorKind::NotFound => {
+                    Ok(Self::default())
+                }
+                result => result,
+            }
+        }
+
+        fn target(&self, triple: &str) -> Option<&CrossTarget> {
+            self.targets.get(triple)
+        }
+
+        /// The image used for the given target.
+        pub fn image(&self, triple: &str) -> Option<&str> {
+            self.target(triple)
+                .and_then(|target| target.image.as_deref())
+                .or(self.build.image.as_deref())
+        }
+
+        /// The pre-build commands for the given target, which replace the ones in the `[build]` table.
+        pub fn pre_build(&self, triple: &str) -> Option<&PreBuild> {
+            self.target(triple)
+                .and_then(|target| target.pre_build.as_ref())
+                .or(self.build.pre_build.as_ref())
+        }
+
+        /// The environment variables forwarded for the given target, from both the `[build]` and target tables.
+        pub fn passthrough(&self, triple: &str) -> Vec<&str> {
+            self.build
+                .env
+                .passthrough
+                .iter()
+                .chain(
+                    self.target(triple)
+                        .into_iter()
+                        .flat_map(|target| &target.env.passthrough),
+                )
+                .map(String::as_str)
+                .collect()
+        }
+
+        /// The environment variables mounted as volumes for the given target, from both the `[build]` and target tables.
+        pub fn volumes(&self, triple: &str) -> Vec<&str> {
+            self.build
+                .env
+                .volumes
+                .iter()
+                .chain(
+                    self.target(triple)
+                        .into_iter()
+                        .flat_map(|target| &target.env.volumes),
+                )
+                .map(String::as_str)
+                .collect()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "toml"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repository_config() {
+        let config =
+            CrossConfig::discover(env!("CARGO_MANIFEST_DIR")).expect("Cross.toml should parse");
+        let windows = "x86_64-pc-windows-msvc";
+
+        assert_eq!(
+            config.image(windows),
+            Some("ghcr.io/cross-rs/x86_64-pc-windows-msvc-cross:local")
+        );
+        assert_eq!(config.image("aarch64-unknown-linux-gnu"), None);
+        assert_eq!(config.pre_build(windows), None);
+        assert!(config.passthrough(windows).is_empty());
+        assert_eq!(config.build, CrossTarget::default());
+    }
+
+    #[test]
+    fn target_over_build() {
+        let config: CrossConfig = r#"
+            [build]
+            image = "ghcr.io/cross-rs/base:main"
+            pre-build = ["apt-get update"]
+            default-target = "aarch64-unknown-linux-gnu"
+
+            [build.env]
+            passthrough = ["RUST_LOG", "MODE=release"]
+            volumes = ["BUILD_DIR"]
+
+            [target.aarch64-unknown-linux-gnu]
+            image = { name = "ghcr.io/cross-rs/aarch64-unknown-linux-gnu:main" }
+            pre-build = "scripts/pre-build.sh"
+            runner = "qemu-user"
+
+            [target.aarch64-unknown-linux-gnu.env]
+            passthrough = ["OPENSSL_DIR"]
+
+            [target.armv7-unknown-linux-gnueabihf]
+            build-std = true
+        "#
+        .parse()
+        .unwrap();
+
+        let aarch64 = "aarch64-unknown-linux-gnu";
+        let armv7 = "armv7-unknown-linux-gnueabihf";
+
+        // Target tables replace the image and pre-build commands of the `[build]` table.
+        assert_eq!(
+            config.image(aarch64),
+            Some("ghcr.io/cross-rs/aarch64-unknown-linux-gnu:main")
+        );
+        assert_eq!(
+            config.pre_build(aarch64),
+            Some(&PreBuild::Script(PathBuf::from("scripts/pre-build.sh")))
+        );
+        assert_eq!(config.image(armv7), Some("ghcr.io/cross-rs/base:main"));
+        assert_eq!(
+            config.pre_build(armv7),
+            Some(&PreBuild::Commands(vec!["apt-get update".to_string()]))
+        );
+
+        // Environment variables add up.
+        assert_eq!(
+            config.passthrough(aarch64),
+            ["RUST_LOG", "MODE=release", "OPENSSL_DIR"]
+        );
+        assert_eq!(config.passthrough(armv7), ["RUST_LOG", "MODE=release"]);
+        assert_eq!(config.volumes(aarch64), ["BUILD_DIR"]);
+
+        assert_eq!(config.build.default_target.as_deref(), Some(aarch64));
+        assert_eq!(config.targets[aarch64].runner.as_deref(), Some("qemu-user"));
+        assert_eq!(config.targets[armv7].build_std, Some(true));
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+mod cross;
+pub use cross::Cross;
+#[cfg(feature = "toml")]
+pub use cross::{CrossConfig, CrossEnv, CrossTarget, PreBuild};
+
+#[cfg(feature = "json")]
+use super::message::Message;
+
+/// A program accepting the same arguments as cargo that runs the build commands, E.g. inside a container.
+pub trait Wrapper: Debug + Send + Sync {
+    /// The path to the executable.
+    fn program(&self) -> &Path;
+
+    /// Maps a path reported by the program to the matching host path, E.g. from a mount of the container.
+    fn map_path(&self, path: &Path) -> PathBuf {
+        path.to_path_buf()
+    }
+}
+
+/// The program that runs the build commands.
+#[derive(Debug, Clone, Default)]
+pub enum Driver {
+    /// Runs cargo directly, using the [path](super::Cargo::path) and [toolchain](super::Cargo::toolchain) of [Cargo](super::Cargo).
+    #[default]
+    Cargo,
+    /// Runs cargo through cross, inside a container.
+    Cross(Cross),
+    /// Runs cargo through any other wrapper.
+    Wrapper(Arc<dyn Wrapper>),
+}
+
+impl Driver {
+    /// The executable run instead of cargo, [None] for [Driver::Cargo].
+    pub fn program(&self) -> Option<&Path> {
+        match self {
+            Driver::Cargo => None,
+            Driver::Cross(cross) => Some(cross.program()),
+            Driver::Wrapper(wrapper) => Some(wrapper.program()),
+        }
+    }
+
+    /// Maps a path reported by the driver to the matching host path.
+    pub fn map_path(&self, path: &Path) -> PathBuf {
+        match self {
+            Driver::Cargo => path.to_path_buf(),
+            Driver::Cross(cross) => cross.map_path(path),
+            Driver::Wrapper(wrapper) => wrapper.map_path(path),
+        }
+    }
+
+    /// Maps the paths in the given messages to host paths.
+    #[cfg(feature = "json")]
+    pub(crate) fn map_messages(&self, messages: &mut [Message]) {
+        if matches!(self, Driver::Cargo) {
+            return;
+        }
+
+        let map = |path: &mut PathBuf| *path = self.map_path(path);
+
+        for message in messages {
+            match message {
+                Message::CompilerArtifact(artifact) => {
+                    map(&mut artifact.manifest_path);
+                    map(&mut artifact.target.src_path);
+                    artifact.filenames.iter_mut().for_each(map);
+                    artifact.executable.iter_mut().for_each(map);
+                }
+                Message::CompilerMessage(message) => {
+                    map(&mut message.manifest_path);
+                    map(&mut message.target.src_path);
+                    message
+                        .message
+                        .spans
+                        .iter_mut()
+                        .for_each(|span| map(&mut span.file_name));
+                }
+                Message::BuildScriptExecuted(script) => {
+                    map(&mut script.out_dir);
+
+                    // Search paths can be prefixed with their kind, E.g. "native=/target/debug/build".
+                    for linked_path in &mut script.linked_paths {
+                        let (kind, path) = match linked_path.split_once('=') {
+                            Some((kind, path)) => (Some(kind), path),
+                            None => (None, linked_path.as_str()),
+                        };
+
+                        let path = self.map_path(Path::new(path));
+
+                        *linked_path = match kind {
+                            Some(kind) => format!("{kind}={}", path.display()),
+                            None => path.display().to_string(),
+                        };
+                    }
+                }
+                Message::TimingInfo(info) => map(&mut info.target.src_path),
+                Message::BuildFinished(_) | Message::Unknown => {}
+            }
+        }
+    }
+}
+
+#[cfg(all(test, unix, feature = "json"))]
+mod tests {
+    use super::*;
+    use crate::cargo::{home::temp_dir, BuildConfig, Cargo, MetadataConfig};
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    #[test]
+    fn stub_cross() {
+        let dir = temp_dir("cross");
+        let package = dir.join("hello");
+        let log = dir.join("cross.log");
+        let stub = dir.join("cross");
+
+        fs::create_dir_all(package.join("src")).unwrap();
+        fs::write(
+            package.join("Cargo.toml"),
+            "[package]\nname = \"hello\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+        )
+        .unwrap();
+        fs::write(package.join("src").join("lib.rs"), "pub fn hello() {}\n").unwrap();
+
+        // Runs cargo on the host, reporting paths as if the package was mounted like cross does.
+        fs::write(
+            &stub,
+            format!(
+                "#!/bin/sh\necho \"$@\" >> '{log}'\ncargo \"$@\" | sed -e 's#{target}#/target#g' -e 's#{package}#/project#g'\n",
+                log = log.display(),
+                target = package.join("target").display(),
+                package = package.display(),
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let cross = Cross::new(&package, package.join("target")).path(&stub);
+        let mut cargo = Cargo::new();
+        cargo.driver(Driver::Cross(cross));
+
+        let manifest_path = package.join("Cargo.toml");
+        let metadata = cargo.metadata(MetadataConfig {
+            manifest_path: Some(manifest_path.clone()),
+            no_deps: true,
+            ..Default::default()
+        });
+        let output = cargo.check(BuildConfig {
+            manifest_path: Some(manifest_path),
+            ..Default::default()
+        });
+        let invocations = fs::read_to_string(&log);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(metadata.unwrap().workspace_root, package);
+
+        let filenames: Vec<_> = output
+            .unwrap()
+            .artifacts()
+            .flat_map(|artifact| artifact.filenames.clone())
+            .collect();
+        assert!(!filenames.is_empty());
+        assert!(filenames
+            .iter()
+            .all(|filename| filename.starts_with(package.join("target"))));
+
+        // Only the build command went through the stub.
+        let invocations = invocations.unwrap();
+        assert_eq!(invocations.lines().count(), 1);
+        assert!(invocations.starts_with("check "));
+    }
+
+    #[test]
+    fn mounts() {
+        let cross =
+            Cross::new("/home/me/ws", "/home/me/ws/target").mount("/cargo", "/home/me/.cargo");
+
+        assert_eq!(
+            cross.map_path(Path::new("/project/src/lib.rs")),
+            Path::new("/home/me/ws/src/lib.rs")
+        );
+        assert_eq!(
+            cross.map_path(Path::new("/cargo/registry/src/serde/lib.rs")),
+            Path::new("/home/me/.cargo/registry/src/serde/lib.rs")
+        );
+        assert_eq!(cross.map_path(Path::new("/usr/lib")), Path::new("/usr/lib"));
+        assert_eq!(
+            cross
+                .clone()
+                .mount("/project/vendor", "/srv/vendor")
+                .map_path(Path::new("/project/vendor/serde")),
+            Path::new("/srv/vendor/serde")
+        );
+        assert_eq!(
+            cross.without_mounts().map_path(Path::new("/project/src")),
+            Path::new("/project/src")
+        );
+    }
+}
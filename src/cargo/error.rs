@@ -1,6 +1,11 @@
-use std::{io::Error as IoError, str::Utf8Error};
+use std::{
+    io::Error as IoError, path::PathBuf, process::ExitStatus, str::Utf8Error, time::Duration,
+};
 use thiserror::Error;
 
+#[cfg(feature = "json")]
+use super::doc::Impact;
+
 pub type Result<T, E = ParsingError> = std::result::Result<T, E>;
 
 #[derive(Debug, Error)]
@@ -8,11 +13,65 @@ pub type Result<T, E = ParsingError> = std::result::Result<T, E>;
 pub enum ParsingError {
     #[error("Missing \"{0}\" key when parsing Version")]
     Version(&'static str),
+    #[error("Missing \"{0}\" environment variable")]
+    Env(&'static str),
+    #[error("Invalid source id \"{0}\"")]
+    SourceId(String),
+    #[error("Invalid package id \"{0}\"")]
+    PackageId(String),
+    #[error("Invalid rust version \"{0}\"")]
+    RustVersion(String),
+    #[error("Invalid lockfile: {0}")]
+    Lockfile(String),
+    #[error("Invalid registry index: {0}")]
+    Index(String),
+    #[error("Invalid cargo configuration: {0}")]
+    Config(String),
+    #[error("Invalid rustdoc JSON: {0}")]
+    Rustdoc(String),
     #[error(
         "Error when executing command. The following is the stderr output:\n{0}",
         String::from_utf8_lossy(stderr)
     )]
     Exec { stderr: Vec<u8> },
+    #[error(
+        "Build failed:\n{}{}{}",
+        diagnostics.join("\n"),
+        if diagnostics.is_empty() { "" } else { "\n" },
+        String::from_utf8_lossy(stderr)
+    )]
+    Build {
+        diagnostics: Vec<String>,
+        stderr: Vec<u8>,
+    },
+    #[error("Command timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("Command was cancelled")]
+    Cancelled,
+    #[error("Program exited unsuccessfully ({status})")]
+    Program {
+        status: ExitStatus,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+    },
+    #[error("Could not determine what to run: {0}")]
+    Run(String),
+    #[error(
+        "The working directory contains uncommitted changes to: {}",
+        files.iter().map(|file| file.display().to_string()).collect::<Vec<_>>().join(", ")
+    )]
+    DirtyWorktree { files: Vec<PathBuf> },
+    #[error("The manifest is missing required metadata: {}", fields.join(", "))]
+    MissingMetadata { fields: Vec<String> },
+    #[error("Invalid package archive: {0}")]
+    Package(String),
+    #[error("Could not select a package or target: {0}")]
+    Selection(String),
+    #[error("Invalid release: {0}")]
+    Release(String),
+    #[cfg(feature = "json")]
+    #[error("The API changes require a {required} release but the version bump only allows a {allowed} release")]
+    SemverViolation { required: Impact, allowed: Impact },
     #[error("")]
     Io(#[from] IoError),
     #[error("")]
@@ -24,4 +83,10 @@ pub enum ParsingError {
     #[cfg(feature = "json")]
     #[error("{0}")]
     Serde(#[from] serde_json::Error),
+    #[cfg(feature = "toml")]
+    #[error("{0}")]
+    Toml(#[from] toml::de::Error),
+    #[cfg(feature = "release")]
+    #[error("{0}")]
+    TomlEdit(#[from] toml_edit::TomlError),
 }
@@ -0,0 +1,98 @@
+use semver::Version;
+use std::path::PathBuf;
+use target_lexicon::Triple;
+
+use super::{Cargo, Result};
+
+#[derive(Debug, Default, Clone)]
+pub struct FetchConfig {
+    /// Only fetch the dependencies needed for these targets, every platform if empty.
+    pub targets: Vec<Triple>,
+    pub manifest_path: Option<PathBuf>,
+}
+
+/// A package downloaded by [fetch](Cargo::fetch).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DownloadedPackage {
+    pub name: String,
+    pub version: Version,
+}
+
+impl Cargo {
+    /// Runs `cargo fetch`, returning the packages that weren't downloaded yet.
+    pub fn fetch(&mut self, config: FetchConfig) -> Result<Vec<DownloadedPackage>> {
+        let mut command = self.command(["fetch"]);
+
+        for target in &config.targets {
+            command.arg("--target").arg(target.to_string());
+        }
+
+        if let Some(manifest_path) = &config.manifest_path {
+            command.arg("--manifest-path").arg(manifest_path);
+        }
+
+        let output = self.output(&mut command)?;
+
+        Ok(std::str::from_utf8(&output.stderr)?
+            .lines()
+            .filter_map(downloaded_package)
+            .collect())
+    }
+}
+
+/// Parses a line like "  Downloaded serde v1.0.163", ignoring the "Downloaded 3 crates (1.2 MB) in 0.5s" summary.
+fn downloaded_package(line: &str) -> Option<DownloadedPackage> {
+    let mut words = line.trim().strip_prefix("Downloaded ")?.split_whitespace();
+    let name = words.next()?;
+    let version = words.next()?.strip_prefix('v')?.parse().ok()?;
+
+    Some(DownloadedPackage {
+        name: name.to_string(),
+        version,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn downloaded(name: &str, version: &str) -> Option<DownloadedPackage> {
+        Some(DownloadedPackage {
+            name: name.to_string(),
+            version: version.parse().unwrap(),
+        })
+    }
+
+    #[test]
+    fn downloaded_packages() {
+        assert_eq!(
+            downloaded_package("  Downloaded serde v1.0.163"),
+            downloaded("serde", "1.0.163")
+        );
+        assert_eq!(
+            downloaded_package("  Downloaded wasm-bindgen v0.2.87-alpha.1"),
+            downloaded("wasm-bindgen", "0.2.87-alpha.1")
+        );
+        // Packages from other registries are followed by the name of the registry.
+        assert_eq!(
+            downloaded_package("  Downloaded private v0.1.0 (registry `my-registry`)"),
+            downloaded("private", "0.1.0")
+        );
+    }
+
+    #[test]
+    fn other_lines() {
+        assert_eq!(
+            downloaded_package("  Downloaded 3 crates (1.2 MB) in 0.5s"),
+            None
+        );
+        assert_eq!(
+            downloaded_package("  Downloaded 1 crate (52.3 KB) in 0.21s"),
+            None
+        );
+        assert_eq!(downloaded_package("    Updating crates.io index"), None);
+        assert_eq!(downloaded_package(" Downloading crates ..."), None);
+        assert_eq!(downloaded_package("  Downloaded serde"), None);
+        assert_eq!(downloaded_package(""), None);
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Arc,
+};
+
+use super::{home::temp_dir, Result};
+
+/// The environment variables kept by default, needed to find the toolchain.
+const KEPT_ENV: [&str; 4] = ["PATH", "RUSTUP_HOME", "RUSTUP_TOOLCHAIN", "SYSTEMROOT"];
+
+/// Runs cargo isolated from the machine it runs on, so builds are reproducible anywhere.
+///
+/// Every invocation uses a private, empty `CARGO_HOME`, runs with `--frozen` and `--offline`,
+/// and only inherits the environment variables needed to find the toolchain. Dependencies have to
+/// come from a vendored directory, E.g. with [vendored](Hermetic::vendored).
+///
+/// Vendoring downloads the dependencies and may write the lockfile, which a hermetic [Cargo](crate::cargo::Cargo)
+/// refuses to do, so [vendor](crate::cargo::Cargo::vendor) with a separate one that isn't hermetic.
+#[derive(Debug, Clone)]
+pub struct Hermetic {
+    home: Arc<HermeticHome>,
+    config: Vec<String>,
+    kept_env: Vec<OsString>,
+}
+
+/// Removes the private `CARGO_HOME` once no [Hermetic] uses it anymore.
+#[derive(Debug)]
+struct HermeticHome(PathBuf);
+
+impl Drop for HermeticHome {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+impl Hermetic {
+    /// Creates the private `CARGO_HOME` in the temporary directory.
+    pub fn new() -> Result<Self> {
+        let home = temp_dir("home");
+        fs::create_dir_all(&home)?;
+
+        Ok(Hermetic {
+            home: Arc::new(HermeticHome(home)),
+            config: Vec::new(),
+            kept_env: KEPT_ENV.iter().map(OsString::from).collect(),
+        })
+    }
+
+    /// The private `CARGO_HOME`.
+    pub fn cargo_home(&self) -> &Path {
+        &self.home.0
+    }
+
+    /// Adds a configuration value passed with `--config`, E.g. `build.jobs=4`.
+    pub fn config<S: Into<String>>(mut self, value: S) -> Self {
+        self.config.push(value.into());
+        self
+    }
+
+    /// Keeps an environment variable from the current environment, on top of the ones needed to find the toolchain.
+    ///
+    /// Variables set with [Cargo::env](crate::cargo::Cargo::env) are always passed.
+    pub fn keep_env<K: AsRef<OsStr>>(mut self, key: K) -> Self {
+        self.kept_env.push(key.as_ref().to_os_string());
+        self
+    }
+
+    /// Replaces the inherited environment of the command.
+    pub(crate) fn apply_env(&self, command: &mut Command) {
+        command.env_clear();
+
+        for key in &self.kept_env {
+            if let Some(value) = env::var_os(key) {
+                command.env(key, value);
+            }
+        }
+
+        command.env("CARGO_HOME", self.cargo_home());
+    }
+
+    /// The configuration values and kept environment variables, which change the output of cargo.
+    #[cfg(feature = "json")]
+    pub(crate) fn options(&self) -> (&[String], &[OsString]) {
+        (&self.config, &self.kept_env)
+    }
+
+    /// Passes the configuration values, after the toolchain argument.
+    pub(crate) fn apply_config(&self, command: &mut Command) {
+        for value in &self.config {
+            command.arg("--config").arg(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cargo::Cargo;
+
+    #[test]
+    fn command() {
+        let hermetic = Hermetic::new().unwrap().config("build.jobs=4");
+        let home = hermetic.cargo_home().to_path_buf();
+
+        let mut cargo = Cargo::new();
+        cargo
+            .toolchain(Some("nightly"))
+            .env("CARGO_TERM_COLOR", "never")
+            .hermetic(Some(hermetic));
+
+        let command = cargo.command(["build"]);
+
+        // The toolchain has to come first, and the configuration before the subcommand.
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            [
+                "+nightly",
+                "--frozen",
+                "--offline",
+                "--config",
+                "build.jobs=4",
+                "build"
+            ]
+        );
+
+        let envs: Vec<_> = command.get_envs().collect();
+        assert!(envs.contains(&(OsStr::new("CARGO_HOME"), Some(home.as_os_str()))));
+        assert!(envs.contains(&(OsStr::new("CARGO_TERM_COLOR"), Some(OsStr::new("never")))));
+
+        // The private `CARGO_HOME` goes away with the last user.
+        assert!(home.is_dir());
+        drop(cargo);
+        assert!(!home.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn environment() {
+        let names = |hermetic: &Hermetic| {
+            let mut command = Command::new("env");
+            hermetic.apply_env(&mut command);
+
+            let output = command.output().unwrap();
+            String::from_utf8(output.stdout)
+                .unwrap()
+                .lines()
+                .filter_map(|line| line.split_once('=').map(|(name, _)| name.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        let hermetic = Hermetic::new().unwrap();
+        let stripped = names(&hermetic);
+        let kept = names(&hermetic.keep_env("HOME"));
+
+        assert!(stripped
+            .iter()
+            .all(|name| name == "CARGO_HOME" || KEPT_ENV.contains(&name.as_str())));
+        assert!(stripped.iter().any(|name| name == "CARGO_HOME"));
+        assert!(stripped.iter().any(|name| name == "PATH"));
+        assert!(!stripped.iter().any(|name| name == "HOME"));
+
+        if env::var_os("HOME").is_some() {
+            assert!(kept.iter().any(|name| name == "HOME"));
+        }
+    }
+}
@@ -0,0 +1,432 @@
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use super::{cargo_home, ParsingError, Result, RustVersion};
+
+/// The version of cargo's index cache files this reader understands.
+const CACHE_VERSION: u8 = 3;
+/// The newest version of index entries this reader understands, newer entries are skipped.
+const INDEX_VERSION: u32 = 2;
+
+/// A single version of a crate in a registry index.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// The name of the crate.
+    pub name: String,
+    /// The version of the crate.
+    #[serde(rename = "vers")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub version: Version,
+    /// Array of direct dependencies of the crate.
+    pub deps: Vec<IndexDependency>,
+    /// The sha256 checksum of the `.crate` file.
+    #[serde(rename = "cksum")]
+    pub checksum: String,
+    /// Set of features defined for the crate.
+    #[serde(default)]
+    pub features: BTreeMap<String, Vec<String>>,
+    /// Features using the newer `dep:` and `?` syntax, stored separately for older versions of cargo.
+    pub features2: Option<BTreeMap<String, Vec<String>>>,
+    /// Whether the version was yanked.
+    #[serde(default)]
+    pub yanked: bool,
+    /// The `links` value from the manifest.
+    pub links: Option<String>,
+    /// The minimum supported rust version.
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub rust_version: Option<RustVersion>,
+    /// The version of the entry format, missing for version 1.
+    pub v: Option<u32>,
+}
+
+impl IndexEntry {
+    /// Returns every feature, merging `features` and `features2`.
+    pub fn all_features(&self) -> BTreeMap<String, Vec<String>> {
+        let mut features = self.features.clone();
+
+        for (name, values) in self.features2.iter().flatten() {
+            features
+                .entry(name.clone())
+                .or_default()
+                .extend(values.iter().cloned());
+        }
+
+        features
+    }
+}
+
+/// A dependency of an [IndexEntry].
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IndexDependency {
+    /// The name of the dependency, or the name it's renamed to if `package` is set.
+    pub name: String,
+    /// The version requirement of the dependency.
+    #[serde_as(as = "DisplayFromStr")]
+    pub req: VersionReq,
+    /// Array of features enabled on the dependency.
+    pub features: Vec<String>,
+    /// Whether the dependency is optional.
+    pub optional: bool,
+    /// Whether the default features of the dependency are enabled.
+    pub default_features: bool,
+    /// The platform the dependency is limited to, E.g. "cfg(unix)".
+    pub target: Option<String>,
+    /// The kind of the dependency, [None] for normal dependencies.
+    pub kind: Option<DependencyKind>,
+    /// The url of the index of the registry the dependency is from, [None] for the same registry.
+    pub registry: Option<String>,
+    /// The actual name of the crate when the dependency is renamed.
+    pub package: Option<String>,
+}
+
+/// The kind of a dependency.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum DependencyKind {
+    #[serde(rename = "normal")]
+    Normal,
+    #[serde(rename = "dev")]
+    Dev,
+    #[serde(rename = "build")]
+    Build,
+}
+
+/// The on-disk layout of an index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    /// One file of JSON lines per crate, like a git index checkout or a sparse mirror.
+    Files,
+    /// Cargo's own binary cache files in the `.cache` directory.
+    Cache,
+}
+
+/// A registry index read from the local filesystem, without accessing the network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryIndex {
+    root: PathBuf,
+    layout: Layout,
+}
+
+impl RegistryIndex {
+    /// Reads an index stored as plain files, such as a sparse mirror or a git index checkout.
+    pub fn directory<P: Into<PathBuf>>(root: P) -> Self {
+        RegistryIndex {
+            root: root.into(),
+            layout: Layout::Files,
+        }
+    }
+
+    /// Reads one of cargo's index caches, E.g. "$CARGO_HOME/registry/index/index.crates.io-1949cf8c6b5b557f".
+    ///
+    /// Only crates cargo has already resolved are available.
+    pub fn cache<P: Into<PathBuf>>(root: P) -> Self {
+        RegistryIndex {
+            root: root.into(),
+            layout: Layout::Cache,
+        }
+    }
+
+    /// Returns every index cache in the given cargo home, defaulting to [cargo_home].
+    pub fn caches(cargo_home: Option<&Path>) -> Result<Vec<Self>> {
+        let cargo_home = match cargo_home {
+            Some(cargo_home) => cargo_home.to_path_buf(),
+            None => self::cargo_home().ok_or(ParsingError::Env("CARGO_HOME"))?,
+        };
+
+        let entries = match fs::read_dir(cargo_home.join("registry").join("index")) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut caches = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+
+            if entry.file_type()?.is_dir() {
+                caches.push(Self::cache(entry.path()));
+            }
+        }
+
+        caches.sort_by(|a, b| a.root.cmp(&b.root));
+
+        Ok(caches)
+    }
+
+    /// Returns the crates.io index cache in the given cargo home, preferring the sparse index.
+    pub fn crates_io_cache(cargo_home: Option<&Path>) -> Result<Option<Self>> {
+        let caches = Self::caches(cargo_home)?;
+        let named = |prefix: &str| {
+            caches.iter().find(|cache| {
+                cache
+                    .root
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with(prefix))
+            })
+        };
+
+        Ok(named("index.crates.io-")
+            .or_else(|| named("github.com-"))
+            .cloned())
+    }
+
+    /// The root directory of the index.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The path of the file describing the given crate, failing if the name isn't a valid crate name.
+    pub fn crate_path(&self, name: &str) -> Result<PathBuf> {
+        let root = match self.layout {
+            Layout::Files => self.root.clone(),
+            Layout::Cache => self.root.join(".cache"),
+        };
+
+        Ok(root.join(index_path(name)?))
+    }
+
+    /// Returns every version of the given crate, or an empty array if the crate is not in the index.
+    ///
+    /// Entries in a newer format than this reader understands, or that can't be parsed, are skipped
+    /// like cargo does.
+    pub fn entries(&self, name: &str) -> Result<Vec<IndexEntry>> {
+        let bytes = match fs::read(self.crate_path(name)?) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+
+        match self.layout {
+            Layout::Files => Ok(bytes
+                .split(|byte| *byte == b'\n')
+                .filter(|line| !line.iter().all(u8::is_ascii_whitespace))
+                .filter_map(parse_entry)
+                .collect()),
+            Layout::Cache => parse_cache(&bytes),
+        }
+    }
+
+    /// Returns the given version of a crate.
+    pub fn entry(&self, name: &str, version: &Version) -> Result<Option<IndexEntry>> {
+        Ok(self
+            .entries(name)?
+            .into_iter()
+            .find(|entry| &entry.version == version))
+    }
+
+    /// Returns the latest version of a crate matching the given requirement, ignoring yanked versions.
+    ///
+    /// Pre-release versions are only considered if the requirement explicitly mentions one.
+    pub fn latest_matching(&self, name: &str, req: &VersionReq) -> Result<Option<IndexEntry>> {
+        Ok(self
+            .entries(name)?
+            .into_iter()
+            .filter(|entry| !entry.yanked && req.matches(&entry.version))
+            .max_by(|a, b| a.version.cmp(&b.version)))
+    }
+
+    /// Returns the latest stable version of a crate, ignoring yanked versions.
+    pub fn latest(&self, name: &str) -> Result<Option<IndexEntry>> {
+        self.latest_matching(name, &VersionReq::STAR)
+    }
+}
+
+/// The path of a crate relative to the root of an index, E.g. "se/rd/serde" or "3/s/syn".
+fn index_path(name: &str) -> Result<PathBuf> {
+    // Crate names are ASCII, so slicing them by byte is safe.
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_')
+    {
+        return Err(ParsingError::Index(format!(
+            "invalid crate name \"{name}\""
+        )));
+    }
+
+    let name = name.to_ascii_lowercase();
+
+    Ok(match name.len() {
+        1 => ["1", &name].iter().collect(),
+        2 => ["2", &name].iter().collect(),
+        3 => ["3", &name[..1], &name].iter().collect(),
+        _ => [&name[..2], &name[2..4], &name].iter().collect(),
+    })
+}
+
+/// Parses one of cargo's index cache files.
+///
+/// The file starts with the cache version as a byte, the index format version as a little endian
+/// u32 and the null terminated index revision, followed by pairs of null terminated versions and JSON entries.
+fn parse_cache(bytes: &[u8]) -> Result<Vec<IndexEntry>> {
+    let invalid = |reason: &str| ParsingError::Index(reason.to_string());
+
+    let (&version, rest) = bytes
+        .split_first()
+        .ok_or_else(|| invalid("empty cache file"))?;
+    if version != CACHE_VERSION {
+        return Err(invalid(&format!("unsupported cache version {version}")));
+    }
+
+    let rest = rest
+        .get(4..)
+        .ok_or_else(|| invalid("truncated cache file"))?;
+
+    let mut fields = rest.split(|byte| *byte == 0);
+
+    // The revision of the index the cache was created from.
+    fields.next();
+
+    let mut entries = Vec::new();
+    while let (Some(version), Some(json)) = (fields.next(), fields.next()) {
+        if version.is_empty() {
+            break;
+        }
+
+        entries.extend(parse_entry(json));
+    }
+
+    Ok(entries)
+}
+
+/// The format version of an entry, read before the entry itself since newer formats may not fit [IndexEntry].
+#[derive(Deserialize)]
+struct EntryVersion {
+    v: Option<u32>,
+}
+
+/// Parses a single entry, [None] if it's in a newer format or invalid.
+fn parse_entry(json: &[u8]) -> Option<IndexEntry> {
+    let EntryVersion { v } = serde_json::from_slice(json).ok()?;
+
+    if v.unwrap_or(1) > INDEX_VERSION {
+        return None;
+    }
+
+    serde_json::from_slice(json).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cargo::home::temp_dir;
+
+    fn entry(name: &str, version: &str, extra: &str) -> String {
+        format!(
+            r#"{{"name":"{name}","vers":"{version}","deps":[],"cksum":"00","features":{{}}{extra}}}"#
+        )
+    }
+
+    #[test]
+    fn index_paths() {
+        let path = |name| index_path(name).unwrap();
+
+        assert_eq!(path("a"), Path::new("1/a"));
+        assert_eq!(path("io"), Path::new("2/io"));
+        assert_eq!(path("syn"), Path::new("3/s/syn"));
+        assert_eq!(path("toml"), Path::new("to/ml/toml"));
+        assert_eq!(path("serde"), Path::new("se/rd/serde"));
+        assert_eq!(path("Serde_JSON"), Path::new("se/rd/serde_json"));
+    }
+
+    #[test]
+    fn invalid_names() {
+        for name in ["", "é", "sérde", "../etc", "a b"] {
+            assert!(
+                matches!(index_path(name), Err(ParsingError::Index(_))),
+                "{name:?}"
+            );
+        }
+
+        assert!(RegistryIndex::directory("/nonexistent")
+            .entries("")
+            .is_err());
+    }
+
+    #[test]
+    fn directory() {
+        let root = temp_dir("index");
+        fs::create_dir_all(root.join("3/s")).unwrap();
+        fs::write(
+            root.join("3/s/syn"),
+            [
+                entry("syn", "1.0.0", ""),
+                entry("syn", "2.0.0", r#","yanked":true"#),
+                entry(
+                    "syn",
+                    "1.5.0",
+                    r#","v":2,"features2":{"full":["dep:quote"]}"#,
+                ),
+                entry("syn", "3.0.0", r#","v":3"#),
+                r#"{"name":"syn","vers":"4.0.0","deps":{"serde":"1"},"v":3}"#.to_string(),
+                r#"{"name":"syn","vers":"not a version"}"#.to_string(),
+                "{".to_string(),
+                entry("syn", "2.0.0-alpha", ""),
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let index = RegistryIndex::directory(&root);
+        let entries = index.entries("syn");
+        let latest = index.latest("syn");
+        let matching = index.latest_matching("syn", &"^1.0".parse().unwrap());
+        let missing = index.entries("serde");
+        fs::remove_dir_all(&root).unwrap();
+
+        // Entries in a newer format and invalid entries are skipped.
+        let versions: Vec<String> = entries
+            .unwrap()
+            .iter()
+            .map(|entry| entry.version.to_string())
+            .collect();
+        assert_eq!(versions, ["1.0.0", "2.0.0", "1.5.0", "2.0.0-alpha"]);
+
+        let latest = latest.unwrap().unwrap();
+        assert_eq!(latest.version.to_string(), "1.5.0");
+        assert_eq!(latest.all_features()["full"], ["dep:quote"]);
+        assert_eq!(matching.unwrap().unwrap().version.to_string(), "1.5.0");
+        assert!(missing.unwrap().is_empty());
+    }
+
+    #[test]
+    fn cache() {
+        let root = temp_dir("index");
+        fs::create_dir_all(root.join(".cache/se/rd")).unwrap();
+
+        let mut bytes = vec![CACHE_VERSION];
+        bytes.extend(2u32.to_le_bytes());
+        bytes.extend(b"etag\0");
+        for (version, json) in [
+            ("1.0.0", entry("serde", "1.0.0", "")),
+            ("2.0.0", entry("serde", "2.0.0", r#","v":3"#)),
+            ("3.0.0", "{".to_string()),
+        ] {
+            bytes.extend(version.as_bytes());
+            bytes.push(0);
+            bytes.extend(json.as_bytes());
+            bytes.push(0);
+        }
+        fs::write(root.join(".cache/se/rd/serde"), &bytes).unwrap();
+
+        let entries = RegistryIndex::cache(&root).entries("serde");
+        fs::remove_dir_all(&root).unwrap();
+
+        let entries = entries.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].version.to_string(), "1.0.0");
+
+        assert!(matches!(
+            parse_cache(&[1, 0, 0, 0, 0]),
+            Err(ParsingError::Index(_))
+        ));
+    }
+}
@@ -0,0 +1,492 @@
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use super::{cargo_home, Cargo, Features, GitReference, PackageId, ParsingError, Result};
+
+#[derive(Debug, Clone)]
+pub struct InstallConfig {
+    /// Where to install the package from.
+    pub source: InstallSource,
+    /// The version to install, either exact or a requirement such as "^1.2".
+    pub version: Option<String>,
+    /// The directory to install into, defaults to `CARGO_HOME`.
+    pub root: Option<PathBuf>,
+    /// Use the lockfile shipped with the package.
+    pub locked: bool,
+    pub features: Option<Features>,
+    /// Reinstall even if the package is already installed.
+    pub force: bool,
+    /// Install only the given binaries.
+    pub bins: Vec<String>,
+    /// The name of the registry to install from, as configured in the cargo configuration.
+    pub registry: Option<String>,
+    /// The url of the registry index to install from.
+    pub index: Option<String>,
+}
+
+impl InstallConfig {
+    pub fn new(source: InstallSource) -> Self {
+        InstallConfig {
+            source,
+            version: None,
+            root: None,
+            locked: false,
+            features: None,
+            force: false,
+            bins: Vec::new(),
+            registry: None,
+            index: None,
+        }
+    }
+}
+
+/// Where `cargo install` gets the package from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallSource {
+    /// A crate from a registry.
+    Registry(String),
+    /// A local package.
+    Path(PathBuf),
+    /// A git repository, optionally selecting a package when it contains more than one.
+    Git {
+        url: String,
+        reference: GitReference,
+        package: Option<String>,
+    },
+}
+
+/// The packages installed in a root, as recorded by `cargo install`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledPackages {
+    /// The installation root, with the binaries in its "bin" directory.
+    pub root: PathBuf,
+    /// Array of installed packages.
+    pub packages: Vec<InstalledPackage>,
+}
+
+/// A package installed by `cargo install`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledPackage {
+    /// The id of the installed package, with its exact version and source.
+    pub id: PackageId,
+    /// The version requirement the package was installed with, if any.
+    pub version_req: Option<String>,
+    /// The names of the installed binaries.
+    pub bins: BTreeSet<String>,
+    /// The features the package was installed with.
+    pub features: BTreeSet<String>,
+    /// Whether the package was installed with `--all-features`.
+    pub all_features: bool,
+    /// Whether the package was installed with `--no-default-features`.
+    pub no_default_features: bool,
+    /// The profile the package was built with, E.g. "release".
+    pub profile: Option<String>,
+    /// The target triple the package was built for.
+    pub target: Option<String>,
+    /// The output of `rustc -vV` of the compiler the package was built with.
+    pub rustc: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CratesV2 {
+    installs: BTreeMap<String, InstallInfo>,
+}
+
+#[derive(Deserialize)]
+struct InstallInfo {
+    version_req: Option<String>,
+    #[serde(default)]
+    bins: BTreeSet<String>,
+    #[serde(default)]
+    features: BTreeSet<String>,
+    #[serde(default)]
+    all_features: bool,
+    #[serde(default)]
+    no_default_features: bool,
+    profile: Option<String>,
+    target: Option<String>,
+    rustc: Option<String>,
+}
+
+impl InstalledPackages {
+    /// Reads the packages installed in the default root.
+    ///
+    /// This is the "CARGO_INSTALL_ROOT" environment variable if set, otherwise `CARGO_HOME`.
+    pub fn load() -> Result<Self> {
+        let root = env::var_os("CARGO_INSTALL_ROOT")
+            .filter(|root| !root.is_empty())
+            .map(PathBuf::from)
+            .or_else(cargo_home)
+            .ok_or(ParsingError::Env("CARGO_HOME"))?;
+
+        Self::from_root(root)
+    }
+
+    /// Reads the packages installed in the given root.
+    ///
+    /// Reads ".crates2.json" when present, which records the features and profile of each package,
+    /// and falls back to the older ".crates.toml" when built with the "toml" feature.
+    /// A root without either file has no installed packages.
+    pub fn from_root<P: Into<PathBuf>>(root: P) -> Result<Self> {
+        let root = root.into();
+
+        let packages = match fs::read(root.join(".crates2.json")) {
+            Ok(bytes) => {
+                let crates: CratesV2 = serde_json::from_slice(&bytes)?;
+
+                crates
+                    .installs
+                    .into_iter()
+                    .map(|(id, info)| {
+                        Ok(InstalledPackage {
+                            id: id.parse()?,
+                            version_req: info.version_req,
+                            bins: info.bins,
+                            features: info.features,
+                            all_features: info.all_features,
+                            no_default_features: info.no_default_features,
+                            profile: info.profile,
+                            target: info.target,
+                            rustc: info.rustc,
+                        })
+                    })
+                    .collect::<Result<_>>()?
+            }
+            Err(error) if error.kind() == ErrorKind::NotFound => read_crates_v1(&root)?,
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(InstalledPackages { root, packages })
+    }
+
+    /// Returns the installed package with the given name.
+    pub fn get(&self, name: &str) -> Option<&InstalledPackage> {
+        self.packages.iter().find(|package| package.id.name == name)
+    }
+
+    /// Returns the package that installed the given binary.
+    pub fn owner_of(&self, bin: &str) -> Option<&InstalledPackage> {
+        self.packages
+            .iter()
+            .find(|package| package.bins.contains(bin))
+    }
+
+    /// The directory the binaries are installed into.
+    pub fn bin_dir(&self) -> PathBuf {
+        self.root.join("bin")
+    }
+}
+
+#[cfg(feature = "toml")]
+fn read_crates_v1(root: &Path) -> Result<Vec<InstalledPackage>> {
+    #[derive(Deserialize)]
+    struct CratesV1 {
+        #[serde(default)]
+        v1: BTreeMap<String, BTreeSet<String>>,
+    }
+
+    let contents = match fs::read_to_string(root.join(".crates.toml")) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error.into()),
+    };
+
+    let crates: CratesV1 = toml::from_str(&contents)?;
+
+    crates
+        .v1
+        .into_iter()
+        .map(|(id, bins)| {
+            Ok(InstalledPackage {
+                id: id.parse()?,
+                version_req: None,
+                bins,
+                features: BTreeSet::new(),
+                all_features: false,
+                no_default_features: false,
+                profile: None,
+                target: None,
+                rustc: None,
+            })
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "toml"))]
+fn read_crates_v1(_root: &Path) -> Result<Vec<InstalledPackage>> {
+    Ok(Vec::new())
+}
+
+impl Cargo {
+    /// Runs `cargo install`.
+    pub fn install(&mut self, config: InstallConfig) -> Result<()> {
+        self.exec(&mut self.install_command(&config))?;
+
+        Ok(())
+    }
+
+    fn install_command(&self, config: &InstallConfig) -> Command {
+        let mut command = self.command(["install"]);
+
+        match &config.source {
+            InstallSource::Registry(name) => {
+                command.arg(name);
+            }
+            InstallSource::Path(path) => {
+                command.arg("--path").arg(path);
+            }
+            InstallSource::Git {
+                url,
+                reference,
+                package,
+            } => {
+                command.arg("--git").arg(url);
+
+                match reference {
+                    GitReference::Branch(branch) => command.arg("--branch").arg(branch),
+                    GitReference::Tag(tag) => command.arg("--tag").arg(tag),
+                    GitReference::Rev(rev) => command.arg("--rev").arg(rev),
+                    GitReference::DefaultBranch => &mut command,
+                };
+
+                if let Some(package) = package {
+                    command.arg(package);
+                }
+            }
+        }
+
+        if let Some(version) = &config.version {
+            command.arg("--version").arg(version);
+        }
+
+        if let Some(root) = &config.root {
+            command.arg("--root").arg(root);
+        }
+
+        // The global flag is already passed by `command` when set on Cargo.
+        if config.locked && !self.locked {
+            command.arg("--locked");
+        }
+
+        if let Some(features) = &config.features {
+            features.apply(&mut command);
+        }
+
+        if config.force {
+            command.arg("--force");
+        }
+
+        for bin in &config.bins {
+            command.arg("--bin").arg(bin);
+        }
+
+        if let Some(registry) = &config.registry {
+            command.arg("--registry").arg(registry);
+        }
+
+        if let Some(index) = &config.index {
+            command.arg("--index").arg(index);
+        }
+
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cargo::home::temp_dir;
+
+    #[test]
+    fn crates2() {
+        let root = temp_dir("install");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join(".crates2.json"),
+            r#"{"installs":{"ripgrep 13.0.0 (registry+https://github.com/rust-lang/crates.io-index)":{"version_req":"^13","bins":["rg"],"features":["pcre2"],"all_features":false,"no_default_features":false,"profile":"release","target":"x86_64-unknown-linux-gnu","rustc":"rustc 1.70.0"}}}"#,
+        )
+        .unwrap();
+
+        let installed = InstalledPackages::from_root(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let ripgrep = installed.get("ripgrep").unwrap();
+        assert_eq!(ripgrep.id.version.to_string(), "13.0.0");
+        assert!(ripgrep.id.source.as_ref().unwrap().is_crates_io());
+        assert_eq!(ripgrep.version_req.as_deref(), Some("^13"));
+        assert!(ripgrep.features.contains("pcre2"));
+        assert_eq!(ripgrep.profile.as_deref(), Some("release"));
+        assert_eq!(installed.owner_of("rg"), Some(ripgrep));
+        assert_eq!(installed.bin_dir(), root.join("bin"));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn crates_v1() {
+        let root = temp_dir("install");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join(".crates.toml"),
+            "[v1]\n\"ripgrep 13.0.0 (registry+https://github.com/rust-lang/crates.io-index)\" = [\"rg\"]\n",
+        )
+        .unwrap();
+
+        let installed = InstalledPackages::from_root(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(installed.owner_of("rg").unwrap().id.name, "ripgrep");
+    }
+
+    #[test]
+    fn empty_root() {
+        let installed = InstalledPackages::from_root(temp_dir("install")).unwrap();
+        assert!(installed.packages.is_empty());
+    }
+
+    fn args(cargo: &Cargo, config: InstallConfig) -> Vec<String> {
+        cargo
+            .install_command(&config)
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn registry_command() {
+        let mut cargo = Cargo::new();
+
+        assert_eq!(
+            args(
+                &cargo,
+                InstallConfig {
+                    version: Some("^13".to_string()),
+                    registry: Some("my-registry".to_string()),
+                    bins: vec!["rg".to_string()],
+                    ..InstallConfig::new(InstallSource::Registry("ripgrep".to_string()))
+                }
+            ),
+            [
+                "install",
+                "ripgrep",
+                "--version",
+                "^13",
+                "--bin",
+                "rg",
+                "--registry",
+                "my-registry"
+            ]
+        );
+        assert_eq!(
+            args(
+                &cargo,
+                InstallConfig {
+                    root: Some(PathBuf::from("/opt/tools")),
+                    index: Some("sparse+https://example.com/index/".to_string()),
+                    locked: true,
+                    force: true,
+                    ..InstallConfig::new(InstallSource::Registry("ripgrep".to_string()))
+                }
+            ),
+            [
+                "install",
+                "ripgrep",
+                "--root",
+                "/opt/tools",
+                "--locked",
+                "--force",
+                "--index",
+                "sparse+https://example.com/index/"
+            ]
+        );
+
+        // The global flag already asks for the lockfile.
+        cargo.locked(true);
+        let locked = args(
+            &cargo,
+            InstallConfig {
+                locked: true,
+                ..InstallConfig::new(InstallSource::Registry("ripgrep".to_string()))
+            },
+        );
+        assert_eq!(locked.iter().filter(|arg| *arg == "--locked").count(), 1);
+    }
+
+    #[test]
+    fn git_command() {
+        let git = |reference: GitReference, package: Option<&str>| {
+            args(
+                &Cargo::new(),
+                InstallConfig::new(InstallSource::Git {
+                    url: "https://github.com/a/tools".to_string(),
+                    reference,
+                    package: package.map(str::to_string),
+                }),
+            )
+        };
+
+        assert_eq!(
+            git(GitReference::DefaultBranch, None),
+            ["install", "--git", "https://github.com/a/tools"]
+        );
+        assert_eq!(
+            git(GitReference::Branch("main".to_string()), Some("tool")),
+            [
+                "install",
+                "--git",
+                "https://github.com/a/tools",
+                "--branch",
+                "main",
+                "tool"
+            ]
+        );
+        assert_eq!(
+            git(GitReference::Tag("v1.0.0".to_string()), None)[3..],
+            ["--tag", "v1.0.0"]
+        );
+        assert_eq!(
+            git(GitReference::Rev("0123abc".to_string()), None)[3..],
+            ["--rev", "0123abc"]
+        );
+    }
+
+    #[test]
+    fn install_path() {
+        let dir = temp_dir("install");
+        let home = dir.join("home");
+        let package = dir.join("hello");
+
+        fs::create_dir_all(package.join("src")).unwrap();
+        fs::create_dir_all(&home).unwrap();
+        fs::write(
+            package.join("Cargo.toml"),
+            "[package]\nname = \"hello\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+        )
+        .unwrap();
+        fs::write(package.join("src/main.rs"), "fn main() {}\n").unwrap();
+
+        let mut cargo = Cargo::new();
+        cargo
+            .env("CARGO_HOME", &home)
+            .env("CARGO_TARGET_DIR", dir.join("target"));
+
+        let result = cargo.install(InstallConfig::new(InstallSource::Path(package)));
+        let installed = InstalledPackages::from_root(&home);
+        fs::remove_dir_all(&dir).unwrap();
+
+        result.unwrap();
+        let installed = installed.unwrap();
+        let hello = installed.get("hello").unwrap();
+
+        assert!(hello.id.source.as_ref().unwrap().is_path());
+        assert!(hello
+            .bins
+            .contains(&format!("hello{}", env::consts::EXE_SUFFIX)));
+        assert_eq!(hello.profile.as_deref(), Some("release"));
+    }
+}
@@ -0,0 +1,14 @@
+use std::path::PathBuf;
+
+#[derive(Debug, Default)]
+pub struct UpdateConfig {
+    /// Packages to update, passed as `-p`. Updates every package if empty.
+    pub packages: Vec<String>,
+    /// Update a single package to exactly this version.
+    pub precise: Option<String>,
+    /// Also update the dependencies of the selected packages.
+    pub recursive: bool,
+    /// Don't write the lockfile, only report what would change.
+    pub dry_run: bool,
+    pub manifest_path: Option<PathBuf>,
+}
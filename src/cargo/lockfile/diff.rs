@@ -0,0 +1,413 @@
+use semver::Version;
+use std::collections::BTreeMap;
+
+use super::{LockedPackage, Lockfile};
+use crate::cargo::{PackageId, SourceId};
+
+/// The differences between two lockfile snapshots.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LockfileDiff {
+    /// Packages only present in the new lockfile.
+    pub added: Vec<PackageId>,
+    /// Packages added by a dry run, whose source isn't known since cargo doesn't print it.
+    pub added_unresolved: Vec<UnresolvedPackage>,
+    /// Packages only present in the old lockfile.
+    pub removed: Vec<PackageId>,
+    /// Packages whose version increased.
+    pub upgraded: Vec<VersionChange>,
+    /// Packages whose version decreased.
+    pub downgraded: Vec<VersionChange>,
+    /// Packages whose version stayed the same but whose source changed,
+    /// E.g. a git dependency locked to a different commit.
+    pub source_changed: Vec<SourceChange>,
+}
+
+/// A package whose locked version changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionChange {
+    /// The name of the package.
+    pub name: String,
+    /// The version in the old lockfile.
+    pub from: Version,
+    /// The version in the new lockfile.
+    pub to: Version,
+    /// The source in the old lockfile.
+    pub from_source: Option<SourceId>,
+    /// The source in the new lockfile.
+    pub to_source: Option<SourceId>,
+    /// Whether the two versions are semver compatible.
+    pub compatibility: Compatibility,
+}
+
+/// A package whose source changed while its version stayed the same.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceChange {
+    /// The name of the package.
+    pub name: String,
+    /// The version of the package.
+    pub version: Version,
+    /// The source in the old lockfile.
+    pub from: Option<SourceId>,
+    /// The source in the new lockfile.
+    pub to: Option<SourceId>,
+}
+
+/// A package added by a dry run of `cargo update`, before it is written to the lockfile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedPackage {
+    /// The name of the package.
+    pub name: String,
+    /// The version of the package.
+    pub version: Version,
+}
+
+/// Semver compatibility between two versions, following cargo's rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compatibility {
+    /// The versions share the same leftmost non-zero component, E.g. "1.2.0" and "1.4.1".
+    Compatible,
+    /// The versions differ in their leftmost non-zero component, E.g. "0.2.0" and "0.3.0".
+    Incompatible,
+}
+
+impl Compatibility {
+    /// Classifies the change from one version to another.
+    pub fn between(from: &Version, to: &Version) -> Self {
+        if compatibility_key(from) == compatibility_key(to) {
+            Self::Compatible
+        } else {
+            Self::Incompatible
+        }
+    }
+}
+
+impl VersionChange {
+    fn new(from: PackageId, to: PackageId) -> Self {
+        VersionChange {
+            compatibility: Compatibility::between(&from.version, &to.version),
+            name: to.name,
+            from: from.version,
+            to: to.version,
+            from_source: from.source,
+            to_source: to.source,
+        }
+    }
+
+    /// Whether the source also changed along with the version.
+    pub fn source_changed(&self) -> bool {
+        self.from_source != self.to_source
+    }
+}
+
+impl LockfileDiff {
+    /// Compares two sets of locked packages.
+    pub fn new(old: &[LockedPackage], new: &[LockedPackage]) -> Self {
+        let mut by_name: BTreeMap<&str, (Vec<PackageId>, Vec<PackageId>)> = BTreeMap::new();
+
+        for package in old {
+            by_name
+                .entry(&package.name)
+                .or_default()
+                .0
+                .push(package.id());
+        }
+
+        for package in new {
+            by_name
+                .entry(&package.name)
+                .or_default()
+                .1
+                .push(package.id());
+        }
+
+        let mut diff = LockfileDiff::default();
+
+        for (mut old, mut new) in by_name.into_values() {
+            // Packages present in both snapshots didn't change.
+            old.retain(|id| {
+                if let Some(index) = new.iter().position(|other| other == id) {
+                    new.remove(index);
+                    false
+                } else {
+                    true
+                }
+            });
+
+            old.sort();
+            new.sort();
+
+            // Prefer pairing semver compatible versions, E.g. an update from "1.0.0" to "1.2.0"
+            // when "2.0.0" was also added, then pair whatever is left in version order.
+            let mut pairs = Vec::new();
+            old.retain(|from| {
+                if let Some(index) = new.iter().position(|to| {
+                    compatibility_key(&from.version) == compatibility_key(&to.version)
+                }) {
+                    pairs.push((from.clone(), new.remove(index)));
+                    false
+                } else {
+                    true
+                }
+            });
+
+            let leftover = old.len().min(new.len());
+            pairs.extend(old.drain(..leftover).zip(new.drain(..leftover)));
+
+            for (from, to) in pairs {
+                diff.push_change(from, to);
+            }
+
+            diff.removed.extend(old);
+            diff.added.extend(new);
+        }
+
+        diff
+    }
+
+    pub(crate) fn push_change(&mut self, from: PackageId, to: PackageId) {
+        if from.version == to.version {
+            self.source_changed.push(SourceChange {
+                name: to.name,
+                version: to.version,
+                from: from.source,
+                to: to.source,
+            });
+        } else if to.version > from.version {
+            self.upgraded.push(VersionChange::new(from, to));
+        } else {
+            self.downgraded.push(VersionChange::new(from, to));
+        }
+    }
+
+    /// Whether the two snapshots are identical.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.added_unresolved.is_empty()
+            && self.removed.is_empty()
+            && self.upgraded.is_empty()
+            && self.downgraded.is_empty()
+            && self.source_changed.is_empty()
+    }
+
+    /// Returns all the version changes that are not semver compatible.
+    pub fn incompatible_changes(&self) -> impl Iterator<Item = &VersionChange> {
+        self.upgraded
+            .iter()
+            .chain(&self.downgraded)
+            .filter(|change| change.compatibility == Compatibility::Incompatible)
+    }
+
+    /// Builds a diff from the summary cargo prints on stderr when running `cargo update`,
+    /// using the lockfile before the update to fill in the sources.
+    ///
+    /// Added packages aren't in that lockfile, so they end up in [added_unresolved](Self::added_unresolved).
+    pub(crate) fn from_update_output(before: &[LockedPackage], stderr: &str) -> Self {
+        let mut diff = LockfileDiff::default();
+
+        let find = |name: &str, version: &Version| {
+            before
+                .iter()
+                .find(|package| package.name == name && &package.version == version)
+                .map(LockedPackage::id)
+        };
+
+        for line in stderr.lines() {
+            let mut parts = line.split_whitespace();
+
+            let (Some(status), Some(name), Some(version)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+
+            // Lines such as "Updating crates.io index" or "Locking 2 packages" don't carry versions.
+            let Some(Ok(version)) = version.strip_prefix('v').map(Version::parse) else {
+                continue;
+            };
+
+            match status {
+                "Adding" => diff.added_unresolved.push(UnresolvedPackage {
+                    name: name.to_string(),
+                    version,
+                }),
+                "Removing" => {
+                    if let Some(id) = find(name, &version) {
+                        diff.removed.push(id);
+                    }
+                }
+                "Updating" | "Downgrading" => {
+                    let Some(from) = find(name, &version) else {
+                        continue;
+                    };
+
+                    // Git sources may be followed by their url and commit, E.g. "(https://...#abc)".
+                    let Some(target) = parts.skip_while(|part| *part != "->").nth(1) else {
+                        continue;
+                    };
+
+                    let mut to = from.clone();
+                    if let Some(precise) = target.strip_prefix('#') {
+                        if let Some(source) = &mut to.source {
+                            source.precise = Some(precise.to_string());
+                        }
+                    } else if let Some(Ok(version)) = target.strip_prefix('v').map(Version::parse) {
+                        to.version = version;
+                    } else {
+                        continue;
+                    }
+
+                    diff.push_change(from, to);
+                }
+                _ => {}
+            }
+        }
+
+        diff
+    }
+}
+
+impl Lockfile {
+    /// Compares this lockfile with a newer snapshot.
+    pub fn diff(&self, new: &Lockfile) -> LockfileDiff {
+        LockfileDiff::new(&self.packages, &new.packages)
+    }
+}
+
+/// The leftmost non-zero component of a version, used for cargo's semver compatibility rules.
+fn compatibility_key(version: &Version) -> (u64, u64, u64) {
+    match (version.major, version.minor) {
+        (0, 0) => (0, 0, version.patch),
+        (0, minor) => (0, minor, 0),
+        (major, _) => (major, 0, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CRATES_IO: &str = "registry+https://github.com/rust-lang/crates.io-index";
+    const REPO: &str = "git+https://github.com/user/dep";
+
+    fn package(name: &str, version: &str, source: Option<&str>) -> LockedPackage {
+        LockedPackage {
+            name: name.to_string(),
+            version: version.parse().unwrap(),
+            source: source.map(|source| source.parse().unwrap()),
+            checksum: None,
+            dependencies: Vec::new(),
+        }
+    }
+
+    fn before() -> Vec<LockedPackage> {
+        vec![
+            package("app", "0.1.0", None),
+            package("itoa", "1.0.9", Some(CRATES_IO)),
+            package("ryu", "1.0.15", Some(CRATES_IO)),
+            package("serde", "1.0.160", Some(CRATES_IO)),
+            package("dep", "0.2.0", Some(&format!("{REPO}#1111111"))),
+        ]
+    }
+
+    #[test]
+    fn compatibility() {
+        let between = |from: &str, to: &str| {
+            Compatibility::between(&from.parse().unwrap(), &to.parse().unwrap())
+        };
+
+        assert_eq!(between("1.2.0", "1.4.1"), Compatibility::Compatible);
+        assert_eq!(between("1.2.0", "2.0.0"), Compatibility::Incompatible);
+        assert_eq!(between("0.2.1", "0.2.5"), Compatibility::Compatible);
+        assert_eq!(between("0.2.0", "0.3.0"), Compatibility::Incompatible);
+        assert_eq!(between("0.0.1", "0.0.2"), Compatibility::Incompatible);
+    }
+
+    #[test]
+    fn diff() {
+        let after = vec![
+            package("app", "0.1.0", None),
+            package("itoa", "1.0.9", Some(CRATES_IO)),
+            package("serde", "1.0.200", Some(CRATES_IO)),
+            package("serde", "2.0.0", Some(CRATES_IO)),
+            package("dep", "0.2.0", Some(&format!("{REPO}#2222222"))),
+            package("memchr", "2.6.0", Some(CRATES_IO)),
+        ];
+
+        let diff = LockfileDiff::new(&before(), &after);
+
+        let names = |ids: &[PackageId]| -> Vec<String> {
+            ids.iter()
+                .map(|id| format!("{} {}", id.name, id.version))
+                .collect()
+        };
+
+        // The compatible version is paired with the old one, the incompatible one is new.
+        assert_eq!(names(&diff.added), ["memchr 2.6.0", "serde 2.0.0"]);
+        assert_eq!(names(&diff.removed), ["ryu 1.0.15"]);
+        assert_eq!(diff.upgraded.len(), 1);
+        assert_eq!(diff.upgraded[0].to.to_string(), "1.0.200");
+        assert_eq!(diff.upgraded[0].compatibility, Compatibility::Compatible);
+        assert!(diff.downgraded.is_empty());
+        assert_eq!(diff.source_changed.len(), 1);
+        assert_eq!(
+            diff.source_changed[0]
+                .to
+                .as_ref()
+                .unwrap()
+                .precise
+                .as_deref(),
+            Some("2222222")
+        );
+        assert_eq!(diff.incompatible_changes().count(), 0);
+        assert!(LockfileDiff::new(&before(), &before()).is_empty());
+    }
+
+    #[test]
+    fn update_adding() {
+        let diff = LockfileDiff::from_update_output(
+            &before(),
+            "    Updating crates.io index\n     Locking 1 package to latest compatible version\n      Adding memchr v2.6.0\n",
+        );
+
+        assert!(diff.added.is_empty());
+        assert_eq!(
+            diff.added_unresolved,
+            [UnresolvedPackage {
+                name: "memchr".to_string(),
+                version: "2.6.0".parse().unwrap(),
+            }]
+        );
+    }
+
+    #[test]
+    fn update_updating() {
+        let diff = LockfileDiff::from_update_output(
+            &before(),
+            "    Updating serde v1.0.160 -> v1.0.200\n    Downgrading itoa v1.0.9 -> v1.0.5\n    Updating dep v0.2.0 (https://github.com/user/dep#1111111) -> #2222222\n",
+        );
+
+        assert_eq!(diff.upgraded.len(), 1);
+        assert_eq!(diff.upgraded[0].name, "serde");
+        assert!(diff.upgraded[0].to_source.as_ref().unwrap().is_crates_io());
+        assert_eq!(diff.downgraded.len(), 1);
+        assert_eq!(diff.downgraded[0].to.to_string(), "1.0.5");
+        assert_eq!(diff.source_changed.len(), 1);
+        assert_eq!(
+            diff.source_changed[0]
+                .to
+                .as_ref()
+                .unwrap()
+                .precise
+                .as_deref(),
+            Some("2222222")
+        );
+    }
+
+    #[test]
+    fn update_removing() {
+        let diff = LockfileDiff::from_update_output(&before(), "    Removing ryu v1.0.15\n");
+
+        assert_eq!(diff.removed, [before()[2].id()]);
+        assert!(diff.added.is_empty() && diff.added_unresolved.is_empty());
+    }
+}
@@ -0,0 +1,479 @@
+use semver::Version;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+#[cfg(feature = "json")]
+use super::Metadata;
+use super::{PackageId, ParsingError, Result, SourceId};
+
+mod config;
+mod diff;
+pub use config::UpdateConfig;
+pub use diff::{Compatibility, LockfileDiff, SourceChange, UnresolvedPackage, VersionChange};
+
+/// The name of the lockfile cargo places at the root of a workspace.
+pub const LOCKFILE_NAME: &str = "Cargo.lock";
+
+/// A parsed `Cargo.lock` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lockfile {
+    /// The version of the lockfile format.
+    pub version: LockfileVersion,
+    /// Array of all the locked packages, including workspace members.
+    pub packages: Vec<LockedPackage>,
+}
+
+/// The version of the lockfile format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LockfileVersion {
+    /// The original format, with checksums stored in the `[metadata]` table.
+    ///
+    /// Lockfiles with neither a `[metadata]` table nor a `version` marker are read as [LockfileVersion::V2].
+    V1,
+    /// Checksums are stored inline and dependencies are written in their shortest unambiguous form.
+    V2,
+    /// Same as [LockfileVersion::V2] but with an explicit `version = 3` marker.
+    V3,
+    /// Same as [LockfileVersion::V3] but with percent-encoded git references.
+    V4,
+}
+
+/// A single package in the lockfile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedPackage {
+    /// The name of the package.
+    pub name: String,
+    /// The exact version the package is locked to.
+    pub version: Version,
+    /// The source of the package.
+    ///
+    /// This is [None] for path dependencies and workspace members.
+    pub source: Option<SourceId>,
+    /// The sha256 checksum of the package, only present for registry packages.
+    pub checksum: Option<String>,
+    /// Array of the resolved dependencies of this package.
+    pub dependencies: Vec<PackageId>,
+}
+
+impl LockedPackage {
+    /// The [PackageId] of this package.
+    pub fn id(&self) -> PackageId {
+        PackageId {
+            name: self.name.clone(),
+            version: self.version.clone(),
+            source: self.source.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawLockfile {
+    version: Option<u32>,
+    #[serde(default)]
+    package: Vec<RawPackage>,
+    root: Option<RawPackage>,
+    #[serde(default)]
+    metadata: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Deserialize)]
+struct RawPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+    checksum: Option<String>,
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+impl Lockfile {
+    /// Reads and parses the lockfile at the given path.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Reads and parses the lockfile at the root of the given workspace.
+    pub fn for_workspace<P: AsRef<Path>>(workspace_root: P) -> Result<Self> {
+        Self::open(Self::path(workspace_root))
+    }
+
+    /// The path of the lockfile for the given workspace root.
+    pub fn path<P: AsRef<Path>>(workspace_root: P) -> PathBuf {
+        workspace_root.as_ref().join(LOCKFILE_NAME)
+    }
+
+    /// Returns all the locked packages with the given name.
+    ///
+    /// There may be more than one if multiple semver-incompatible versions are in use.
+    pub fn packages_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a LockedPackage> {
+        self.packages
+            .iter()
+            .filter(move |package| package.name == name)
+    }
+
+    /// Returns the locked package with the given name, if there is exactly one.
+    pub fn package(&self, name: &str) -> Option<&LockedPackage> {
+        let mut packages = self.packages.iter().filter(|package| package.name == name);
+
+        match (packages.next(), packages.next()) {
+            (Some(package), None) => Some(package),
+            _ => None,
+        }
+    }
+
+    /// Returns the locked package with the given id.
+    pub fn get(&self, id: &PackageId) -> Option<&LockedPackage> {
+        self.packages.iter().find(|package| {
+            package.name == id.name && package.version == id.version && package.source == id.source
+        })
+    }
+
+    /// Returns the version the package with the given name is locked to, if there is exactly one.
+    pub fn locked_version(&self, name: &str) -> Option<&Version> {
+        self.package(name).map(|package| &package.version)
+    }
+}
+
+impl FromStr for Lockfile {
+    type Err = ParsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let raw: RawLockfile = toml::from_str(s)?;
+
+        // Checksums of version 1 lockfiles are stored in the metadata table with keys in the
+        // form of "checksum name version (source)".
+        let mut checksums = BTreeMap::new();
+        for (key, value) in &raw.metadata {
+            if let Some(id) = key.strip_prefix("checksum ") {
+                if let Some(checksum) = value.as_str().filter(|checksum| *checksum != "<none>") {
+                    checksums.insert(id.parse::<PackageId>()?, checksum.to_string());
+                }
+            }
+        }
+
+        let version = match raw.version {
+            Some(3) => LockfileVersion::V3,
+            Some(4) => LockfileVersion::V4,
+            Some(version) => {
+                return Err(ParsingError::Lockfile(format!(
+                    "unsupported lockfile version {version}"
+                )))
+            }
+            // Only version 1 lockfiles have a metadata table, version 2 has no marker at all.
+            None if raw.metadata.is_empty() => LockfileVersion::V2,
+            None => LockfileVersion::V1,
+        };
+
+        let raw_packages: Vec<RawPackage> = raw.root.into_iter().chain(raw.package).collect();
+
+        let mut packages = Vec::with_capacity(raw_packages.len());
+        for raw_package in &raw_packages {
+            let version = raw_package.version.parse()?;
+            let source = raw_package.source.as_deref().map(str::parse).transpose()?;
+
+            packages.push(LockedPackage {
+                name: raw_package.name.clone(),
+                version,
+                source,
+                checksum: raw_package.checksum.clone(),
+                dependencies: Vec::new(),
+            });
+        }
+
+        for package in &mut packages {
+            if package.checksum.is_none() {
+                package.checksum = checksums.get(&package.id()).cloned();
+            }
+        }
+
+        // Dependencies may be written as just "name" or "name version" when unambiguous,
+        // so they can only be resolved once every package is known.
+        let mut dependencies = Vec::with_capacity(raw_packages.len());
+        for raw_package in &raw_packages {
+            dependencies.push(
+                raw_package
+                    .dependencies
+                    .iter()
+                    .map(|dependency| resolve_dependency(&packages, dependency))
+                    .collect::<Result<Vec<_>>>()?,
+            );
+        }
+
+        for (package, dependencies) in packages.iter_mut().zip(dependencies) {
+            package.dependencies = dependencies;
+        }
+
+        Ok(Lockfile { version, packages })
+    }
+}
+
+/// Resolves a dependency string to the [PackageId] of the locked package it refers to.
+fn resolve_dependency(packages: &[LockedPackage], dependency: &str) -> Result<PackageId> {
+    let mut parts = dependency.splitn(3, ' ');
+    let name = parts.next().unwrap_or_default();
+    let version = parts.next().map(Version::parse).transpose()?;
+    let source = parts
+        .next()
+        .map(|source| {
+            source
+                .strip_prefix('(')
+                .and_then(|source| source.strip_suffix(')'))
+                .ok_or_else(|| ParsingError::PackageId(dependency.to_string()))?
+                .parse::<SourceId>()
+        })
+        .transpose()?;
+
+    let mut candidates = packages.iter().filter(|package| {
+        package.name == name
+            && version
+                .as_ref()
+                .is_none_or(|version| &package.version == version)
+            && source
+                .as_ref()
+                .is_none_or(|source| package.source.as_ref() == Some(source))
+    });
+
+    match (candidates.next(), candidates.next()) {
+        (Some(package), None) => Ok(package.id()),
+        (None, _) => Err(ParsingError::Lockfile(format!(
+            "dependency \"{dependency}\" does not match any package"
+        ))),
+        (Some(_), Some(_)) => Err(ParsingError::Lockfile(format!(
+            "dependency \"{dependency}\" is ambiguous"
+        ))),
+    }
+}
+
+#[cfg(feature = "json")]
+impl Metadata {
+    /// The path of the lockfile next to the workspace root.
+    pub fn lockfile_path(&self) -> PathBuf {
+        Lockfile::path(&self.workspace_root)
+    }
+
+    /// Reads and parses the lockfile next to the workspace root.
+    pub fn lockfile(&self) -> Result<Lockfile> {
+        Lockfile::for_workspace(&self.workspace_root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cargo::{GitReference, SourceKind};
+
+    const CRATES_IO: &str = "registry+https://github.com/rust-lang/crates.io-index";
+
+    #[test]
+    fn v1() {
+        let lockfile: Lockfile = format!(
+            r#"
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "itoa 1.0.9 ({CRATES_IO})",
+]
+
+[[package]]
+name = "itoa"
+version = "1.0.9"
+source = "{CRATES_IO}"
+
+[metadata]
+"checksum itoa 1.0.9 ({CRATES_IO})" = "af150ab688ff2122fcef229be89cb50dd66af9e01a4ff320cc137eecc9bacc38"
+"#
+        )
+        .parse()
+        .unwrap();
+
+        assert_eq!(lockfile.version, LockfileVersion::V1);
+
+        let itoa = lockfile.package("itoa").unwrap();
+        assert!(itoa.source.as_ref().unwrap().is_crates_io());
+        assert_eq!(
+            itoa.checksum.as_deref(),
+            Some("af150ab688ff2122fcef229be89cb50dd66af9e01a4ff320cc137eecc9bacc38")
+        );
+
+        let app = lockfile.package("app").unwrap();
+        assert_eq!(app.source, None);
+        assert_eq!(app.dependencies, vec![itoa.id()]);
+    }
+
+    #[test]
+    fn v2() {
+        let lockfile: Lockfile = format!(
+            r#"
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "itoa",
+]
+
+[[package]]
+name = "itoa"
+version = "1.0.9"
+source = "{CRATES_IO}"
+checksum = "af150ab688ff2122fcef229be89cb50dd66af9e01a4ff320cc137eecc9bacc38"
+"#
+        )
+        .parse()
+        .unwrap();
+
+        assert_eq!(lockfile.version, LockfileVersion::V2);
+
+        let itoa = lockfile.package("itoa").unwrap();
+        assert!(itoa.checksum.is_some());
+        assert_eq!(
+            lockfile.package("app").unwrap().dependencies,
+            vec![itoa.id()]
+        );
+    }
+
+    #[test]
+    fn v2_without_checksums() {
+        let lockfile: Lockfile = r#"
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "core",
+]
+
+[[package]]
+name = "core"
+version = "0.1.0"
+"#
+        .parse()
+        .unwrap();
+
+        assert_eq!(lockfile.version, LockfileVersion::V2);
+        assert_eq!(
+            lockfile.package("app").unwrap().dependencies,
+            vec![lockfile.package("core").unwrap().id()]
+        );
+    }
+
+    #[test]
+    fn v3() {
+        let lockfile: Lockfile = format!(
+            r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "itoa 0.4.8",
+ "itoa 1.0.9",
+]
+
+[[package]]
+name = "itoa"
+version = "0.4.8"
+source = "{CRATES_IO}"
+checksum = "b71991ff56294aa922b450139ee08b3bfc70982c6b2c7562771375cf73542dd4"
+
+[[package]]
+name = "itoa"
+version = "1.0.9"
+source = "{CRATES_IO}"
+checksum = "af150ab688ff2122fcef229be89cb50dd66af9e01a4ff320cc137eecc9bacc38"
+"#
+        )
+        .parse()
+        .unwrap();
+
+        assert_eq!(lockfile.version, LockfileVersion::V3);
+        assert_eq!(lockfile.packages_named("itoa").count(), 2);
+        assert_eq!(lockfile.package("itoa"), None);
+
+        let versions: Vec<String> = lockfile
+            .package("app")
+            .unwrap()
+            .dependencies
+            .iter()
+            .map(|id| id.version.to_string())
+            .collect();
+        assert_eq!(versions, ["0.4.8", "1.0.9"]);
+    }
+
+    #[test]
+    fn v4() {
+        let lockfile: Lockfile = r#"
+version = 4
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "dep",
+]
+
+[[package]]
+name = "dep"
+version = "0.2.0"
+source = "git+https://github.com/user/dep?branch=feature%2Ffoo#5e85ba14aaa20f8133863373404cb0af69eeef2c"
+"#
+        .parse()
+        .unwrap();
+
+        assert_eq!(lockfile.version, LockfileVersion::V4);
+
+        let source = lockfile.package("dep").unwrap().source.clone().unwrap();
+        assert_eq!(
+            source.kind,
+            SourceKind::Git(GitReference::Branch("feature/foo".to_string()))
+        );
+        assert_eq!(
+            source.precise.as_deref(),
+            Some("5e85ba14aaa20f8133863373404cb0af69eeef2c")
+        );
+    }
+
+    #[test]
+    fn unsupported_version() {
+        assert!(matches!(
+            "version = 5".parse::<Lockfile>(),
+            Err(ParsingError::Lockfile(_))
+        ));
+    }
+
+    #[test]
+    fn ambiguous_dependency() {
+        let lockfile = format!(
+            r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "itoa",
+]
+
+[[package]]
+name = "itoa"
+version = "0.4.8"
+source = "{CRATES_IO}"
+
+[[package]]
+name = "itoa"
+version = "1.0.9"
+source = "{CRATES_IO}"
+"#
+        );
+
+        assert!(matches!(
+            lockfile.parse::<Lockfile>(),
+            Err(ParsingError::Lockfile(_))
+        ));
+    }
+}
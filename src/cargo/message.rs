@@ -0,0 +1,198 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{metadata::Target, unit_graph::Mode};
+
+/// A message emitted by cargo when using `--message-format json`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum Message {
+    /// A target was built, or found to be fresh.
+    CompilerArtifact(Artifact),
+    /// A diagnostic emitted by the compiler.
+    CompilerMessage(CompilerMessage),
+    /// A build script was executed.
+    BuildScriptExecuted(BuildScriptExecuted),
+    /// The build finished.
+    BuildFinished(BuildFinished),
+    /// How long a unit took to build, emitted with `--timings=json`.
+    TimingInfo(TimingInfo),
+    /// A message this version of payload doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+impl Message {
+    /// Parses every JSON message in the given output, skipping lines that aren't messages,
+    /// such as the output of test binaries.
+    pub fn parse_stream<A: AsRef<[u8]>>(bytes: A) -> Vec<Message> {
+        bytes
+            .as_ref()
+            .split(|byte| *byte == b'\n')
+            .filter_map(Self::parse_line)
+            .collect()
+    }
+
+    /// Parses a single line of output, returning [None] if it isn't a JSON message.
+    pub fn parse_line(line: &[u8]) -> Option<Message> {
+        if line.first() != Some(&b'{') {
+            return None;
+        }
+
+        serde_json::from_slice(line).ok()
+    }
+}
+
+/// A built target.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Artifact {
+    /// The Package ID of the package the target belongs to.
+    pub package_id: String,
+    /// Absolute path to the manifest of the package.
+    pub manifest_path: PathBuf,
+    /// The Cargo target.
+    pub target: Target,
+    /// The profile the target was built with.
+    pub profile: ArtifactProfile,
+    /// Array of features enabled on this target.
+    pub features: Vec<String>,
+    /// Array of files generated by the compiler.
+    pub filenames: Vec<PathBuf>,
+    /// The path to the executable, if one was built.
+    pub executable: Option<PathBuf>,
+    /// Whether the target was already up to date and didn't need to be built.
+    pub fresh: bool,
+}
+
+/// The profile settings of an [Artifact].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ArtifactProfile {
+    /// The optimization level.
+    pub opt_level: String,
+    /// The debug information level, either an integer or a string such as "line-tables-only".
+    pub debuginfo: Option<Value>,
+    /// Whether or not debug-assertions are enabled.
+    pub debug_assertions: bool,
+    /// Whether or not overflow-checks are enabled.
+    pub overflow_checks: bool,
+    /// Whether the target was built with `--test`.
+    pub test: bool,
+}
+
+/// A diagnostic emitted by the compiler while building a target.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompilerMessage {
+    /// The Package ID of the package the target belongs to.
+    pub package_id: String,
+    /// Absolute path to the manifest of the package.
+    pub manifest_path: PathBuf,
+    /// The Cargo target.
+    pub target: Target,
+    /// The diagnostic itself.
+    pub message: Diagnostic,
+}
+
+/// A compiler diagnostic.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Diagnostic {
+    /// The primary message.
+    pub message: String,
+    /// The diagnostic code, E.g. "E0308".
+    pub code: Option<DiagnosticCode>,
+    /// The severity of the diagnostic.
+    pub level: DiagnosticLevel,
+    /// The locations in the source code this diagnostic refers to.
+    pub spans: Vec<DiagnosticSpan>,
+    /// The diagnostic rendered the same way rustc would print it.
+    pub rendered: Option<String>,
+}
+
+/// The code of a [Diagnostic].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DiagnosticCode {
+    /// The code itself, E.g. "E0308" or "unused_variables".
+    pub code: String,
+    /// An explanation of the code, if any.
+    pub explanation: Option<String>,
+}
+
+/// The severity of a [Diagnostic].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DiagnosticLevel {
+    #[serde(rename = "error")]
+    Error,
+    #[serde(rename = "warning")]
+    Warning,
+    #[serde(rename = "note")]
+    Note,
+    #[serde(rename = "help")]
+    Help,
+    #[serde(rename = "failure-note")]
+    FailureNote,
+    #[serde(rename = "error: internal compiler error")]
+    Ice,
+    #[serde(other)]
+    Unknown,
+}
+
+/// A location in the source code.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DiagnosticSpan {
+    /// The file the span is in, relative to the workspace root.
+    pub file_name: PathBuf,
+    /// The first line of the span, starting at 1.
+    pub line_start: usize,
+    /// The last line of the span, starting at 1.
+    pub line_end: usize,
+    /// The first column of the span, starting at 1.
+    pub column_start: usize,
+    /// The last column of the span, starting at 1.
+    pub column_end: usize,
+    /// Whether this is the main location of the diagnostic.
+    pub is_primary: bool,
+    /// An optional label for the span.
+    pub label: Option<String>,
+}
+
+/// The output of a build script.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BuildScriptExecuted {
+    /// The Package ID of the package the build script belongs to.
+    pub package_id: String,
+    /// Array of libraries to link, from `rustc-link-lib`.
+    pub linked_libs: Vec<String>,
+    /// Array of library search paths, from `rustc-link-search`.
+    pub linked_paths: Vec<String>,
+    /// Array of enabled configuration options, from `rustc-cfg`.
+    pub cfgs: Vec<String>,
+    /// Array of environment variables to set, from `rustc-env`.
+    pub env: Vec<(String, String)>,
+    /// The `OUT_DIR` of the build script.
+    pub out_dir: PathBuf,
+}
+
+/// The end of a build.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BuildFinished {
+    /// Whether the build succeeded.
+    pub success: bool,
+}
+
+/// How long a unit took to build.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TimingInfo {
+    /// The Package ID of the package the unit belongs to.
+    pub package_id: String,
+    /// The Cargo target.
+    pub target: Target,
+    /// The "mode" of the unit.
+    pub mode: Mode,
+    /// How long the unit took to build, in seconds.
+    pub duration: f64,
+    /// How long it took for the metadata of the unit to be available, in seconds.
+    ///
+    /// Dependents that don't need to link can start building at this point.
+    pub rmeta_time: Option<f64>,
+}
@@ -0,0 +1,297 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    path::{Path, PathBuf},
+};
+
+use super::{Metadata, MetadataConfig, Package};
+use crate::cargo::{home::normalize, Cargo, Result};
+
+/// Why a package is affected by a change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AffectedReason {
+    /// Files of the package changed.
+    Changed { paths: Vec<PathBuf> },
+    /// The package depends on an affected package, directly or through other packages.
+    Dependent {
+        /// The Package ID of the affected dependency.
+        dependency: String,
+    },
+}
+
+/// A workspace member to test after a change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AffectedPackage {
+    /// The name of the package.
+    pub name: String,
+    /// The Package ID of the package.
+    pub id: String,
+    pub reason: AffectedReason,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AffectedPackages {
+    /// The affected workspace members, the changed ones first, then their dependents.
+    pub packages: Vec<AffectedPackage>,
+    /// The changed paths that don't belong to any package, E.g. the root `Cargo.lock` or a CI configuration.
+    pub unowned: Vec<PathBuf>,
+}
+
+impl AffectedPackages {
+    /// Whether a workspace member is affected.
+    pub fn contains(&self, name: &str) -> bool {
+        self.packages.iter().any(|package| package.name == name)
+    }
+
+    /// Returns the affected package with the given name.
+    pub fn get(&self, name: &str) -> Option<&AffectedPackage> {
+        self.packages.iter().find(|package| package.name == name)
+    }
+
+    /// Explains why a package is affected, following the dependencies down to the changed package,
+    /// E.g. `["app", "core"]` when "app" depends on "core", which changed.
+    pub fn chain(&self, name: &str) -> Vec<&AffectedPackage> {
+        let mut chain = Vec::new();
+        let mut current = self.get(name);
+
+        while let Some(package) = current {
+            chain.push(package);
+
+            current = match &package.reason {
+                AffectedReason::Changed { .. } => None,
+                AffectedReason::Dependent { dependency } => self
+                    .packages
+                    .iter()
+                    .find(|package| &package.id == dependency),
+            };
+        }
+
+        chain
+    }
+}
+
+impl Metadata {
+    /// Finds the workspace members affected by changes to the given paths.
+    ///
+    /// A path belongs to the package with the closest directory containing it, looking at the directory
+    /// of each manifest and of each target source. Relative paths are relative to the workspace root.
+    /// Packages depending on an affected package are affected too, with every dependency kind, which
+    /// requires the [resolve](Metadata::resolve) graph.
+    pub fn affected_packages<I, P>(&self, changed: I) -> AffectedPackages
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        // Only path packages can be changed in the workspace.
+        let roots: Vec<(PathBuf, &Package)> = self
+            .packages
+            .iter()
+            .filter(|package| package.source.is_none())
+            .flat_map(|package| {
+                package
+                    .manifest_path
+                    .parent()
+                    .into_iter()
+                    .chain(
+                        package
+                            .targets
+                            .iter()
+                            .filter_map(|target| target.src_path.parent()),
+                    )
+                    // Cargo keeps ".." in the source paths of targets outside the package, E.g. "/ws/app/../shared/lib.rs".
+                    .map(move |root| (normalize(root), package))
+            })
+            .collect();
+
+        let mut changed_packages: Vec<(&Package, Vec<PathBuf>)> = Vec::new();
+        let mut unowned = Vec::new();
+
+        for path in changed {
+            let path = normalize(&self.workspace_root.join(path.as_ref()));
+
+            let owner = roots
+                .iter()
+                .filter(|(root, _)| path.starts_with(root))
+                .max_by_key(|(root, _)| root.components().count())
+                .map(|(_, package)| *package);
+
+            match owner {
+                Some(owner) => match changed_packages
+                    .iter_mut()
+                    .find(|(package, _)| package.id == owner.id)
+                {
+                    Some((_, paths)) => paths.push(path),
+                    None => changed_packages.push((owner, vec![path])),
+                },
+                None => unowned.push(path),
+            }
+        }
+
+        // Every package that depends on each package.
+        let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+
+        for node in self.resolve.iter().flat_map(|resolve| &resolve.nodes) {
+            for dep in &node.deps {
+                dependents
+                    .entry(dep.pkg.as_str())
+                    .or_default()
+                    .push(node.id.as_str());
+            }
+        }
+
+        // Breadth first, so each dependent is explained by one of its closest changed dependencies.
+        let mut reasons: Vec<(&str, AffectedReason)> = Vec::new();
+        let mut queue = VecDeque::new();
+
+        for (package, paths) in changed_packages {
+            reasons.push((package.id.as_str(), AffectedReason::Changed { paths }));
+            queue.push_back(package.id.as_str());
+        }
+
+        while let Some(id) = queue.pop_front() {
+            for &dependent in dependents.get(id).into_iter().flatten() {
+                if !reasons.iter().any(|(affected, _)| *affected == dependent) {
+                    reasons.push((
+                        dependent,
+                        AffectedReason::Dependent {
+                            dependency: id.to_string(),
+                        },
+                    ));
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        let packages = reasons
+            .into_iter()
+            .filter(|(id, _)| self.workspace_members.iter().any(|member| member == id))
+            .filter_map(|(id, reason)| {
+                let package = self.packages.iter().find(|package| package.id == id)?;
+
+                Some(AffectedPackage {
+                    name: package.name.clone(),
+                    id: package.id.clone(),
+                    reason,
+                })
+            })
+            .collect();
+
+        AffectedPackages { packages, unowned }
+    }
+}
+
+impl Cargo {
+    /// Finds the workspace members affected by changes to the given paths, see [Metadata::affected_packages].
+    pub fn affected_packages<I, P>(
+        &mut self,
+        changed: I,
+        manifest_path: Option<PathBuf>,
+    ) -> Result<AffectedPackages>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let metadata = self.metadata(MetadataConfig {
+            manifest_path,
+            ..Default::default()
+        })?;
+
+        Ok(metadata.affected_packages(changed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn package(name: &str, dir: &str, targets: Value) -> Package {
+        Package {
+            id: format!("path+file:///ws/{dir}#{name}@0.1.0"),
+            targets: serde_json::from_value(targets).unwrap(),
+            manifest_path: PathBuf::from(format!("/ws/{dir}/Cargo.toml")),
+            ..Package::fixture(name)
+        }
+    }
+
+    fn node(id: &str, deps: &[&str]) -> Value {
+        let deps: Vec<_> = deps
+            .iter()
+            .map(|dep| json!({ "name": "dep", "pkg": dep, "dep_kinds": [] }))
+            .collect();
+
+        json!({ "id": id, "dependencies": [], "deps": deps, "features": [] })
+    }
+
+    /// "cli" depends on "app", which depends on "core", and "tool" is nested in the directory of "core".
+    fn metadata() -> Metadata {
+        let core = "path+file:///ws/core#core@0.1.0";
+        let app = "path+file:///ws/app#app@0.1.0";
+        let cli = "path+file:///ws/cli#cli@0.1.0";
+        let tool = "path+file:///ws/core/tool#tool@0.1.0";
+
+        let shared = json!([{
+            "kind": ["lib"],
+            "crate_types": ["lib"],
+            "name": "app",
+            "src_path": "/ws/app/../shared/lib.rs",
+            "edition": "2021",
+            "required-features": null,
+            "doc": true,
+            "doctest": true,
+            "test": true,
+        }]);
+
+        serde_json::from_value(json!({
+            "packages": [
+                package("core", "core", json!([])),
+                package("app", "app", shared),
+                package("cli", "cli", json!([])),
+                package("tool", "core/tool", json!([])),
+            ],
+            "workspace_members": [core, app, cli, tool],
+            "resolve": {
+                "nodes": [node(core, &[]), node(app, &[core]), node(cli, &[app]), node(tool, &[])],
+                "root": null,
+            },
+            "target_directory": "/ws/target",
+            "version": 1,
+            "workspace_root": "/ws",
+            "metadata": null,
+        }))
+        .unwrap()
+    }
+
+    fn names<'a>(packages: impl IntoIterator<Item = &'a AffectedPackage>) -> Vec<&'a str> {
+        packages
+            .into_iter()
+            .map(|package| package.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn dependents() {
+        let affected = metadata().affected_packages(["core/src/lib.rs", "Cargo.lock"]);
+
+        assert_eq!(names(&affected.packages), ["core", "app", "cli"]);
+        assert_eq!(
+            affected.get("core").unwrap().reason,
+            AffectedReason::Changed {
+                paths: vec![PathBuf::from("/ws/core/src/lib.rs")]
+            }
+        );
+        assert_eq!(names(affected.chain("cli")), ["cli", "app", "core"]);
+        assert_eq!(affected.unowned, [PathBuf::from("/ws/Cargo.lock")]);
+    }
+
+    #[test]
+    fn closest_owner() {
+        // The nested package owns its files, rather than the package around it.
+        let affected = metadata().affected_packages(["core/tool/src/main.rs"]);
+        assert_eq!(names(&affected.packages), ["tool"]);
+
+        // Targets outside of the package directory make it own their directory too.
+        let affected = metadata().affected_packages(["/ws/shared/util.rs"]);
+        assert_eq!(names(&affected.packages), ["app", "cli"]);
+        assert!(!affected.contains("core"));
+    }
+}
@@ -0,0 +1,635 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    env, fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+use super::{Metadata, MetadataConfig};
+use crate::cargo::{cargo_home, hash::StableHasher, Cargo, Hermetic, Result, Version};
+
+/// The directory, inside the target directory, the cache is stored in.
+const CACHE_DIR: &str = "payload-metadata";
+
+/// How often a [MetadataWatcher] checks if it was stopped while waiting.
+const STOP_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A cache of `cargo metadata` results, used by [Cargo::metadata] when set with [Cargo::metadata_cache].
+///
+/// Results are keyed by the [MetadataConfig], the version of cargo, its environment and the current directory,
+/// and are reused until one of their inputs changes: the manifests and lockfile of the workspace, the source
+/// directories targets are discovered from, and the cargo configuration files.
+///
+/// The version of cargo is only asked once per cache.
+///
+/// Clones share the same results.
+#[derive(Debug, Clone, Default)]
+pub struct MetadataCache {
+    inner: Arc<Mutex<CacheInner>>,
+}
+
+#[derive(Debug, Default)]
+struct CacheInner {
+    entries: HashMap<u64, CacheEntry>,
+    /// The version of every cargo seen, by [invocation_key].
+    versions: HashMap<u64, Version>,
+    persist: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    fingerprint: Fingerprint,
+    metadata: Metadata,
+}
+
+impl MetadataCache {
+    /// Creates a cache only kept in memory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also stores the results in the target directory, so that they are reused by later processes.
+    pub fn persistent() -> Self {
+        let cache = Self::default();
+        cache.lock().persist = true;
+        cache
+    }
+
+    /// Forgets every result, both in memory and on disk for the workspaces seen by this cache.
+    pub fn invalidate(&self) {
+        let mut inner = self.lock();
+
+        if inner.persist {
+            for entry in inner.entries.values() {
+                let _ = fs::remove_dir_all(entry.metadata.target_directory.join(CACHE_DIR));
+            }
+        }
+
+        inner.entries.clear();
+        inner.versions.clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheInner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns the cached result for the config, running `cargo metadata` if there is none or it's stale.
+    pub(crate) fn get(&self, cargo: &mut Cargo, config: &MetadataConfig) -> Result<Metadata> {
+        let key = self.cache_key(cargo, config)?;
+
+        let (persist, cached) = {
+            let inner = self.lock();
+            (inner.persist, inner.entries.get(&key).cloned())
+        };
+
+        let cold_target_dir = if persist {
+            cold_target_dir(config)
+        } else {
+            None
+        };
+
+        let cached = match cached {
+            Some(entry) => Some(entry),
+            None => cold_target_dir
+                .as_ref()
+                .and_then(|target_dir| read_disk(&disk_path(target_dir, key))),
+        };
+
+        if let Some(entry) = cached.filter(|entry| entry.fingerprint.is_fresh()) {
+            let metadata = entry.metadata.clone();
+            self.lock().entries.insert(key, entry);
+            return Ok(metadata);
+        }
+
+        // Creating the target directory changes the modification time of the package directory, which is
+        // an input, so it has to exist before cargo runs.
+        if let Some(target_dir) = &cold_target_dir {
+            let _ = fs::create_dir_all(target_dir.join(CACHE_DIR));
+        }
+
+        let started = SystemTime::now();
+        let metadata = cargo.uncached_metadata(config)?;
+
+        if persist {
+            let _ = fs::create_dir_all(metadata.target_directory.join(CACHE_DIR));
+        }
+
+        let entry = CacheEntry {
+            fingerprint: Fingerprint::new(inputs(&metadata), started),
+            metadata: metadata.clone(),
+        };
+
+        if persist {
+            // The cache is only an optimization, failing to store it isn't an error.
+            let _ = write_disk(&disk_path(&metadata.target_directory, key), &entry);
+        }
+
+        self.lock().entries.insert(key, entry);
+
+        Ok(metadata)
+    }
+
+    /// The key of a result, from everything that changes the output of `cargo metadata` but isn't a file.
+    fn cache_key(&self, cargo: &mut Cargo, config: &MetadataConfig) -> Result<u64> {
+        let invocation = invocation_key(cargo)?;
+        let cached_version = self.lock().versions.get(&invocation).cloned();
+
+        let version = match cached_version {
+            Some(version) => version,
+            None => {
+                let version = cargo.version()?;
+                self.lock().versions.insert(invocation, version.clone());
+                version
+            }
+        };
+
+        let mut hasher = StableHasher::new();
+
+        invocation.hash(&mut hasher);
+        config.hash(&mut hasher);
+        version.hash(&mut hasher);
+        (cargo.frozen, cargo.locked, cargo.offline).hash(&mut hasher);
+        cargo
+            .hermetic
+            .as_ref()
+            .map(Hermetic::options)
+            .hash(&mut hasher);
+
+        Ok(hasher.finish())
+    }
+}
+
+/// Environment variables of the process that change the output of `cargo metadata`.
+const KEY_ENV: [&str; 3] = ["CARGO_TARGET_DIR", "CARGO_BUILD_TARGET", "RUSTUP_TOOLCHAIN"];
+
+/// Identifies the cargo that runs: its path, toolchain and environment, and the current directory
+/// toolchain files are found from.
+fn invocation_key(cargo: &Cargo) -> Result<u64> {
+    let mut hasher = StableHasher::new();
+
+    cargo.path.hash(&mut hasher);
+    cargo.toolchain.hash(&mut hasher);
+    cargo.envs.hash(&mut hasher);
+
+    for name in KEY_ENV {
+        env::var_os(name).hash(&mut hasher);
+    }
+
+    env::current_dir()?.hash(&mut hasher);
+
+    Ok(hasher.finish())
+}
+
+/// Finds the target directory before `cargo metadata` ran, to read a result stored by a previous process.
+///
+/// Returns [None] when it can't be found, e.g. if the cargo configuration can't be read, which only misses
+/// the cache and leaves reporting the error to cargo.
+fn cold_target_dir(config: &MetadataConfig) -> Option<PathBuf> {
+    if let Some(target_dir) = env::var_os("CARGO_TARGET_DIR") {
+        return Some(PathBuf::from(target_dir));
+    }
+
+    let workspace_root = guess_workspace_root(config).ok()?;
+
+    #[cfg(feature = "toml")]
+    if let Some(target_dir) = crate::cargo::CargoConfig::discover(&workspace_root)
+        .ok()?
+        .target_dir(&workspace_root)
+    {
+        return Some(target_dir);
+    }
+
+    Some(workspace_root.join("target"))
+}
+
+/// Finds the workspace root without running cargo: the closest ancestor of the package with a `[workspace]`
+/// table, or the package itself.
+///
+/// Workspaces pointed to by the `package.workspace` key aren't found, which only misses the cache.
+fn guess_workspace_root(config: &MetadataConfig) -> Result<PathBuf> {
+    let current_dir = env::current_dir()?;
+
+    let package_dir = match &config.manifest_path {
+        Some(manifest_path) => current_dir
+            .join(manifest_path)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| current_dir.clone()),
+        None => current_dir
+            .ancestors()
+            .find(|dir| dir.join("Cargo.toml").is_file())
+            .unwrap_or(&current_dir)
+            .to_path_buf(),
+    };
+
+    let workspace_root = package_dir.ancestors().find(|dir| {
+        fs::read_to_string(dir.join("Cargo.toml")).is_ok_and(|manifest| {
+            manifest.lines().any(|line| {
+                let line = line.trim();
+                line == "[workspace]" || line.starts_with("[workspace.")
+            })
+        })
+    });
+
+    Ok(workspace_root.unwrap_or(&package_dir).to_path_buf())
+}
+
+fn disk_path(target_dir: &Path, key: u64) -> PathBuf {
+    target_dir.join(CACHE_DIR).join(format!("{key:016x}.json"))
+}
+
+fn read_disk(path: &Path) -> Option<CacheEntry> {
+    serde_json::from_slice(&fs::read(path).ok()?).ok()
+}
+
+fn write_disk(path: &Path, entry: &CacheEntry) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, serde_json::to_vec(entry)?)?;
+
+    Ok(())
+}
+
+/// The files and directories that `cargo metadata` reads to produce the given result.
+fn inputs(metadata: &Metadata) -> Vec<PathBuf> {
+    let root = &metadata.workspace_root;
+    let mut inputs = vec![root.join("Cargo.toml"), root.join("Cargo.lock")];
+
+    // Path packages, including the workspace members, can be edited.
+    for package in metadata
+        .packages
+        .iter()
+        .filter(|package| package.source.is_none())
+    {
+        inputs.push(package.manifest_path.clone());
+
+        // Targets are discovered from these directories, adding a file changes their modification time.
+        if let Some(dir) = package.manifest_path.parent() {
+            inputs.push(dir.to_path_buf());
+
+            for target_dir in ["src", "src/bin", "examples", "tests", "benches"] {
+                inputs.push(dir.join(target_dir));
+            }
+        }
+    }
+
+    for dir in root.ancestors() {
+        inputs.push(dir.join(".cargo").join("config"));
+        inputs.push(dir.join(".cargo").join("config.toml"));
+    }
+
+    if let Some(cargo_home) = cargo_home() {
+        inputs.push(cargo_home.join("config"));
+        inputs.push(cargo_home.join("config.toml"));
+    }
+
+    inputs.sort();
+    inputs.dedup();
+
+    inputs
+}
+
+/// The state of a set of files, compared to find out if any of them changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Fingerprint(Vec<(PathBuf, Option<Stamp>)>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Stamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+impl Fingerprint {
+    /// Takes the stamps of the inputs of a `cargo metadata` run that started at `started`.
+    ///
+    /// A file modified while cargo ran may not be reflected by the result even though it has the new stamp,
+    /// so it's recorded without one, which makes the result stale right away.
+    fn new(paths: Vec<PathBuf>, started: SystemTime) -> Self {
+        Fingerprint(
+            paths
+                .into_iter()
+                .map(|path| {
+                    let stamp = stamp(&path).filter(|stamp| stamp.modified < started);
+                    (path, stamp)
+                })
+                .collect(),
+        )
+    }
+
+    /// Whether none of the files changed, was created or was removed.
+    fn is_fresh(&self) -> bool {
+        self.0.iter().all(|(path, old)| stamp(path) == *old)
+    }
+}
+
+/// Missing files have no stamp, so creating one is also a change.
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = fs::metadata(path).ok()?;
+
+    Some(Stamp {
+        modified: metadata.modified().ok()?,
+        len: metadata.len(),
+    })
+}
+
+/// Re-runs `cargo metadata` in the background when its inputs change, created by [Cargo::watch_metadata].
+///
+/// Dropping the watcher stops it.
+#[derive(Debug)]
+pub struct MetadataWatcher {
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MetadataWatcher {
+    /// Stops watching, waiting for a running `cargo metadata` to finish.
+    pub fn stop(mut self) {
+        self.stop_thread();
+    }
+
+    fn stop_thread(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for MetadataWatcher {
+    fn drop(&mut self) {
+        self.stop_thread();
+    }
+}
+
+impl Cargo {
+    /// Runs `cargo metadata` in a background thread, and again every time its inputs change.
+    ///
+    /// The callback receives the first result and every later one. Inputs are checked every `interval`.
+    /// After a failure, E.g. a manifest with a syntax error, the next change runs it again.
+    pub fn watch_metadata<F>(
+        &self,
+        config: MetadataConfig,
+        interval: Duration,
+        mut on_change: F,
+    ) -> MetadataWatcher
+    where
+        F: FnMut(Result<Metadata>) + Send + 'static,
+    {
+        let mut cargo = self.clone();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+
+        let thread = thread::spawn(move || {
+            let mut fingerprint: Option<Fingerprint> = None;
+
+            while !thread_stopped.load(Ordering::SeqCst) {
+                if fingerprint
+                    .as_ref()
+                    .is_none_or(|fingerprint| !fingerprint.is_fresh())
+                {
+                    let started = SystemTime::now();
+                    let result = cargo.uncached_metadata(&config);
+
+                    let paths = match (&result, fingerprint) {
+                        (Ok(metadata), _) => inputs(metadata),
+                        // Without a result the inputs are unknown, the previous ones are the best guess.
+                        (Err(_), Some(Fingerprint(paths))) => {
+                            paths.into_iter().map(|(path, _)| path).collect()
+                        }
+                        (Err(_), None) => fallback_inputs(&config),
+                    };
+
+                    fingerprint = Some(Fingerprint::new(paths, started));
+
+                    on_change(result);
+                }
+
+                let mut waited = Duration::ZERO;
+
+                while waited < interval && !thread_stopped.load(Ordering::SeqCst) {
+                    let step = STOP_INTERVAL.min(interval - waited);
+                    thread::sleep(step);
+                    waited += step;
+                }
+            }
+        });
+
+        MetadataWatcher {
+            stopped,
+            thread: Some(thread),
+        }
+    }
+}
+
+/// The manifests that may belong to the workspace when `cargo metadata` never succeeded.
+fn fallback_inputs(config: &MetadataConfig) -> Vec<PathBuf> {
+    let dir = match &config.manifest_path {
+        Some(manifest_path) => manifest_path.parent().map(Path::to_path_buf),
+        None => env::current_dir().ok(),
+    };
+
+    dir.iter()
+        .flat_map(|dir| dir.ancestors())
+        .flat_map(|dir| [dir.join("Cargo.toml"), dir.join("Cargo.lock")])
+        .collect()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::cargo::home::temp_dir;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn invocations() {
+        let dir = temp_dir("metadata-cache");
+        let package = dir.join("hello");
+        let log = dir.join("cargo.log");
+        let stub = dir.join("cargo");
+
+        fs::create_dir_all(package.join("src")).unwrap();
+        fs::write(
+            package.join("Cargo.toml"),
+            "[package]\nname = \"hello\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+        )
+        .unwrap();
+        fs::write(package.join("src").join("lib.rs"), "").unwrap();
+
+        // Records the first argument of every invocation.
+        fs::write(
+            &stub,
+            format!(
+                "#!/bin/sh\necho \"$1\" >> '{}'\nexec cargo \"$@\"\n",
+                log.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let config = MetadataConfig {
+            manifest_path: Some(package.join("Cargo.toml")),
+            no_deps: true,
+            ..Default::default()
+        };
+
+        let mut cargo = Cargo::new();
+        cargo.path(&stub);
+
+        let results: Vec<_> = (0..3)
+            .map(|_| {
+                cargo.metadata_cache(Some(MetadataCache::persistent()));
+                cargo.metadata(config.clone())
+            })
+            .collect();
+
+        cargo.env("CARGO_BUILD_TARGET", "wasm32-unknown-unknown");
+        let with_env = cargo.metadata(config);
+
+        let invocations = fs::read_to_string(&log);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(results.iter().all(Result::is_ok));
+        assert!(with_env.is_ok());
+
+        // Every cache asks for the version once, then the result stored on disk by the first one is reused.
+        // A different environment misses it.
+        assert_eq!(
+            invocations.unwrap().lines().collect::<Vec<_>>(),
+            ["-Vv", "metadata", "-Vv", "-Vv", "-Vv", "metadata"]
+        );
+    }
+
+    #[test]
+    fn changed_while_running() {
+        let dir = temp_dir("metadata-cache");
+        let package = dir.join("hello");
+        let manifest = package.join("Cargo.toml");
+        let log = dir.join("cargo.log");
+        let stub = dir.join("cargo");
+
+        fs::create_dir_all(package.join("src")).unwrap();
+        fs::write(
+            &manifest,
+            "[package]\nname = \"hello\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+        )
+        .unwrap();
+        fs::write(package.join("src").join("lib.rs"), "").unwrap();
+
+        // Edits the manifest after cargo read it, but before the cache takes its stamp.
+        fs::write(
+            &stub,
+            format!(
+                "#!/bin/sh\necho \"$1\" >> '{}'\ncargo \"$@\" || exit\n\
+                 if [ \"$1\" = metadata ]; then touch '{}'; fi\n",
+                log.display(),
+                manifest.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let config = MetadataConfig {
+            manifest_path: Some(manifest.clone()),
+            no_deps: true,
+            ..Default::default()
+        };
+
+        let mut cargo = Cargo::new();
+        cargo.path(&stub);
+        cargo.metadata_cache(Some(MetadataCache::new()));
+
+        let first = cargo.metadata(config.clone());
+        let second = cargo.metadata(config);
+
+        let invocations = fs::read_to_string(&log);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(first.is_ok());
+        assert!(second.is_ok());
+
+        // The first result may predate the edit, so it isn't reused.
+        assert_eq!(
+            invocations.unwrap().lines().collect::<Vec<_>>(),
+            ["-Vv", "metadata", "metadata"]
+        );
+    }
+
+    #[test]
+    fn hermetic_keys() {
+        let cache = MetadataCache::new();
+        let config = MetadataConfig::default();
+        let key = |hermetic: Option<Hermetic>| {
+            let mut cargo = Cargo::new();
+            cargo.hermetic(hermetic);
+            cache.cache_key(&mut cargo, &config).unwrap()
+        };
+
+        let hermetic = Hermetic::new().unwrap();
+
+        assert_ne!(key(None), key(Some(hermetic.clone())));
+        // The private `CARGO_HOME` is empty, so it doesn't matter which one is used.
+        assert_eq!(
+            key(Some(hermetic.clone())),
+            key(Some(Hermetic::new().unwrap()))
+        );
+        assert_ne!(
+            key(Some(hermetic.clone())),
+            key(Some(hermetic.clone().config("build.jobs=1")))
+        );
+        assert_ne!(
+            key(Some(hermetic.clone())),
+            key(Some(hermetic.keep_env("CARGO_TERM_COLOR")))
+        );
+    }
+
+    /// A configuration cargo reads but [CargoConfig](crate::cargo::CargoConfig) rejects only skips the cache.
+    #[cfg(feature = "toml")]
+    #[test]
+    fn unsupported_config() {
+        let dir = temp_dir("metadata-cache");
+        let package = dir.join("hello");
+
+        fs::create_dir_all(package.join("src")).unwrap();
+        fs::create_dir_all(package.join(".cargo")).unwrap();
+        fs::write(
+            package.join("Cargo.toml"),
+            "[package]\nname = \"hello\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+        )
+        .unwrap();
+        fs::write(package.join("src").join("lib.rs"), "").unwrap();
+        fs::write(
+            package.join(".cargo").join("config.toml"),
+            "[build]\njobs = 1.5\n",
+        )
+        .unwrap();
+
+        let config = MetadataConfig {
+            manifest_path: Some(package.join("Cargo.toml")),
+            no_deps: true,
+            ..Default::default()
+        };
+
+        let mut cargo = Cargo::new();
+        cargo.metadata_cache(Some(MetadataCache::persistent()));
+        let metadata = cargo.metadata(config.clone());
+        let cached = cargo.metadata(config.clone());
+        let target_dir = cold_target_dir(&config);
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(target_dir, None);
+        assert_eq!(metadata.unwrap(), cached.unwrap());
+    }
+}
@@ -1,17 +1,44 @@
 use std::{path::PathBuf, process::Command};
 use target_lexicon::Triple;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct MetadataConfig {
     pub features: Option<Features>,
     pub filter_platform: Option<Triple>,
     pub manifest_path: Option<PathBuf>,
+    /// Only output the workspace members, without fetching dependencies.
+    pub no_deps: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[allow(clippy::enum_variant_names)]
 pub enum Features {
     AllFeatures,
     NoDefaultFeatures,
     SomeFeatures(Vec<String>),
+    /// Only the given features, without the default ones.
+    OnlyFeatures(Vec<String>),
+}
+
+impl Features {
+    pub(crate) fn apply(&self, command: &mut Command) {
+        match self {
+            Features::AllFeatures => {
+                command.arg("--all-features");
+            }
+            Features::NoDefaultFeatures => {
+                command.arg("--no-default-features");
+            }
+            Features::SomeFeatures(features) => {
+                command.arg("--features").arg(features.join(","));
+            }
+            Features::OnlyFeatures(features) => {
+                command.arg("--no-default-features");
+
+                if !features.is_empty() {
+                    command.arg("--features").arg(features.join(","));
+                }
+            }
+        }
+    }
 }
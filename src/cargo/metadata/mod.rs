@@ -6,8 +6,15 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use serde_with::{serde_as, DisplayFromStr};
 
+use super::RustVersion;
+
+mod affected;
+mod cache;
 mod config;
+pub use affected::{AffectedPackage, AffectedPackages, AffectedReason};
+pub use cache::{MetadataCache, MetadataWatcher};
 pub use config::{Features, MetadataConfig};
 
 /// The parsed output of `cargo metadata`
@@ -42,7 +49,17 @@ pub struct Metadata {
     pub workspace_metadata: Option<Value>,
 }
 
+impl Metadata {
+    /// Returns the packages that are members of the workspace.
+    pub fn workspace_packages(&self) -> impl Iterator<Item = &Package> {
+        self.packages
+            .iter()
+            .filter(|package| self.workspace_members.contains(&package.id))
+    }
+}
+
 /// A single rust package.
+#[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Package {
     /// The name of the package.
@@ -89,7 +106,8 @@ pub struct Package {
     /// The default binary picked by cargo run.
     pub default_run: Option<String>,
     /// The minimum supported rust version.
-    pub rust_version: Option<String>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub rust_version: Option<RustVersion>,
     /// Array of keywords from the manifest.
     pub keywords: Vec<String>,
     /// The readme value from the manifest or [None] if not specified.
@@ -107,6 +125,39 @@ pub struct Package {
     pub links: Option<String>,
 }
 
+#[cfg(test)]
+impl Package {
+    /// A workspace package in a directory of "/ws" named like it, with every other field empty, for tests to override.
+    pub(crate) fn fixture(name: &str) -> Self {
+        Package {
+            name: name.to_string(),
+            version: "0.1.0".to_string(),
+            id: format!("path+file:///ws/{name}#0.1.0"),
+            license: None,
+            license_file: None,
+            description: None,
+            source: None,
+            dependencies: Vec::new(),
+            targets: Vec::new(),
+            features: HashMap::new(),
+            manifest_path: PathBuf::from(format!("/ws/{name}/Cargo.toml")),
+            package_metadata: None,
+            publish: Publishing(None),
+            authors: Vec::new(),
+            categories: Vec::new(),
+            default_run: None,
+            rust_version: None,
+            keywords: Vec::new(),
+            readme: None,
+            repository: None,
+            homepage: None,
+            documentation: None,
+            edition: Edition::E2021,
+            links: None,
+        }
+    }
+}
+
 /// The publishing restrictions of this package.
 ///
 /// Call `restrictions` to get the actual restrictions.
@@ -219,6 +270,9 @@ pub enum Edition {
     /// Edition 2021
     #[serde(rename = "2021")]
     E2021,
+    /// Edition 2024
+    #[serde(rename = "2024")]
+    E2024,
 }
 
 impl Debug for Edition {
@@ -227,6 +281,7 @@ impl Debug for Edition {
             Self::E2015 => write!(f, "2015"),
             Self::E2018 => write!(f, "2018"),
             Self::E2021 => write!(f, "2021"),
+            Self::E2024 => write!(f, "2024"),
         }
     }
 }
@@ -237,9 +292,54 @@ impl Display for Edition {
             Self::E2015 => write!(f, "2015"),
             Self::E2018 => write!(f, "2018"),
             Self::E2021 => write!(f, "2021"),
+            Self::E2024 => write!(f, "2024"),
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct Resolve {}
+pub struct Resolve {
+    /// Array of nodes, one for each resolved package.
+    pub nodes: Vec<Node>,
+    /// The Package ID of the root package, [None] for a virtual workspace.
+    pub root: Option<String>,
+}
+
+impl Resolve {
+    /// Returns the node of a package.
+    pub fn node(&self, id: &str) -> Option<&Node> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+}
+
+/// A package in the resolved dependency graph.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Node {
+    /// The Package ID of this node.
+    pub id: String,
+    /// The Package IDs of all the dependencies of this package.
+    pub dependencies: Vec<String>,
+    /// The dependencies of this package, with their name and kinds.
+    pub deps: Vec<NodeDep>,
+    /// The features enabled on this package.
+    pub features: Vec<String>,
+}
+
+/// A dependency of a node.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NodeDep {
+    /// The name of the dependency's library target, with dashes replaced by underscores.
+    pub name: String,
+    /// The Package ID of the dependency.
+    pub pkg: String,
+    /// Array of dependency kinds, since a package can be both a normal and a dev dependency.
+    pub dep_kinds: Vec<DepKindInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DepKindInfo {
+    /// The dependency kind, "dev", "build" or [None] for a normal dependency.
+    pub kind: Option<String>,
+    /// The target platform of the dependency, E.g. "cfg(windows)", or [None] for every platform.
+    pub target: Option<String>,
+}
@@ -1,31 +1,122 @@
 use std::{
     env,
-    ffi::OsStr,
-    path::PathBuf,
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
     process::{Command, Output},
+    time::{Duration, Instant},
 };
 
 use which::which;
 
+#[cfg(feature = "json")]
+pub mod build;
+pub mod build_script;
+#[cfg(feature = "json")]
+pub mod clean;
+#[cfg(feature = "toml")]
+pub mod config;
+#[cfg(feature = "json")]
+pub mod doc;
+pub mod driver;
 pub mod error;
+pub mod fetch;
+#[cfg(feature = "json")]
+mod hash;
+pub mod hermetic;
+pub mod home;
+#[cfg(feature = "json")]
+pub mod index;
+#[cfg(feature = "json")]
+pub mod install;
+#[cfg(feature = "toml")]
+pub mod lockfile;
+#[cfg(feature = "json")]
+pub mod message;
 #[cfg(feature = "json")]
 pub mod metadata;
 #[cfg(feature = "json")]
+pub mod msrv;
+#[cfg(feature = "json")]
+pub mod package;
+pub mod package_id;
+pub mod process;
+#[cfg(feature = "release")]
+pub mod release;
+pub mod rust_version;
+#[cfg(feature = "sbom")]
+pub mod sbom;
+pub mod source_id;
+#[cfg(feature = "json")]
+pub mod timings;
+#[cfg(feature = "json")]
 pub mod unit_graph;
+#[cfg(feature = "toml")]
+pub mod vendor;
 pub mod version;
 
+#[cfg(feature = "json")]
+pub use build::{
+    ArtifactLayout, BenchConfig, BenchOutput, BuildConfig, BuildObserver, BuildOutput,
+    FeatureMatrixConfig, MatrixReport, RunConfig, RunHandle,
+};
+pub use build_script::{BuildEnv, Directives};
+#[cfg(feature = "json")]
+pub use clean::{CleanConfig, CleanOutput};
+#[cfg(feature = "toml")]
+pub use config::CargoConfig;
+#[cfg(feature = "json")]
+pub use doc::DocConfig;
+pub use driver::{Cross, Driver, Wrapper};
 pub use error::{ParsingError, Result};
+pub use fetch::{DownloadedPackage, FetchConfig};
+pub use hermetic::Hermetic;
+pub use home::cargo_home;
+#[cfg(feature = "json")]
+pub use index::{IndexEntry, RegistryIndex};
+#[cfg(feature = "json")]
+pub use install::{InstallConfig, InstalledPackages};
+#[cfg(feature = "toml")]
+pub use lockfile::{LockedPackage, Lockfile, LockfileDiff, LockfileVersion, UpdateConfig};
+#[cfg(feature = "json")]
+pub use metadata::{AffectedPackages, Features, Metadata, MetadataCache, MetadataConfig};
+#[cfg(feature = "json")]
+pub use msrv::{MsrvCheck, MsrvConfig, Toolchain};
+#[cfg(feature = "json")]
+pub use package::{PackageConfig, PackageOutput};
+pub use package_id::PackageId;
+pub use process::CancellationToken;
+#[cfg(feature = "release")]
+pub use release::{Bump, ReleaseConfig, ReleasePlan};
+pub use rust_version::RustVersion;
+#[cfg(feature = "sbom")]
+pub use sbom::{Sbom, SbomConfig};
+pub use source_id::{GitReference, SourceId, SourceKind};
 #[cfg(feature = "json")]
-pub use metadata::{Features, Metadata, MetadataConfig};
+pub use timings::TimingReport;
 #[cfg(feature = "json")]
 pub use unit_graph::UnitGraph;
-pub use version::Version;
+#[cfg(feature = "toml")]
+pub use vendor::{VendorConfig, VendoredSources};
+pub use version::{Capabilities, Version};
 
+#[derive(Debug, Clone)]
 pub struct Cargo {
     path: PathBuf,
     frozen: bool,
     locked: bool,
     offline: bool,
+    envs: Vec<(OsString, OsString)>,
+    toolchain: Option<String>,
+    driver: Driver,
+    timeout: Option<Duration>,
+    /// When the current [with_timeout](Cargo::with_timeout) call times out, and its timeout.
+    deadline: Option<(Instant, Duration)>,
+    cancellation: Option<CancellationToken>,
+    hermetic: Option<Hermetic>,
+    #[cfg(feature = "json")]
+    observer: Option<build::SharedObserver>,
+    #[cfg(feature = "json")]
+    metadata_cache: Option<MetadataCache>,
 }
 
 impl Cargo {
@@ -40,6 +131,17 @@ impl Cargo {
             frozen: false,
             locked: false,
             offline: false,
+            envs: Vec::new(),
+            toolchain: None,
+            driver: Driver::Cargo,
+            timeout: None,
+            deadline: None,
+            cancellation: None,
+            hermetic: None,
+            #[cfg(feature = "json")]
+            observer: None,
+            #[cfg(feature = "json")]
+            metadata_cache: None,
         }
     }
 
@@ -71,14 +173,147 @@ impl Cargo {
         self
     }
 
+    /// Sets an environment variable for every cargo invocation, E.g. "CARGO_HOME".
+    pub fn env<K: AsRef<OsStr>, V: AsRef<OsStr>>(&mut self, key: K, value: V) -> &mut Self {
+        self.envs
+            .push((key.as_ref().to_os_string(), value.as_ref().to_os_string()));
+        self
+    }
+
+    /// Sets the rustup toolchain to run cargo with, passed as "+toolchain", E.g. "nightly".
+    ///
+    /// This only works when the cargo [path](Self::path) is the rustup proxy.
+    pub fn toolchain<S: Into<String>>(&mut self, toolchain: Option<S>) -> &mut Self {
+        self.toolchain = toolchain.map(Into::into);
+        self
+    }
+
+    /// Sets the program that runs the build commands, E.g. [Cross] to build inside a container.
+    ///
+    /// Only [build](Self::build), [check](Self::check), [test](Self::test), [bench](Self::bench) and
+    /// [run](Self::run) go through the driver, other commands such as [metadata](Self::metadata) run cargo
+    /// on the host. Paths in the messages of build commands are mapped back to host paths.
+    pub fn driver(&mut self, driver: Driver) -> &mut Self {
+        self.driver = driver;
+        self
+    }
+
+    /// Sets how long every cargo invocation can run before being killed with [ParsingError::Timeout].
+    ///
+    /// For a single call use [with_timeout](Self::with_timeout).
+    ///
+    /// Like with a [cancellation token](Self::cancellation_token), cargo then runs in its own process group
+    /// on unix and doesn't receive the signals of the terminal.
+    pub fn timeout(&mut self, timeout: Option<Duration>) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Runs a call, failing with [ParsingError::Timeout] if the cargo invocations it makes take longer than
+    /// the timeout in total, E.g. `cargo.with_timeout(Duration::from_secs(60), |cargo| cargo.build(config))`.
+    ///
+    /// The [timeout](Self::timeout) of every invocation still applies.
+    pub fn with_timeout<T>(
+        &mut self,
+        timeout: Duration,
+        call: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        let deadline = Instant::now() + timeout;
+        // Nested calls can't extend the deadline of the outer one.
+        let previous = self.deadline;
+        self.deadline = match previous {
+            Some((previous_deadline, _)) if previous_deadline < deadline => previous,
+            _ => Some((deadline, timeout)),
+        };
+
+        let result = call(self);
+        self.deadline = previous;
+
+        result
+    }
+
+    /// Sets a token that kills the running cargo invocation, failing with [ParsingError::Cancelled].
+    ///
+    /// While a token or a [timeout](Self::timeout) is set, cargo runs in its own process group on unix so that
+    /// rustc and build scripts are killed along with it. It then doesn't receive the signals of the terminal,
+    /// E.g. Ctrl-C, and keeps running if the current process is killed without cancelling it first.
+    pub fn cancellation_token(&mut self, token: Option<CancellationToken>) -> &mut Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Runs every cargo invocation in [Hermetic] mode, with `--frozen` and `--offline`.
+    pub fn hermetic(&mut self, hermetic: Option<Hermetic>) -> &mut Self {
+        self.hermetic = hermetic;
+        self
+    }
+
+    /// Sets an observer notified of the progress of [build](Self::build), [check](Self::check)
+    /// and [test](Self::test) while they run.
+    #[cfg(feature = "json")]
+    pub fn observer(&mut self, observer: Option<std::sync::Arc<dyn BuildObserver>>) -> &mut Self {
+        self.observer = observer.map(build::SharedObserver);
+        self
+    }
+
+    /// Sets a cache reused by [metadata](Self::metadata) until the manifests, lockfile or configuration change.
+    #[cfg(feature = "json")]
+    pub fn metadata_cache(&mut self, cache: Option<MetadataCache>) -> &mut Self {
+        self.metadata_cache = cache;
+        self
+    }
+
     pub fn command<I, S>(&self, args: I) -> Command
     where
         I: IntoIterator<Item = S>,
         S: AsRef<OsStr>,
     {
-        let mut command = Command::new(&self.path);
+        self.program_command(&self.path, self.toolchain.as_deref(), args)
+    }
+
+    /// Like [command](Self::command) but runs the [driver](Self::driver), for the commands that build.
+    #[cfg(feature = "json")]
+    pub(crate) fn driver_command<I, S>(&self, args: I) -> Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let path = self.driver.program().unwrap_or(&self.path);
+
+        self.program_command(path, self.toolchain.as_deref(), args)
+    }
+
+    /// Like [command](Self::command) but with the given toolchain instead of the configured one.
+    #[cfg(feature = "json")]
+    fn toolchain_command<I, S>(&self, toolchain: Option<&str>, args: I) -> Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.program_command(&self.path, toolchain, args)
+    }
 
-        if self.frozen {
+    fn program_command<I, S>(&self, path: &Path, toolchain: Option<&str>, args: I) -> Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut command = Command::new(path);
+
+        if let Some(hermetic) = &self.hermetic {
+            hermetic.apply_env(&mut command);
+        }
+
+        command.envs(self.envs.iter().map(|(key, value)| (key, value)));
+
+        // The toolchain has to come before any other argument.
+        if let Some(toolchain) = toolchain {
+            command.arg(format!("+{toolchain}"));
+        }
+
+        let hermetic = self.hermetic.is_some();
+
+        if self.frozen || hermetic {
             command.arg("--frozen");
         }
 
@@ -86,31 +321,95 @@ impl Cargo {
             command.arg("--locked");
         }
 
-        if self.offline {
+        if self.offline || hermetic {
             command.arg("--offline");
         }
 
+        if let Some(hermetic) = &self.hermetic {
+            hermetic.apply_config(&mut command);
+        }
+
         command.args(args);
 
         command
     }
 
     fn exec(&self, command: &mut Command) -> Result<Vec<u8>> {
-        let Output {
-            status,
-            stdout,
-            stderr,
-        } = command.output()?;
-
-        if status.success() {
-            Ok(stdout)
+        Ok(self.output(command)?.stdout)
+    }
+
+    /// Like [exec](Self::exec) but also returns stderr, for commands that report there.
+    fn output(&self, command: &mut Command) -> Result<Output> {
+        let output = self.wait(command)?;
+
+        if output.status.success() {
+            Ok(output)
         } else {
-            Err(ParsingError::Exec { stderr })
+            Err(ParsingError::Exec {
+                stderr: output.stderr,
+            })
+        }
+    }
+
+    /// Runs a command and collects its output, honoring the [timeout](Self::timeout)
+    /// and [cancellation token](Self::cancellation_token).
+    pub(crate) fn wait(&self, command: &mut Command) -> Result<Output> {
+        self.with_deadline(|timeout| process::output(command, timeout, self.cancellation.as_ref()))
+    }
+
+    /// Like [wait](Self::wait) but also passes every line of output to the callback as soon as it's read.
+    #[cfg(feature = "json")]
+    pub(crate) fn wait_lines<F: FnMut(process::Stream, &[u8])>(
+        &self,
+        command: &mut Command,
+        on_line: F,
+    ) -> Result<Output> {
+        self.with_deadline(|timeout| {
+            process::output_lines(command, timeout, self.cancellation.as_ref(), on_line)
+        })
+    }
+
+    /// Runs an invocation with the [timeout](Self::timeout), shortened to what remains of the
+    /// [with_timeout](Self::with_timeout) call.
+    fn with_deadline<T>(&self, run: impl FnOnce(Option<Duration>) -> Result<T>) -> Result<T> {
+        let Some((deadline, call_timeout)) = self.deadline else {
+            return run(self.timeout);
+        };
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+
+        if remaining.is_zero() {
+            return Err(ParsingError::Timeout(call_timeout));
+        }
+
+        let timeout = self
+            .timeout
+            .map_or(remaining, |timeout| timeout.min(remaining));
+
+        run(Some(timeout)).map_err(|error| match error {
+            // Report the timeout of the call when it's the one that ran out.
+            ParsingError::Timeout(_) if timeout == remaining => ParsingError::Timeout(call_timeout),
+            error => error,
+        })
+    }
+
+    /// Returns the root directory of the workspace the given manifest, or the current directory, belongs to.
+    pub fn workspace_root(&mut self, manifest_path: Option<&Path>) -> Result<PathBuf> {
+        let mut command =
+            self.command(["locate-project", "--workspace", "--message-format", "plain"]);
+
+        if let Some(manifest_path) = manifest_path {
+            command.arg("--manifest-path").arg(manifest_path);
         }
+
+        let stdout = self.exec(&mut command)?;
+        let manifest = PathBuf::from(std::str::from_utf8(&stdout)?.trim_end());
+
+        Ok(manifest.parent().map(Path::to_path_buf).unwrap_or(manifest))
     }
 
     pub fn version(&mut self) -> Result<Version> {
-        let mut command = self.command(&["-Vv"]);
+        let mut command = self.command(["-Vv"]);
         std::str::from_utf8(&self.exec(&mut command)?)?.parse()
     }
 
@@ -118,35 +417,38 @@ impl Cargo {
     #[cfg(feature = "json")]
     #[doc(hidden)]
     pub fn _build(&mut self) -> Result<UnitGraph> {
-        let mut command = self.command(&["build", "-Zunstable-options", "--unit-graph"]);
+        let mut command = self.command(["build", "-Zunstable-options", "--unit-graph"]);
 
         let stdout = self.exec(&mut command)?;
 
         Ok(serde_json::from_slice(&stdout)?)
     }
 
+    /// Runs `cargo metadata`, or returns the result in the [cache](Self::metadata_cache) if it's still fresh.
     #[cfg(feature = "json")]
     pub fn metadata(&mut self, config: MetadataConfig) -> Result<Metadata> {
-        let mut command = self.command(&["metadata", "--format-version", "1"]);
+        match self.metadata_cache.clone() {
+            Some(cache) => cache.get(self, &config),
+            None => self.uncached_metadata(&config),
+        }
+    }
+
+    #[cfg(feature = "json")]
+    fn uncached_metadata(&mut self, config: &MetadataConfig) -> Result<Metadata> {
+        let mut command = self.command(["metadata", "--format-version", "1"]);
 
         if let Some(features) = &config.features {
-            match features {
-                Features::AllFeatures => {
-                    command.arg("--all-features");
-                }
-                Features::NoDefaultFeatures => {
-                    command.arg("--no-default-features");
-                }
-                Features::SomeFeatures(features) => {
-                    command.arg("--features").arg(features.join(","));
-                }
-            }
+            features.apply(&mut command);
+        }
+
+        if config.no_deps {
+            command.arg("--no-deps");
         }
 
         if let Some(filter_platform) = &config.filter_platform {
             command
                 .arg("--filter-platform")
-                .arg(&filter_platform.to_string());
+                .arg(filter_platform.to_string());
         }
 
         if let Some(manifest_path) = &config.manifest_path {
@@ -157,6 +459,73 @@ impl Cargo {
 
         Ok(serde_json::from_slice(&stdout)?)
     }
+
+    /// Runs `cargo update` and returns what changed in the lockfile.
+    ///
+    /// With [dry_run](UpdateConfig::dry_run) the lockfile is left untouched and the changes are
+    /// taken from the summary cargo prints instead.
+    #[cfg(feature = "toml")]
+    pub fn update(&mut self, config: UpdateConfig) -> Result<LockfileDiff> {
+        let lockfile_path = Lockfile::path(self.workspace_root(config.manifest_path.as_deref())?);
+
+        let before = match Lockfile::open(&lockfile_path) {
+            Ok(lockfile) => lockfile.packages,
+            Err(ParsingError::Io(error)) if error.kind() == std::io::ErrorKind::NotFound => {
+                Vec::new()
+            }
+            Err(error) => return Err(error),
+        };
+
+        let mut command = self.command(["update"]);
+
+        for package in &config.packages {
+            command.arg("--package").arg(package);
+        }
+
+        if let Some(precise) = &config.precise {
+            command.arg("--precise").arg(precise);
+        }
+
+        if config.recursive {
+            command.arg("--recursive");
+        }
+
+        if config.dry_run {
+            command.arg("--dry-run");
+        }
+
+        if let Some(manifest_path) = &config.manifest_path {
+            command.arg("--manifest-path").arg(manifest_path);
+        }
+
+        let output = self.output(&mut command)?;
+
+        if config.dry_run {
+            Ok(LockfileDiff::from_update_output(
+                &before,
+                std::str::from_utf8(&output.stderr)?,
+            ))
+        } else {
+            Ok(LockfileDiff::new(
+                &before,
+                &Lockfile::open(&lockfile_path)?.packages,
+            ))
+        }
+    }
+
+    /// Runs `cargo generate-lockfile` and returns the new lockfile.
+    #[cfg(feature = "toml")]
+    pub fn generate_lockfile(&mut self, manifest_path: Option<&Path>) -> Result<Lockfile> {
+        let mut command = self.command(["generate-lockfile"]);
+
+        if let Some(manifest_path) = manifest_path {
+            command.arg("--manifest-path").arg(manifest_path);
+        }
+
+        self.exec(&mut command)?;
+
+        Lockfile::for_workspace(self.workspace_root(manifest_path)?)
+    }
 }
 
 impl Default for Cargo {
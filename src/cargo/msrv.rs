@@ -0,0 +1,352 @@
+use std::{path::PathBuf, process::Command};
+
+use which::which;
+
+use super::{
+    BuildConfig, Cargo, Features, MetadataConfig, ParsingError, Result, RustVersion, Version,
+};
+
+/// A toolchain installed with rustup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Toolchain {
+    /// The name of the toolchain, E.g. "1.70-x86_64-unknown-linux-gnu" or "nightly-x86_64-unknown-linux-gnu".
+    pub name: String,
+    /// The version of cargo in the toolchain.
+    pub version: Version,
+}
+
+impl Toolchain {
+    /// Whether this is a stable release, not a beta or nightly one.
+    pub fn is_stable(&self) -> bool {
+        self.version.release.pre.is_empty()
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct MsrvConfig {
+    /// The workspace members to check, every member if empty.
+    pub packages: Vec<String>,
+    pub features: Option<Features>,
+    pub manifest_path: Option<PathBuf>,
+    /// Also use beta and nightly toolchains, only stable ones are used otherwise.
+    ///
+    /// Nightly toolchains have the features of newer releases, so they can make bisecting pick an older
+    /// toolchain than the release the package needs.
+    pub unstable_toolchains: bool,
+}
+
+impl MsrvConfig {
+    /// The installed toolchains to check with, from the oldest to the newest.
+    fn toolchains(&self, toolchains: Vec<Toolchain>) -> Vec<Toolchain> {
+        toolchains
+            .into_iter()
+            .filter(|toolchain| self.unstable_toolchains || toolchain.is_stable())
+            .collect()
+    }
+}
+
+/// The result of checking a package against its declared `rust-version`.
+#[derive(Debug, Clone)]
+pub struct MsrvCheck {
+    /// The name of the package.
+    pub package: String,
+    /// The declared `rust-version`, if any.
+    pub rust_version: Option<RustVersion>,
+    pub status: MsrvStatus,
+}
+
+#[derive(Debug, Clone)]
+pub enum MsrvStatus {
+    /// The package builds with the toolchain.
+    Passed { toolchain: Toolchain },
+    /// The package doesn't build with the toolchain.
+    Failed {
+        toolchain: Toolchain,
+        /// The errors reported by the compiler.
+        diagnostics: Vec<String>,
+        /// The stderr output of cargo, which explains failures that aren't compiler errors,
+        /// E.g. a lockfile version the toolchain can't read.
+        stderr: Vec<u8>,
+    },
+    /// The package doesn't declare a `rust-version`.
+    Undeclared,
+    /// No installed toolchain is the declared release.
+    MissingToolchain,
+}
+
+impl MsrvCheck {
+    /// Whether the package builds with its declared `rust-version`.
+    pub fn passed(&self) -> bool {
+        matches!(self.status, MsrvStatus::Passed { .. })
+    }
+}
+
+impl Cargo {
+    /// Lists the toolchains installed with rustup, from the oldest to the newest.
+    ///
+    /// Toolchains that fail to report their version, E.g. because they're only partially installed,
+    /// are left out.
+    pub fn toolchains(&mut self) -> Result<Vec<Toolchain>> {
+        let rustup = which("rustup").unwrap_or_else(|_| PathBuf::from("rustup"));
+        let mut command = Command::new(rustup);
+        command.args(["toolchain", "list"]);
+
+        let stdout = self.exec(&mut command)?;
+
+        self.toolchain_versions(toolchain_names(std::str::from_utf8(&stdout)?))
+    }
+
+    /// Asks each toolchain for the version of its cargo, skipping the ones that can't tell.
+    fn toolchain_versions(&self, names: Vec<&str>) -> Result<Vec<Toolchain>> {
+        let mut toolchains = Vec::new();
+
+        for name in names {
+            let mut command = self.toolchain_command(Some(name), ["--version"]);
+
+            let version = self
+                .exec(&mut command)
+                .and_then(|stdout| std::str::from_utf8(&stdout)?.parse());
+
+            let version = match version {
+                Ok(version) => version,
+                Err(error @ (ParsingError::Cancelled | ParsingError::Timeout(_))) => {
+                    return Err(error)
+                }
+                Err(_) => continue,
+            };
+
+            toolchains.push(Toolchain {
+                name: name.to_string(),
+                version,
+            });
+        }
+
+        toolchains.sort_by(|a, b| a.version.release.cmp(&b.version.release));
+
+        Ok(toolchains)
+    }
+
+    /// Checks every selected workspace member with the installed toolchain of its declared `rust-version`.
+    ///
+    /// Only stable toolchains are used, unless [unstable_toolchains](MsrvConfig::unstable_toolchains) is set,
+    /// and then they are still preferred over nightly ones of the same release.
+    pub fn check_msrv(&mut self, config: MsrvConfig) -> Result<Vec<MsrvCheck>> {
+        let metadata = self.metadata(MetadataConfig {
+            manifest_path: config.manifest_path.clone(),
+            no_deps: true,
+            ..Default::default()
+        })?;
+
+        let toolchains = config.toolchains(self.toolchains()?);
+        let mut checks = Vec::new();
+
+        for package in metadata
+            .workspace_packages()
+            .filter(|package| config.packages.is_empty() || config.packages.contains(&package.name))
+        {
+            let rust_version = package.rust_version;
+
+            let status = match rust_version {
+                None => MsrvStatus::Undeclared,
+                Some(rust_version) => {
+                    let toolchain = toolchains
+                        .iter()
+                        .filter(|toolchain| rust_version.is_release(&toolchain.version.release))
+                        .min_by_key(|toolchain| toolchain.version.is_nightly());
+
+                    match toolchain {
+                        Some(toolchain) => self.check_with(toolchain, &package.name, &config)?,
+                        None => MsrvStatus::MissingToolchain,
+                    }
+                }
+            };
+
+            checks.push(MsrvCheck {
+                package: package.name.clone(),
+                rust_version,
+                status,
+            });
+        }
+
+        Ok(checks)
+    }
+
+    /// Finds the oldest installed toolchain the package builds with, by bisecting the installed toolchains.
+    ///
+    /// The declared `rust-version` is ignored, and so are the [packages](MsrvConfig::packages) of the config.
+    /// Only stable toolchains are bisected, unless [unstable_toolchains](MsrvConfig::unstable_toolchains) is set.
+    /// This assumes that once a toolchain works every newer one does too. Returns [None] if even the newest fails.
+    pub fn bisect_msrv(&mut self, package: &str, config: MsrvConfig) -> Result<Option<Toolchain>> {
+        let toolchains = config.toolchains(self.toolchains()?);
+
+        let passes = |cargo: &mut Cargo, toolchain: &Toolchain| -> Result<bool> {
+            Ok(matches!(
+                cargo.check_with(toolchain, package, &config)?,
+                MsrvStatus::Passed { .. }
+            ))
+        };
+
+        let Some(newest) = toolchains.last() else {
+            return Ok(None);
+        };
+
+        if !passes(self, newest)? {
+            return Ok(None);
+        }
+
+        // The toolchain at `high` always passes, the ones before `low` always fail.
+        let (mut low, mut high) = (0, toolchains.len() - 1);
+
+        while low < high {
+            let middle = low + (high - low) / 2;
+
+            if passes(self, &toolchains[middle])? {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+
+        Ok(Some(toolchains[high].clone()))
+    }
+
+    /// Runs `cargo check` on a package with the given toolchain.
+    fn check_with(
+        &self,
+        toolchain: &Toolchain,
+        package: &str,
+        config: &MsrvConfig,
+    ) -> Result<MsrvStatus> {
+        let mut cargo = self.clone();
+        cargo.toolchain(Some(&toolchain.name));
+
+        let result = cargo.check(BuildConfig {
+            packages: vec![package.to_string()],
+            features: config.features.clone(),
+            manifest_path: config.manifest_path.clone(),
+            // Older toolchains refuse to build the package otherwise, which is what bisecting tests.
+            ignore_rust_version: true,
+            ..Default::default()
+        });
+
+        let toolchain = toolchain.clone();
+
+        match result {
+            Ok(_) => Ok(MsrvStatus::Passed { toolchain }),
+            Err(ParsingError::Build {
+                diagnostics,
+                stderr,
+            }) => Ok(MsrvStatus::Failed {
+                toolchain,
+                diagnostics,
+                stderr,
+            }),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// The names in the output of `rustup toolchain list`.
+///
+/// Each line is a name followed by markers, E.g. "stable-x86_64-unknown-linux-gnu (active, default)",
+/// and rustup prints "no installed toolchains" when there are none.
+fn toolchain_names(output: &str) -> Vec<&str> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| *line != "no installed toolchains")
+        .filter_map(|line| line.split_whitespace().next())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toolchain(name: &str, release: &str) -> Toolchain {
+        Toolchain {
+            name: name.to_string(),
+            version: format!("cargo {release}").parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(
+            toolchain_names(
+                "stable-x86_64-unknown-linux-gnu (active, default)\nnightly-x86_64-unknown-linux-gnu\n1.70-x86_64-unknown-linux-gnu\n"
+            ),
+            [
+                "stable-x86_64-unknown-linux-gnu",
+                "nightly-x86_64-unknown-linux-gnu",
+                "1.70-x86_64-unknown-linux-gnu"
+            ]
+        );
+        assert!(toolchain_names("no installed toolchains\n").is_empty());
+        assert!(toolchain_names("").is_empty());
+    }
+
+    #[test]
+    fn stable_only() {
+        let toolchains = vec![
+            toolchain("1.70", "1.70.0"),
+            toolchain("beta", "1.96.0-beta.2"),
+            toolchain("nightly-2025-01-01", "1.85.0-nightly"),
+            toolchain("stable", "1.95.0"),
+        ];
+
+        let names = |config: MsrvConfig| -> Vec<String> {
+            config
+                .toolchains(toolchains.clone())
+                .into_iter()
+                .map(|toolchain| toolchain.name)
+                .collect()
+        };
+
+        assert_eq!(names(MsrvConfig::default()), ["1.70", "stable"]);
+        assert_eq!(
+            names(MsrvConfig {
+                unstable_toolchains: true,
+                ..Default::default()
+            })
+            .len(),
+            4
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn broken_toolchains() {
+        use std::{fs, os::unix::fs::PermissionsExt};
+
+        let dir = crate::cargo::home::temp_dir("msrv");
+        fs::create_dir_all(&dir).unwrap();
+
+        // A cargo that only works with some toolchains.
+        let stub = dir.join("cargo");
+        fs::write(
+            &stub,
+            "#!/bin/sh\ncase \"$1\" in\n\
+             +stable) echo 'cargo 1.95.0 (0123456789 2026-01-01)' ;;\n\
+             +1.70) echo 'cargo 1.70.0 (0123456789 2023-06-01)' ;;\n\
+             +garbled) echo 'not a version' ;;\n\
+             *) echo 'error: toolchain is not installed' >&2; exit 1 ;;\n\
+             esac\n",
+        )
+        .unwrap();
+        fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut cargo = Cargo::new();
+        cargo.path(&stub);
+        let toolchains = cargo.toolchain_versions(vec!["stable", "broken", "garbled", "1.70"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<String> = toolchains
+            .unwrap()
+            .into_iter()
+            .map(|toolchain| toolchain.name)
+            .collect();
+        assert_eq!(names, ["1.70", "stable"]);
+    }
+}
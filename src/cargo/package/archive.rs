@@ -0,0 +1,183 @@
+use flate2::read::GzDecoder;
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+use tar::Archive;
+
+use crate::cargo::{ParsingError, Result};
+
+/// A `.crate` file, the gzipped tarball produced by `cargo package`.
+#[derive(Debug, Clone)]
+pub struct CrateArchive {
+    path: PathBuf,
+}
+
+impl CrateArchive {
+    /// Opens the `.crate` file at the given path.
+    pub fn open<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let path = path.into();
+
+        // Fail early instead of on the first read.
+        File::open(&path)?;
+
+        Ok(CrateArchive { path })
+    }
+
+    /// The path of the `.crate` file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn archive(&self) -> Result<Archive<GzDecoder<File>>> {
+        Ok(Archive::new(GzDecoder::new(File::open(&self.path)?)))
+    }
+
+    /// Returns the path of every file in the archive, relative to the root of the package.
+    ///
+    /// Every file in a `.crate` is placed inside a "name-version" directory, which is stripped.
+    pub fn files(&self) -> Result<Vec<PathBuf>> {
+        let mut archive = self.archive()?;
+        let mut files = Vec::new();
+
+        for entry in archive.entries()? {
+            let entry = entry?;
+
+            if entry.header().entry_type().is_file() {
+                files.push(strip_root(&entry.path()?));
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Reads the file at the given path, relative to the root of the package.
+    pub fn read_file<P: AsRef<Path>>(&self, path: P) -> Result<Option<Vec<u8>>> {
+        let mut archive = self.archive()?;
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+
+            if strip_root(&entry.path()?) == path.as_ref() {
+                let mut contents = Vec::new();
+                entry.read_to_end(&mut contents)?;
+                return Ok(Some(contents));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Reads the normalized `Cargo.toml` cargo generated for the package.
+    pub fn manifest(&self) -> Result<String> {
+        let manifest = self
+            .read_file("Cargo.toml")?
+            .ok_or_else(|| ParsingError::Package("the archive has no Cargo.toml".to_string()))?;
+
+        Ok(String::from_utf8(manifest).map_err(|error| error.utf8_error())?)
+    }
+
+    /// Parses the normalized `Cargo.toml` cargo generated for the package.
+    #[cfg(feature = "toml")]
+    pub fn manifest_toml(&self) -> Result<toml::Table> {
+        Ok(toml::from_str(&self.manifest()?)?)
+    }
+
+    /// Extracts the archive into the given directory, creating it if needed,
+    /// and returns the root of the extracted package.
+    pub fn extract<P: AsRef<Path>>(&self, dest: P) -> Result<PathBuf> {
+        let dest = dest.as_ref();
+        fs::create_dir_all(dest)?;
+        let mut archive = self.archive()?;
+        let mut root = None;
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+
+            if root.is_none() {
+                root = entry
+                    .path()?
+                    .components()
+                    .next()
+                    .map(|root| dest.join(root));
+            }
+
+            entry.unpack_in(dest)?;
+        }
+
+        root.ok_or_else(|| ParsingError::Package("the archive is empty".to_string()))
+    }
+}
+
+fn strip_root(path: &Path) -> PathBuf {
+    path.components().skip(1).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cargo::home::temp_dir;
+    use flate2::{write::GzEncoder, Compression};
+    use tar::{Builder, EntryType, Header};
+
+    /// Writes a `.crate` file with a directory entry and two files.
+    fn write_crate(path: &Path) {
+        let mut builder = Builder::new(GzEncoder::new(
+            File::create(path).unwrap(),
+            Compression::default(),
+        ));
+
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Directory);
+        header.set_size(0);
+        header.set_mode(0o755);
+        builder
+            .append_data(&mut header, "demo-0.1.0/src", &[][..])
+            .unwrap();
+
+        for (path, contents) in [
+            ("demo-0.1.0/Cargo.toml", "[package]\nname = \"demo\"\n"),
+            ("demo-0.1.0/src/lib.rs", "pub fn demo() {}\n"),
+        ] {
+            let mut header = Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            builder
+                .append_data(&mut header, path, contents.as_bytes())
+                .unwrap();
+        }
+
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn listing() {
+        let dir = temp_dir("crate-archive");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("demo-0.1.0.crate");
+        write_crate(&path);
+
+        let archive = CrateArchive::open(&path).unwrap();
+
+        assert_eq!(
+            archive.files().unwrap(),
+            [PathBuf::from("Cargo.toml"), PathBuf::from("src/lib.rs")]
+        );
+        assert_eq!(
+            archive.read_file("src/lib.rs").unwrap().as_deref(),
+            Some(&b"pub fn demo() {}\n"[..])
+        );
+        assert_eq!(archive.read_file("README.md").unwrap(), None);
+        assert_eq!(archive.manifest().unwrap(), "[package]\nname = \"demo\"\n");
+
+        let root = archive.extract(dir.join("extracted")).unwrap();
+        let lib = fs::read_to_string(root.join("src").join("lib.rs"));
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(root, dir.join("extracted").join("demo-0.1.0"));
+        assert_eq!(lib.unwrap(), "pub fn demo() {}\n");
+        assert!(CrateArchive::open(dir.join("missing.crate")).is_err());
+    }
+}
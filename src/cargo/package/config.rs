@@ -0,0 +1,17 @@
+use std::path::PathBuf;
+
+use crate::cargo::Features;
+
+#[derive(Debug, Default, Clone)]
+pub struct PackageConfig {
+    /// The package to package, passed as `-p`. Defaults to the current package.
+    pub package: Option<String>,
+    /// Allow packaging with uncommitted changes in the working directory.
+    pub allow_dirty: bool,
+    /// Don't build the packaged crate to verify it.
+    pub no_verify: bool,
+    pub features: Option<Features>,
+    /// The registry to publish to, only used by [publish_dry_run](crate::cargo::Cargo::publish_dry_run).
+    pub registry: Option<String>,
+    pub manifest_path: Option<PathBuf>,
+}
@@ -0,0 +1,255 @@
+use std::{
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+use super::{build::select_package, Cargo, MetadataConfig, ParsingError, Result};
+
+#[cfg(feature = "archive")]
+mod archive;
+mod config;
+#[cfg(feature = "archive")]
+pub use archive::CrateArchive;
+pub use config::PackageConfig;
+
+/// The result of packaging a crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageOutput {
+    /// The files included in the package, relative to its root, as listed by `cargo package --list`.
+    pub files: Vec<PathBuf>,
+    /// The path of the produced `.crate` file.
+    pub crate_file: PathBuf,
+    /// The metadata cargo warned about being missing from the manifest, E.g. "description" or
+    /// "license or license-file".
+    pub missing_metadata: Vec<String>,
+}
+
+impl Cargo {
+    /// Runs `cargo package`, returning the packaged files and the `.crate` file.
+    ///
+    /// Fails with [ParsingError::DirtyWorktree] if there are uncommitted changes and
+    /// [allow_dirty](PackageConfig::allow_dirty) is not set.
+    pub fn package(&mut self, config: PackageConfig) -> Result<PackageOutput> {
+        self.package_with("package", &config)
+    }
+
+    /// Runs `cargo publish --dry-run`, which packages and verifies the crate without uploading it.
+    ///
+    /// Unlike [package](Self::package) this fails with [ParsingError::MissingMetadata] if the manifest
+    /// lacks the metadata crates.io requires, a description and a license or license file. The missing
+    /// documentation, homepage and repository are only reported in the output.
+    pub fn publish_dry_run(&mut self, config: PackageConfig) -> Result<PackageOutput> {
+        let output = self.package_with("publish", &config)?;
+        let required = required_metadata(&output.missing_metadata);
+
+        if required.is_empty() {
+            Ok(output)
+        } else {
+            Err(ParsingError::MissingMetadata { fields: required })
+        }
+    }
+
+    fn package_with(&mut self, subcommand: &str, config: &PackageConfig) -> Result<PackageOutput> {
+        let metadata = self.metadata(MetadataConfig {
+            manifest_path: config.manifest_path.clone(),
+            no_deps: true,
+            ..Default::default()
+        })?;
+
+        let package = select_package(
+            &metadata,
+            config.package.as_deref(),
+            config.manifest_path.as_deref(),
+        )?;
+
+        let mut list = self.package_command("package", config);
+        list.arg("--list");
+        let files = std::str::from_utf8(&self.package_output(&mut list)?.stdout)?
+            .lines()
+            .map(PathBuf::from)
+            .collect();
+
+        let mut command = self.package_command(subcommand, config);
+        if subcommand == "publish" {
+            command.arg("--dry-run");
+
+            if let Some(registry) = &config.registry {
+                command.arg("--registry").arg(registry);
+            }
+        }
+
+        let output = self.package_output(&mut command)?;
+
+        Ok(PackageOutput {
+            files,
+            crate_file: metadata
+                .target_directory
+                .join("package")
+                .join(format!("{}-{}.crate", package.name, package.version)),
+            missing_metadata: missing_metadata(&String::from_utf8_lossy(&output.stderr)),
+        })
+    }
+
+    fn package_command(&self, subcommand: &str, config: &PackageConfig) -> Command {
+        let mut command = self.command([subcommand]);
+
+        if let Some(package) = &config.package {
+            command.arg("--package").arg(package);
+        }
+
+        if config.allow_dirty {
+            command.arg("--allow-dirty");
+        }
+
+        if config.no_verify {
+            command.arg("--no-verify");
+        }
+
+        if let Some(features) = &config.features {
+            features.apply(&mut command);
+        }
+
+        if let Some(manifest_path) = &config.manifest_path {
+            command.arg("--manifest-path").arg(manifest_path);
+        }
+
+        command
+    }
+
+    /// Like [output](Self::output) but recognizes a dirty working directory.
+    fn package_output(&self, command: &mut Command) -> Result<Output> {
+        self.output(command).map_err(|error| match error {
+            ParsingError::Exec { stderr } => match dirty_files(&String::from_utf8_lossy(&stderr)) {
+                Some(files) => ParsingError::DirtyWorktree { files },
+                None => ParsingError::Exec { stderr },
+            },
+            error => error,
+        })
+    }
+}
+
+/// Parses the files listed in cargo's "files in the working directory contain changes" error.
+fn dirty_files(stderr: &str) -> Option<Vec<PathBuf>> {
+    let mut lines = stderr
+        .lines()
+        .skip_while(|line| !line.contains("in the working directory contain changes"))
+        .skip(1)
+        .skip_while(|line| line.trim().is_empty());
+
+    let first = lines.next()?;
+
+    Some(
+        std::iter::once(first)
+            .chain(lines.take_while(|line| !line.trim().is_empty()))
+            .map(|line| PathBuf::from(line.trim()))
+            .collect(),
+    )
+}
+
+/// The fields cargo checks together, a group is only reported when every field of it is missing.
+const METADATA_GROUPS: [&[&str]; 3] = [
+    &["description"],
+    &["license", "license-file"],
+    &["documentation", "homepage", "repository"],
+];
+
+/// Parses cargo's "manifest has no description, license, ..." warning into the missing groups of fields,
+/// E.g. "description" and "license or license-file".
+fn missing_metadata(stderr: &str) -> Vec<String> {
+    let Some(fields) = stderr
+        .lines()
+        .find_map(|line| line.strip_prefix("warning: manifest has no "))
+    else {
+        return Vec::new();
+    };
+
+    let fields: Vec<&str> = fields
+        .trim_end_matches('.')
+        .split(", ")
+        .flat_map(|field| field.split(" or "))
+        .collect();
+
+    let mut missing: Vec<String> = METADATA_GROUPS
+        .iter()
+        .filter(|group| group.iter().all(|field| fields.contains(field)))
+        .map(|group| group.join(" or "))
+        .collect();
+
+    // Fields added by newer versions of cargo are kept on their own.
+    missing.extend(
+        fields
+            .iter()
+            .filter(|field| !METADATA_GROUPS.iter().any(|group| group.contains(field)))
+            .map(|field| field.to_string()),
+    );
+
+    missing
+}
+
+/// The missing metadata crates.io refuses to publish without, the other fields are only recommended.
+fn required_metadata(missing: &[String]) -> Vec<String> {
+    missing
+        .iter()
+        .filter(|missing| matches!(missing.as_str(), "description" | "license or license-file"))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata() {
+        let stderr = "warning: manifest has no description, license, license-file, documentation, homepage or repository.\n\
+            See https://doc.rust-lang.org/cargo/reference/manifest.html#package-metadata for more info.\n";
+        let missing = missing_metadata(stderr);
+
+        assert_eq!(
+            missing,
+            [
+                "description",
+                "license or license-file",
+                "documentation or homepage or repository"
+            ]
+        );
+        assert_eq!(
+            required_metadata(&missing),
+            ["description", "license or license-file"]
+        );
+
+        // crates.io accepts a crate without documentation, homepage or repository.
+        let missing =
+            missing_metadata("warning: manifest has no documentation, homepage or repository.\n");
+        assert_eq!(missing, ["documentation or homepage or repository"]);
+        assert!(required_metadata(&missing).is_empty());
+
+        assert_eq!(
+            missing_metadata("warning: manifest has no description or funding.\n"),
+            ["description", "funding"]
+        );
+        assert!(missing_metadata("   Packaging a v0.1.0 (/ws/a)\n").is_empty());
+    }
+
+    #[test]
+    fn dirty() {
+        let stderr = "error: 2 files in the working directory contain changes that were not yet committed into git:\n\
+            \n\
+            src/lib.rs\n\
+            Cargo.toml\n\
+            \n\
+            to proceed despite this and include the uncommitted changes, pass the `--allow-dirty` flag\n";
+
+        assert_eq!(
+            dirty_files(stderr),
+            Some(vec![
+                PathBuf::from("src/lib.rs"),
+                PathBuf::from("Cargo.toml")
+            ])
+        );
+        assert_eq!(
+            dirty_files("error: failed to verify package tarball\n"),
+            None
+        );
+    }
+}
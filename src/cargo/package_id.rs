@@ -0,0 +1,88 @@
+use semver::Version;
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use super::{ParsingError, Result, SourceId};
+
+/// Uniquely identifies a package by its name, version and source.
+///
+/// Parses both the "name version (source)" form used by lockfiles and older versions of
+/// `cargo metadata` and the "source#name@version" form used by newer versions of `cargo metadata`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PackageId {
+    /// The name of the package.
+    pub name: String,
+    /// The version of the package.
+    pub version: Version,
+    /// The source of the package.
+    ///
+    /// This is [None] for path dependencies in lockfiles.
+    pub source: Option<SourceId>,
+}
+
+impl FromStr for PackageId {
+    type Err = ParsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParsingError::PackageId(s.to_string());
+
+        // The "name version (source)" form.
+        let mut parts = s.splitn(3, ' ');
+        if let (Some(name), Some(version)) = (parts.next(), parts.next()) {
+            let source = match parts.next() {
+                Some(source) => Some(
+                    source
+                        .strip_prefix('(')
+                        .and_then(|source| source.strip_suffix(')'))
+                        .ok_or_else(invalid)?
+                        .parse()?,
+                ),
+                None => None,
+            };
+
+            return Ok(PackageId {
+                name: name.to_string(),
+                version: version.parse()?,
+                source,
+            });
+        }
+
+        // The "source#name@version" form, where "name@" may be omitted if it matches the last path segment.
+        let (source, fragment) = s.rsplit_once('#').ok_or_else(invalid)?;
+        let source: SourceId = source.parse()?;
+
+        let (name, version) = match fragment.split_once('@') {
+            Some((name, version)) => (name.to_string(), version),
+            None => {
+                let name = source
+                    .url
+                    .trim_end_matches('/')
+                    .rsplit('/')
+                    .next()
+                    .ok_or_else(invalid)?;
+
+                (name.to_string(), fragment)
+            }
+        };
+
+        Ok(PackageId {
+            name,
+            version: version.parse()?,
+            source: Some(source),
+        })
+    }
+}
+
+impl Display for PackageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.name, self.version)?;
+
+        if let Some(source) = &self.source {
+            write!(f, " ({source})")?;
+        }
+
+        Ok(())
+    }
+}
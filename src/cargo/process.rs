@@ -0,0 +1,364 @@
+use std::{
+    io::{self, BufRead, BufReader, Read},
+    process::{Child, Command, Output, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{ParsingError, Result};
+
+/// How often a running command is checked for a timeout or cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Cancels running cargo commands, E.g. from another thread or a signal handler.
+///
+/// Clones share the same state, so cancelling any of them cancels every command using one.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels every command using this token, killing their processes.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Puts the command in a new process group, so it can be killed along with every process it starts.
+pub(crate) fn set_process_group(command: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    #[cfg(not(unix))]
+    let _ = command;
+}
+
+/// Kills a child, along with every process in its group if it was started with [set_process_group].
+///
+/// The child must not have been waited on yet, otherwise its id could belong to another process.
+pub(crate) fn kill_group(child: &mut Child) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        // The group id is the id of the child, and a negative pid targets the whole group.
+        let pid = child.id() as libc::pid_t;
+
+        if unsafe { libc::kill(-pid, libc::SIGKILL) } == 0 {
+            return Ok(());
+        }
+
+        let error = io::Error::last_os_error();
+
+        // The group is already gone.
+        if error.raw_os_error() != Some(libc::ESRCH) {
+            return Err(error);
+        }
+    }
+
+    match child.kill() {
+        Err(error) if error.kind() != io::ErrorKind::InvalidInput => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/// Kills a child like [kill_group], and waits for it to exit.
+fn kill_and_reap(child: &mut Child) -> io::Result<()> {
+    kill_group(child)?;
+    child.wait()?;
+    Ok(())
+}
+
+/// The output stream a line was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Spawns the command and collects its output, killing it when it times out or is cancelled.
+///
+/// Like [Command::output], stdin is null and both stdout and stderr are captured.
+///
+/// With a timeout or a cancellation token, the command is put in its own process group so it can be killed
+/// along with the processes it starts. Otherwise it stays in the group of the current process, and receives
+/// the signals of the terminal such as Ctrl-C.
+pub(crate) fn output(
+    command: &mut Command,
+    timeout: Option<Duration>,
+    cancellation: Option<&CancellationToken>,
+) -> Result<Output> {
+    output_lines(command, timeout, cancellation, |_, _| {})
+}
+
+/// Like [output] but also passes every line to the callback as soon as it's read, with its line ending.
+///
+/// The callback runs on the calling thread.
+pub(crate) fn output_lines<F: FnMut(Stream, &[u8])>(
+    command: &mut Command,
+    timeout: Option<Duration>,
+    cancellation: Option<&CancellationToken>,
+    on_line: F,
+) -> Result<Output> {
+    if cancellation.is_some_and(CancellationToken::is_cancelled) {
+        return Err(ParsingError::Cancelled);
+    }
+
+    if timeout.is_some() || cancellation.is_some() {
+        set_process_group(command);
+    }
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    wait_lines(&mut child, timeout, cancellation, on_line)
+}
+
+/// Waits for a child to exit and collects its piped output, closing its stdin first like [Child::wait_with_output].
+///
+/// The child is killed when it times out, is cancelled or its output can't be read, along with its group
+/// if it was started with [set_process_group].
+#[cfg(feature = "json")]
+pub(crate) fn wait_output(
+    child: &mut Child,
+    timeout: Option<Duration>,
+    cancellation: Option<&CancellationToken>,
+) -> Result<Output> {
+    wait_lines(child, timeout, cancellation, |_, _| {})
+}
+
+fn wait_lines<F: FnMut(Stream, &[u8])>(
+    child: &mut Child,
+    timeout: Option<Duration>,
+    cancellation: Option<&CancellationToken>,
+    mut on_line: F,
+) -> Result<Output> {
+    drop(child.stdin.take());
+
+    let (sender, receiver) = mpsc::channel();
+
+    if let Some(stdout) = child.stdout.take() {
+        read_lines(Stream::Stdout, stdout, sender.clone());
+    }
+
+    if let Some(stderr) = child.stderr.take() {
+        read_lines(Stream::Stderr, stderr, sender);
+    }
+
+    let killable = timeout.is_some() || cancellation.is_some();
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let mut reading = true;
+
+    let status = loop {
+        if killable {
+            let error = if cancellation.is_some_and(CancellationToken::is_cancelled) {
+                Some(ParsingError::Cancelled)
+            } else if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                Some(ParsingError::Timeout(timeout.unwrap_or_default()))
+            } else {
+                None
+            };
+
+            if let Some(error) = error {
+                kill_and_reap(child)?;
+
+                // The readers are left behind, a process outside of the group could still hold the pipes open.
+                return Err(error);
+            }
+        }
+
+        if reading {
+            match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(Ok((stream, line))) => {
+                    on_line(stream, &line);
+
+                    match stream {
+                        Stream::Stdout => stdout.extend(line),
+                        Stream::Stderr => stderr.extend(line),
+                    }
+                }
+                Ok(Err(error)) => {
+                    kill_and_reap(child)?;
+                    return Err(error.into());
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => reading = false,
+            }
+        } else if !killable {
+            break child.wait()?;
+        } else if let Some(status) = child.try_wait()? {
+            break status;
+        } else {
+            thread::sleep(POLL_INTERVAL);
+        }
+    };
+
+    Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+type Line = io::Result<(Stream, Vec<u8>)>;
+
+/// Sends every line of the reader until it's closed.
+fn read_lines<R: Read + Send + 'static>(stream: Stream, reader: R, sender: Sender<Line>) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+
+        loop {
+            let mut line = Vec::new();
+
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => {
+                    if sender.send(Ok((stream, line))).is_err() {
+                        break;
+                    }
+                }
+                Err(error) => {
+                    let _ = sender.send(Err(error));
+                    break;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::cargo::Cargo;
+
+    /// Runs a shell script as cargo.
+    fn script(cargo: &Cargo, script: &str) -> Result<Output> {
+        let mut cargo = cargo.clone();
+        cargo.path("sh");
+        cargo.wait(&mut cargo.command(["-c", script]))
+    }
+
+    fn sleep(cargo: &mut Cargo, seconds: &str) -> Result<Output> {
+        cargo.path("sleep");
+        cargo.wait(&mut cargo.command([seconds]))
+    }
+
+    #[test]
+    fn timeouts() {
+        let mut cargo = Cargo::new();
+        let call_timeout = Duration::from_millis(200);
+
+        let started = Instant::now();
+        let error = cargo.with_timeout(call_timeout, |cargo| {
+            sleep(cargo, "0.1")?;
+            sleep(cargo, "5")
+        });
+
+        assert!(matches!(error, Err(ParsingError::Timeout(timeout)) if timeout == call_timeout));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        // The timeout of every invocation is shorter than the remaining time of the call.
+        cargo.timeout(Some(Duration::from_millis(50)));
+        let error = cargo.with_timeout(Duration::from_secs(10), |cargo| sleep(cargo, "5"));
+
+        assert!(
+            matches!(error, Err(ParsingError::Timeout(timeout)) if timeout == Duration::from_millis(50))
+        );
+
+        cargo.timeout(None);
+        assert!(sleep(&mut cargo, "0").is_ok());
+    }
+
+    #[test]
+    fn cancellation() {
+        let token = CancellationToken::new();
+        let mut cargo = Cargo::new();
+        cargo.cancellation_token(Some(token.clone()));
+
+        // Starts a grandchild that would outlive the shell if only the shell was killed.
+        let pid_file = std::env::temp_dir().join(format!("payload-cancel-{}", std::process::id()));
+        let forever = format!("sleep 30 & echo $! > '{}'; wait", pid_file.display());
+
+        let canceller = {
+            let pid_file = pid_file.clone();
+            thread::spawn(move || {
+                let pid = (0..500)
+                    .find_map(|_| {
+                        thread::sleep(POLL_INTERVAL);
+                        std::fs::read_to_string(&pid_file)
+                            .ok()
+                            .filter(|pid| pid.ends_with('\n'))
+                    })
+                    .unwrap_or_default();
+                token.cancel();
+                pid.trim().to_string()
+            })
+        };
+
+        let started = Instant::now();
+        let result = script(&cargo, &forever);
+        let pid = canceller.join().unwrap();
+        let _ = std::fs::remove_file(&pid_file);
+
+        assert!(matches!(result, Err(ParsingError::Cancelled)));
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(!pid.is_empty());
+
+        // Orphans are reaped by init, they can stay zombies for a moment.
+        let gone = (0..500).any(|_| {
+            let gone = std::fs::read_to_string(format!("/proc/{pid}/stat")).map_or(true, |stat| {
+                stat.rsplit(") ")
+                    .next()
+                    .is_some_and(|stat| stat.starts_with('Z'))
+            });
+            thread::sleep(POLL_INTERVAL);
+            gone
+        });
+        assert!(gone);
+
+        // A cancelled token fails without starting anything.
+        assert!(matches!(
+            script(&cargo, "true"),
+            Err(ParsingError::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn process_groups() {
+        let group = |cargo: &Cargo| {
+            let output = script(cargo, "cut -d ' ' -f 5 /proc/$$/stat").unwrap();
+            String::from_utf8(output.stdout).unwrap().trim().to_string()
+        };
+        let own_group = unsafe { libc::getpgrp() }.to_string();
+
+        // Without a timeout or a token cargo stays in the group of the terminal.
+        let mut cargo = Cargo::new();
+        assert_eq!(group(&cargo), own_group);
+
+        cargo.timeout(Some(Duration::from_secs(60)));
+        assert_ne!(group(&cargo), own_group);
+
+        cargo.timeout(None);
+        cargo.cancellation_token(Some(CancellationToken::new()));
+        assert_ne!(group(&cargo), own_group);
+    }
+}
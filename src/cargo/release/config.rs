@@ -0,0 +1,44 @@
+use semver::{BuildMetadata, Prerelease, Version};
+use std::path::PathBuf;
+
+/// How the version of a package changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bump {
+    /// E.g. "1.2.3" becomes "2.0.0".
+    Major,
+    /// E.g. "1.2.3" becomes "1.3.0".
+    Minor,
+    /// E.g. "1.2.3" becomes "1.2.4".
+    Patch,
+    /// Set the version, E.g. "2.0.0-rc.1".
+    Version(Version),
+}
+
+impl Bump {
+    /// Returns the bumped version, dropping any pre-release and build metadata.
+    pub fn apply(&self, version: &Version) -> Version {
+        let (major, minor, patch) = match self {
+            Bump::Major => (version.major + 1, 0, 0),
+            Bump::Minor => (version.major, version.minor + 1, 0),
+            Bump::Patch => (version.major, version.minor, version.patch + 1),
+            Bump::Version(version) => return version.clone(),
+        };
+
+        Version {
+            major,
+            minor,
+            patch,
+            pre: Prerelease::EMPTY,
+            build: BuildMetadata::EMPTY,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ReleaseConfig {
+    /// The workspace members to release, by name, with how their version changes.
+    pub packages: Vec<(String, Bump)>,
+    /// Don't write the manifests, only report what would change.
+    pub dry_run: bool,
+    pub manifest_path: Option<PathBuf>,
+}
@@ -0,0 +1,577 @@
+use semver::{Version, VersionReq};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+use toml_edit::{Document, Item, TableLike};
+
+use super::{
+    metadata::{Dependency, Package, PublishingRestrictions},
+    Cargo, Metadata, MetadataConfig, ParsingError, Result,
+};
+
+mod config;
+pub use config::{Bump, ReleaseConfig};
+
+/// The new version of a released package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionBump {
+    /// The name of the package.
+    pub package: String,
+    pub from: Version,
+    pub to: Version,
+    /// Whether the version is inherited from `workspace.package.version`, so it is bumped in the workspace manifest.
+    pub inherited: bool,
+}
+
+/// A `version` requirement on a released package, updated to its new version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequirementUpdate {
+    /// The manifest the requirement is written in.
+    pub manifest_path: PathBuf,
+    /// The name of the dependent package, [None] for the `workspace.dependencies` table.
+    pub dependent: Option<String>,
+    /// The name of the released package.
+    pub dependency: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// A package that isn't released but depends on a released package, directly or through other cascading
+/// packages, so it needs a release too for its users to get the new version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CascadingBump {
+    /// The name of the package.
+    pub package: String,
+    /// The names of the released or cascading packages it depends on.
+    pub dependencies: Vec<String>,
+    /// Whether a new version isn't matched by the previous requirement, which is a breaking change
+    /// if the dependency is part of the public API of the package.
+    pub breaking: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReleasePlan {
+    pub bumps: Vec<VersionBump>,
+    pub requirements: Vec<RequirementUpdate>,
+    /// The publishable released packages, ordered so each one is published after its dependencies.
+    pub publish_order: Vec<String>,
+    pub cascading: Vec<CascadingBump>,
+}
+
+/// The manifests being edited, parsed once and written at the end.
+#[derive(Default)]
+struct Manifests {
+    documents: BTreeMap<PathBuf, Document>,
+    changed: BTreeSet<PathBuf>,
+}
+
+impl Manifests {
+    fn get(&mut self, path: &Path) -> Result<&mut Document> {
+        if !self.documents.contains_key(path) {
+            let document = fs::read_to_string(path)?.parse()?;
+            self.documents.insert(path.to_path_buf(), document);
+        }
+
+        Ok(self.documents.get_mut(path).expect("just inserted"))
+    }
+
+    fn write(&self) -> Result<()> {
+        for path in &self.changed {
+            fs::write(path, self.documents[path].to_string())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Cargo {
+    /// Bumps the version of the selected workspace members and updates the `version` requirements on them
+    /// across the workspace, keeping the formatting of the manifests.
+    ///
+    /// Path dependencies without a `version` requirement are left as they are. Fails if the manifest of a
+    /// released package has no `version` to bump. Nothing is written with [dry_run](ReleaseConfig::dry_run).
+    pub fn release(&mut self, config: ReleaseConfig) -> Result<ReleasePlan> {
+        let metadata = self.metadata(MetadataConfig {
+            manifest_path: config.manifest_path.clone(),
+            no_deps: true,
+            ..Default::default()
+        })?;
+
+        let root_manifest = metadata.workspace_root.join("Cargo.toml");
+        let mut manifests = Manifests::default();
+        let mut plan = ReleasePlan::default();
+
+        let members: Vec<&Package> = metadata.workspace_packages().collect();
+
+        for (name, bump) in &config.packages {
+            let package = members
+                .iter()
+                .find(|package| &package.name == name)
+                .ok_or_else(|| {
+                    ParsingError::Selection(format!("no workspace member named {name}"))
+                })?;
+
+            let from: Version = package.version.parse()?;
+            let to = bump.apply(&from);
+
+            let inherited = is_inherited(manifests.get(&package.manifest_path)?);
+
+            // Every member inheriting the workspace version gets the same new version.
+            let released: Vec<&Package> = if inherited {
+                let mut inheriting = Vec::new();
+
+                for member in &members {
+                    if is_inherited(manifests.get(&member.manifest_path)?) {
+                        inheriting.push(*member);
+                    }
+                }
+
+                inheriting
+            } else {
+                vec![*package]
+            };
+
+            for package in released {
+                match plan.bumps.iter().find(|bump| bump.package == package.name) {
+                    Some(bump) if bump.to != to => {
+                        return Err(ParsingError::Release(format!(
+                            "{} is bumped to both {} and {to}",
+                            package.name, bump.to
+                        )))
+                    }
+                    Some(_) => {}
+                    None => plan.bumps.push(VersionBump {
+                        package: package.name.clone(),
+                        from: package.version.parse()?,
+                        to: to.clone(),
+                        inherited,
+                    }),
+                }
+            }
+
+            let (path, section) = if inherited {
+                (&root_manifest, ["workspace", "package"].as_slice())
+            } else {
+                (&package.manifest_path, ["package"].as_slice())
+            };
+
+            let document = manifests.get(path)?;
+
+            let version = table_like(document.as_item_mut(), section)
+                .and_then(|table| table.get_mut("version"))
+                .ok_or_else(|| {
+                    ParsingError::Release(format!(
+                        "{} has no `{}.version` to bump",
+                        path.display(),
+                        section.join(".")
+                    ))
+                })?;
+
+            set_string(version, &to.to_string());
+            manifests.changed.insert(path.clone());
+        }
+
+        let versions: BTreeMap<&str, &Version> = plan
+            .bumps
+            .iter()
+            .map(|bump| (bump.package.as_str(), &bump.to))
+            .collect();
+
+        // Requirements written in the workspace manifest, used with `workspace = true`.
+        let document = manifests.get(&root_manifest)?;
+
+        if let Some(dependencies) =
+            table_like(document.as_item_mut(), &["workspace", "dependencies"])
+        {
+            for (key, entry) in dependencies.iter_mut() {
+                if let Some(update) = update_requirement(entry, &key, &versions) {
+                    plan.requirements.push(RequirementUpdate {
+                        manifest_path: root_manifest.clone(),
+                        dependent: None,
+                        ..update
+                    });
+                }
+            }
+        }
+
+        if plan
+            .requirements
+            .iter()
+            .any(|update| update.dependent.is_none())
+        {
+            manifests.changed.insert(root_manifest.clone());
+        }
+
+        for member in &members {
+            for dependency in member
+                .dependencies
+                .iter()
+                .filter(|dependency| dependency.path.is_some())
+            {
+                let key = dependency.rename.as_ref().unwrap_or(&dependency.name);
+                let document = manifests.get(&member.manifest_path)?;
+
+                let Some(entry) = table_like(document.as_item_mut(), &section(dependency))
+                    .and_then(|table| table.get_mut(key))
+                else {
+                    continue;
+                };
+
+                if let Some(update) = update_requirement(entry, key, &versions) {
+                    plan.requirements.push(RequirementUpdate {
+                        manifest_path: member.manifest_path.clone(),
+                        dependent: Some(member.name.clone()),
+                        ..update
+                    });
+                    manifests.changed.insert(member.manifest_path.clone());
+                }
+            }
+        }
+
+        plan.cascading = cascading_bumps(&metadata, &versions);
+        plan.publish_order = publish_order(&members, &versions)?;
+
+        if !config.dry_run {
+            manifests.write()?;
+        }
+
+        Ok(plan)
+    }
+}
+
+/// Whether the manifest uses `version.workspace = true`.
+fn is_inherited(document: &mut Document) -> bool {
+    table_like(document.as_item_mut(), &["package", "version"])
+        .and_then(|version| version.get("workspace"))
+        .and_then(Item::as_bool)
+        .unwrap_or(false)
+}
+
+/// Follows the keys through nested tables, inline or not.
+fn table_like<'a, S: AsRef<str>>(item: &'a mut Item, keys: &[S]) -> Option<&'a mut dyn TableLike> {
+    let mut item = item;
+
+    for key in keys {
+        item = item.as_table_like_mut()?.get_mut(key.as_ref())?;
+    }
+
+    item.as_table_like_mut()
+}
+
+/// The keys of the table a dependency is declared in, E.g. `["target", "cfg(unix)", "dev-dependencies"]`.
+fn section(dependency: &Dependency) -> Vec<&str> {
+    let kind = match dependency.kind.as_deref() {
+        Some("dev") => "dev-dependencies",
+        Some("build") => "build-dependencies",
+        _ => "dependencies",
+    };
+
+    match &dependency.target {
+        Some(target) => vec!["target", target, kind],
+        None => vec![kind],
+    }
+}
+
+/// Updates the `version` of a dependency entry if it refers to a released package.
+fn update_requirement(
+    entry: &mut Item,
+    key: &str,
+    versions: &BTreeMap<&str, &Version>,
+) -> Option<RequirementUpdate> {
+    let table = entry.as_table_like_mut()?;
+
+    // Only path dependencies can refer to workspace members.
+    table.get("path")?;
+
+    let name = table
+        .get("package")
+        .and_then(Item::as_str)
+        .unwrap_or(key)
+        .to_string();
+
+    let to = versions.get(name.as_str())?;
+    let version = table.get_mut("version")?;
+    let from = version.as_str()?.to_string();
+    let requirement = bumped_requirement(&from, to);
+
+    set_string(version, &requirement);
+
+    Some(RequirementUpdate {
+        manifest_path: PathBuf::new(),
+        dependent: None,
+        dependency: name,
+        from,
+        to: requirement,
+    })
+}
+
+/// The requirement on the new version, keeping the operator of a single comparator, E.g. "=1.2.3" becomes "=1.3.0".
+fn bumped_requirement(requirement: &str, to: &Version) -> String {
+    let requirement = requirement.trim();
+
+    if requirement.contains(',') || requirement.contains('*') {
+        return to.to_string();
+    }
+
+    let operator: String = requirement
+        .chars()
+        .take_while(|char| !char.is_ascii_digit())
+        .collect();
+
+    format!("{}{to}", operator.trim())
+}
+
+/// Replaces a string value, keeping the whitespace and comments around it.
+fn set_string(item: &mut Item, string: &str) {
+    let decor = item.as_value().map(|value| value.decor().clone());
+
+    *item = toml_edit::value(string);
+
+    if let (Some(decor), Some(value)) = (decor, item.as_value_mut()) {
+        *value.decor_mut() = decor;
+    }
+}
+
+/// The members that aren't released but need to be, because they depend on a released package or on
+/// another member that needs to be released.
+///
+/// Dev dependencies are ignored since they don't end up in the published package.
+fn cascading_bumps(metadata: &Metadata, versions: &BTreeMap<&str, &Version>) -> Vec<CascadingBump> {
+    let mut cascading: Vec<CascadingBump> = Vec::new();
+
+    // Each round finds the members depending on the ones found in the previous round, until none is left.
+    loop {
+        let mut found = Vec::new();
+
+        for package in metadata
+            .workspace_packages()
+            .filter(|package| !versions.contains_key(package.name.as_str()))
+            .filter(|package| package.publish.restrictions() != PublishingRestrictions::Forbidden)
+            .filter(|package| !cascading.iter().any(|bump| bump.package == package.name))
+        {
+            let dependencies: Vec<&Dependency> = package
+                .dependencies
+                .iter()
+                .filter(|dependency| {
+                    dependency.path.is_some()
+                        && dependency.kind.as_deref() != Some("dev")
+                        && (versions.contains_key(dependency.name.as_str())
+                            || cascading.iter().any(|bump| bump.package == dependency.name))
+                })
+                .collect();
+
+            if dependencies.is_empty() {
+                continue;
+            }
+
+            // The new version of a cascading dependency is unknown, only released ones can break.
+            let breaking = dependencies.iter().any(|dependency| {
+                versions
+                    .get(dependency.name.as_str())
+                    .is_some_and(|version| {
+                        VersionReq::parse(&dependency.req)
+                            .is_ok_and(|requirement| !requirement.matches(version))
+                    })
+            });
+
+            let mut names: Vec<String> = dependencies
+                .iter()
+                .map(|dependency| dependency.name.clone())
+                .collect();
+            names.sort();
+            names.dedup();
+
+            found.push(CascadingBump {
+                package: package.name.clone(),
+                dependencies: names,
+                breaking,
+            });
+        }
+
+        if found.is_empty() {
+            return cascading;
+        }
+
+        cascading.extend(found);
+    }
+}
+
+/// Orders the publishable released packages so each one comes after the released packages it depends on.
+fn publish_order(members: &[&Package], versions: &BTreeMap<&str, &Version>) -> Result<Vec<String>> {
+    let released: Vec<&Package> = members
+        .iter()
+        .copied()
+        .filter(|package| versions.contains_key(package.name.as_str()))
+        .filter(|package| package.publish.restrictions() != PublishingRestrictions::Forbidden)
+        .collect();
+
+    // The released dependencies of each released package that aren't published yet.
+    let mut pending: BTreeMap<&str, BTreeSet<&str>> = released
+        .iter()
+        .map(|package| {
+            let dependencies = package
+                .dependencies
+                .iter()
+                .filter(|dependency| dependency.kind.as_deref() != Some("dev"))
+                .filter(|dependency| dependency.name != package.name)
+                .filter(|dependency| {
+                    released
+                        .iter()
+                        .any(|released| released.name == dependency.name)
+                })
+                .map(|dependency| dependency.name.as_str())
+                .collect();
+
+            (package.name.as_str(), dependencies)
+        })
+        .collect();
+
+    let mut order = Vec::new();
+
+    while !pending.is_empty() {
+        let ready: Vec<&str> = pending
+            .iter()
+            .filter(|(_, dependencies)| dependencies.is_empty())
+            .map(|(name, _)| *name)
+            .collect();
+
+        if ready.is_empty() {
+            return Err(ParsingError::Release(format!(
+                "dependency cycle between {}",
+                pending.keys().copied().collect::<Vec<_>>().join(", ")
+            )));
+        }
+
+        for name in ready {
+            pending.remove(name);
+
+            for dependencies in pending.values_mut() {
+                dependencies.remove(name);
+            }
+
+            order.push(name.to_string());
+        }
+    }
+
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cargo::home::temp_dir;
+    use serde_json::{json, Value};
+
+    fn package(name: &str, dependencies: &[(&str, &str, Option<&str>)], publish: Value) -> Package {
+        let dependencies: Vec<_> = dependencies
+            .iter()
+            .map(|(name, req, kind)| {
+                json!({
+                    "name": name,
+                    "source": null,
+                    "req": req,
+                    "kind": kind,
+                    "rename": null,
+                    "optional": false,
+                    "uses_default_features": true,
+                    "features": [],
+                    "target": null,
+                    "path": format!("/ws/{name}"),
+                    "registry": null,
+                })
+            })
+            .collect();
+
+        Package {
+            dependencies: serde_json::from_value(Value::Array(dependencies)).unwrap(),
+            publish: serde_json::from_value(publish).unwrap(),
+            ..Package::fixture(name)
+        }
+    }
+
+    #[test]
+    fn requirements() {
+        let to: Version = "1.3.0".parse().unwrap();
+
+        assert_eq!(bumped_requirement("1.2", &to), "1.3.0");
+        assert_eq!(bumped_requirement("^1.2.3", &to), "^1.3.0");
+        assert_eq!(bumped_requirement("=1.2.3", &to), "=1.3.0");
+        assert_eq!(bumped_requirement(" ~1.2 ", &to), "~1.3.0");
+        assert_eq!(bumped_requirement(">= 1.2", &to), ">=1.3.0");
+
+        // Several comparators or wildcards can't keep their shape.
+        assert_eq!(bumped_requirement(">=1.2, <2", &to), "1.3.0");
+        assert_eq!(bumped_requirement("1.*", &to), "1.3.0");
+    }
+
+    #[test]
+    fn cascading() {
+        let packages = [
+            package("core", &[], Value::Null),
+            package("app", &[("core", "^0.1", None)], Value::Null),
+            package("cli", &[("app", "^0.1", None)], Value::Null),
+            package("bench", &[("cli", "^0.1", Some("dev"))], Value::Null),
+            package("internal", &[("core", "^0.1", None)], json!([])),
+        ];
+        let members: Vec<_> = packages.iter().map(|package| package.id.clone()).collect();
+
+        let metadata: Metadata = serde_json::from_value(json!({
+            "packages": packages,
+            "workspace_members": members,
+            "resolve": null,
+            "target_directory": "/ws/target",
+            "version": 1,
+            "workspace_root": "/ws",
+            "metadata": null,
+        }))
+        .unwrap();
+
+        let to: Version = "0.2.0".parse().unwrap();
+        let versions = BTreeMap::from([("core", &to)]);
+
+        // "cli" only depends on "core" through "app", dev dependencies and unpublished packages don't count.
+        assert_eq!(
+            cascading_bumps(&metadata, &versions),
+            [
+                CascadingBump {
+                    package: "app".to_string(),
+                    dependencies: vec!["core".to_string()],
+                    breaking: true,
+                },
+                CascadingBump {
+                    package: "cli".to_string(),
+                    dependencies: vec!["app".to_string()],
+                    breaking: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_version() {
+        let dir = temp_dir("release");
+
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"unversioned\"\nedition = \"2021\"\n\n[workspace]\n",
+        )
+        .unwrap();
+        fs::write(dir.join("src").join("lib.rs"), "").unwrap();
+
+        let result = Cargo::new().release(ReleaseConfig {
+            packages: vec![("unversioned".to_string(), Bump::Patch)],
+            dry_run: true,
+            manifest_path: Some(dir.join("Cargo.toml")),
+        });
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(
+            matches!(result, Err(ParsingError::Release(_))),
+            "{result:?}"
+        );
+    }
+}
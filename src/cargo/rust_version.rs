@@ -0,0 +1,139 @@
+use semver::Version;
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use super::{ParsingError, Result};
+
+/// A minimum supported rust version, as written in the `rust-version` field of a manifest, E.g. "1.70" or "1.70.1".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RustVersion {
+    pub major: u64,
+    pub minor: u64,
+    /// The patch version, often left out.
+    pub patch: Option<u64>,
+}
+
+impl FromStr for RustVersion {
+    type Err = ParsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParsingError::RustVersion(s.to_string());
+        let number = |part: &str| part.parse::<u64>().map_err(|_| invalid());
+
+        let mut parts = s.trim().split('.');
+
+        let (Some(major), Some(minor)) = (parts.next(), parts.next()) else {
+            return Err(invalid());
+        };
+
+        let version = RustVersion {
+            major: number(major)?,
+            minor: number(minor)?,
+            patch: parts.next().map(number).transpose()?,
+        };
+
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(version)
+    }
+}
+
+impl Display for RustVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+
+        if let Some(patch) = self.patch {
+            write!(f, ".{patch}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl RustVersion {
+    /// The full version, with a missing patch version as 0.
+    pub fn to_semver(&self) -> Version {
+        Version::new(self.major, self.minor, self.patch.unwrap_or(0))
+    }
+
+    /// Whether a toolchain of the given version is new enough.
+    ///
+    /// Pre-releases count as the release they precede, E.g. "1.70.0-nightly" satisfies "1.70".
+    pub fn is_satisfied_by(&self, version: &Version) -> bool {
+        (version.major, version.minor, version.patch)
+            >= (self.major, self.minor, self.patch.unwrap_or(0))
+    }
+
+    /// Whether the given version is the release this version names, E.g. "1.70.2" is for "1.70" but not for "1.70.1".
+    pub fn is_release(&self, version: &Version) -> bool {
+        version.major == self.major
+            && version.minor == self.minor
+            && self.patch.is_none_or(|patch| version.patch == patch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rust_version(s: &str) -> RustVersion {
+        s.parse().unwrap()
+    }
+
+    fn version(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(
+            rust_version("1.70"),
+            RustVersion {
+                major: 1,
+                minor: 70,
+                patch: None
+            }
+        );
+        assert_eq!(rust_version("1.70").to_string(), "1.70");
+        assert_eq!(rust_version("1.70.1").patch, Some(1));
+        assert_eq!(rust_version("1.70.1").to_string(), "1.70.1");
+    }
+
+    #[test]
+    fn invalid() {
+        for invalid in ["1", "1.70.0.1", "", "1.x", "1.70.", "1.70.0-nightly"] {
+            assert!(
+                matches!(
+                    invalid.parse::<RustVersion>(),
+                    Err(ParsingError::RustVersion(value)) if value == invalid
+                ),
+                "{invalid:?} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn satisfied() {
+        let msrv = rust_version("1.70");
+
+        assert!(msrv.is_satisfied_by(&version("1.70.0-nightly")));
+        assert!(msrv.is_satisfied_by(&version("1.70.0")));
+        assert!(msrv.is_satisfied_by(&version("1.85.1")));
+        assert!(!msrv.is_satisfied_by(&version("1.69.9")));
+        assert!(!rust_version("1.70.1").is_satisfied_by(&version("1.70.0")));
+    }
+
+    #[test]
+    fn releases() {
+        let version = version("1.70.2");
+
+        assert!(rust_version("1.70").is_release(&version));
+        assert!(rust_version("1.70.2").is_release(&version));
+        assert!(!rust_version("1.70.1").is_release(&version));
+        assert!(!rust_version("1.71").is_release(&version));
+    }
+}
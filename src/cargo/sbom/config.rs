@@ -0,0 +1,19 @@
+use std::path::PathBuf;
+use target_lexicon::Triple;
+
+use crate::cargo::Features;
+
+#[derive(Debug, Default, Clone)]
+pub struct SbomConfig {
+    /// The workspace member the SBOM describes. Describes the whole workspace if [None] and no
+    /// [bin](Self::bin) is selected.
+    pub package: Option<String>,
+    /// Only include the dependencies linked into this binary of the package, without dev and build dependencies.
+    ///
+    /// Defaults to the current package if no [package](Self::package) is selected.
+    pub bin: Option<String>,
+    /// Only include the dependencies used on this platform, passed as `--filter-platform`.
+    pub target: Option<Triple>,
+    pub features: Option<Features>,
+    pub manifest_path: Option<PathBuf>,
+}
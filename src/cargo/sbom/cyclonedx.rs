@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+
+use super::{ComponentKind, Sbom, SbomComponent};
+
+/// A CycloneDX 1.5 JSON document.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CycloneDx {
+    /// Always "CycloneDX".
+    pub bom_format: String,
+    pub spec_version: String,
+    /// The version of the document, incremented when it is modified.
+    pub version: u32,
+    pub metadata: CycloneDxMetadata,
+    pub components: Vec<CycloneDxComponent>,
+    pub dependencies: Vec<CycloneDxDependency>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CycloneDxMetadata {
+    /// When the document was created, E.g. "2023-06-01T12:00:00Z".
+    pub timestamp: String,
+    pub tools: CycloneDxTools,
+    /// The described package, [None] for a whole workspace.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component: Option<CycloneDxComponent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CycloneDxTools {
+    pub components: Vec<CycloneDxComponent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CycloneDxComponent {
+    /// "application" or "library".
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The identifier used by the dependencies, the Package ID.
+    #[serde(rename = "bom-ref", skip_serializing_if = "Option::is_none")]
+    pub bom_ref: Option<String>,
+    pub name: String,
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub licenses: Vec<CycloneDxLicense>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purl: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hashes: Vec<CycloneDxHash>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CycloneDxLicense {
+    /// An SPDX license expression.
+    pub expression: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CycloneDxHash {
+    /// The algorithm, E.g. "SHA-256".
+    pub alg: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CycloneDxDependency {
+    /// The `bom-ref` of the component.
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub depends_on: Vec<String>,
+}
+
+impl From<&SbomComponent> for CycloneDxComponent {
+    fn from(component: &SbomComponent) -> Self {
+        CycloneDxComponent {
+            kind: match component.kind {
+                ComponentKind::Application => "application",
+                ComponentKind::Library => "library",
+            }
+            .to_string(),
+            bom_ref: Some(component.id.clone()),
+            name: component.name.clone(),
+            version: component.version.clone(),
+            description: component.description.clone(),
+            licenses: component
+                .license
+                .iter()
+                .map(|license| CycloneDxLicense {
+                    expression: license.clone(),
+                })
+                .collect(),
+            purl: Some(component.purl.clone()),
+            hashes: component
+                .sha256
+                .iter()
+                .map(|sha256| CycloneDxHash {
+                    alg: "SHA-256".to_string(),
+                    content: sha256.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Sbom {
+    /// Exports the SBOM as a CycloneDX document, with the described package as the metadata component.
+    pub fn to_cyclonedx(&self) -> CycloneDx {
+        let is_root = |component: &&SbomComponent| self.root.as_ref() == Some(&component.id);
+
+        CycloneDx {
+            bom_format: "CycloneDX".to_string(),
+            spec_version: "1.5".to_string(),
+            version: 1,
+            metadata: CycloneDxMetadata {
+                timestamp: timestamp(self),
+                tools: CycloneDxTools {
+                    components: vec![CycloneDxComponent {
+                        kind: "application".to_string(),
+                        bom_ref: None,
+                        name: env!("CARGO_PKG_NAME").to_string(),
+                        version: env!("CARGO_PKG_VERSION").to_string(),
+                        description: None,
+                        licenses: Vec::new(),
+                        purl: None,
+                        hashes: Vec::new(),
+                    }],
+                },
+                component: self.components.iter().find(is_root).map(Into::into),
+            },
+            components: self
+                .components
+                .iter()
+                .filter(|component| !is_root(component))
+                .map(Into::into)
+                .collect(),
+            dependencies: self
+                .dependencies
+                .iter()
+                .map(|(id, dependencies)| CycloneDxDependency {
+                    reference: id.clone(),
+                    depends_on: dependencies.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The creation time of the SBOM, to the second, E.g. "2023-06-01T12:00:00Z".
+pub(super) fn timestamp(sbom: &Sbom) -> String {
+    sbom.created
+        .replace_nanosecond(0)
+        .unwrap_or(sbom.created)
+        .format(&Rfc3339)
+        .unwrap_or_default()
+}
@@ -0,0 +1,631 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::ErrorKind,
+};
+use time::OffsetDateTime;
+
+use super::{
+    build::select_package,
+    metadata::{Node, Package, TargetKind},
+    Cargo, Lockfile, Metadata, MetadataConfig, PackageId, ParsingError, Result, SourceId,
+    SourceKind,
+};
+
+mod config;
+mod cyclonedx;
+mod spdx;
+pub use config::SbomConfig;
+pub use cyclonedx::CycloneDx;
+pub use spdx::Spdx;
+
+/// A software bill of materials, the packages a workspace, package or binary is made of.
+///
+/// Export it with [to_cyclonedx](Sbom::to_cyclonedx) or [to_spdx](Sbom::to_spdx).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sbom {
+    /// The Package ID of the described package, [None] for a whole workspace.
+    pub root: Option<String>,
+    /// The Package IDs of the workspace members.
+    pub workspace_members: Vec<String>,
+    /// Every package, including the described one.
+    pub components: Vec<SbomComponent>,
+    /// The Package IDs of the dependencies of each component, by Package ID.
+    pub dependencies: BTreeMap<String, Vec<String>>,
+    /// When the SBOM was created, set to a fixed time for reproducible documents.
+    pub created: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentKind {
+    /// The described binary.
+    Application,
+    Library,
+}
+
+/// A package in an [Sbom].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SbomComponent {
+    /// The Package ID of the package.
+    pub id: String,
+    pub kind: ComponentKind,
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    /// The license of the package as an SPDX expression, E.g. "MIT OR Apache-2.0".
+    pub license: Option<String>,
+    /// The package URL, E.g. "pkg:cargo/serde@1.0.0".
+    pub purl: String,
+    /// Where the package can be downloaded from, [None] for local packages.
+    pub download_url: Option<String>,
+    /// The sha256 checksum of the `.crate` file from the lockfile, only known for registry packages.
+    pub sha256: Option<String>,
+}
+
+impl Sbom {
+    /// Builds the SBOM of the workspace from its metadata, with the checksums from its lockfile if given.
+    ///
+    /// Every package of the [resolve](Metadata::resolve) graph is included, use [Cargo::sbom] to scope it to
+    /// a package or a binary.
+    pub fn from_metadata(metadata: &Metadata, lockfile: Option<&Lockfile>) -> Sbom {
+        let nodes: Vec<&Node> = metadata
+            .resolve
+            .iter()
+            .flat_map(|resolve| &resolve.nodes)
+            .collect();
+
+        Sbom::from_nodes(metadata, lockfile, None, &nodes)
+    }
+
+    fn from_nodes(
+        metadata: &Metadata,
+        lockfile: Option<&Lockfile>,
+        root: Option<(&str, ComponentKind)>,
+        nodes: &[&Node],
+    ) -> Sbom {
+        let included: BTreeSet<&str> = nodes.iter().map(|node| node.id.as_str()).collect();
+
+        let components = metadata
+            .packages
+            .iter()
+            .filter(|package| included.contains(package.id.as_str()))
+            .map(|package| {
+                let kind = match root {
+                    Some((id, kind)) if id == package.id => kind,
+                    _ => ComponentKind::Library,
+                };
+
+                component(package, kind, lockfile)
+            })
+            .collect();
+
+        let dependencies = nodes
+            .iter()
+            .map(|node| {
+                let dependencies = node
+                    .deps
+                    .iter()
+                    .filter(|dep| included.contains(dep.pkg.as_str()))
+                    .map(|dep| dep.pkg.clone())
+                    .collect();
+
+                (node.id.clone(), dependencies)
+            })
+            .collect();
+
+        Sbom {
+            root: root.map(|(id, _)| id.to_string()),
+            workspace_members: metadata.workspace_members.clone(),
+            components,
+            dependencies,
+            created: OffsetDateTime::now_utc(),
+        }
+    }
+
+    /// Builds the SBOM of a package, with only the normal dependencies of the package if `scoped` to one of
+    /// its binaries.
+    fn scoped(
+        metadata: &Metadata,
+        lockfile: Option<&Lockfile>,
+        package: &Package,
+        scoped: bool,
+    ) -> Sbom {
+        let resolve = metadata.resolve.as_ref();
+        let node = |id: &str| resolve.and_then(|resolve| resolve.node(id));
+
+        // Binaries only link normal dependencies, while the dev dependencies of a package only matter
+        // for the package itself.
+        let follows = |node: &Node, is_root: bool| -> Vec<String> {
+            node.deps
+                .iter()
+                .filter(|dep| {
+                    dep.dep_kinds.iter().any(|info| match info.kind.as_deref() {
+                        None => true,
+                        Some("dev") => is_root && !scoped,
+                        Some(_) => !scoped,
+                    })
+                })
+                .map(|dep| dep.pkg.clone())
+                .collect()
+        };
+
+        let mut nodes: Vec<&Node> = Vec::new();
+        let mut edges: BTreeSet<(String, String)> = BTreeSet::new();
+        let mut pending = vec![(package.id.clone(), true)];
+
+        while let Some((id, is_root)) = pending.pop() {
+            let Some(current) = node(&id) else {
+                continue;
+            };
+
+            if nodes.iter().any(|node| node.id == id) {
+                continue;
+            }
+
+            nodes.push(current);
+
+            for dependency in follows(current, is_root) {
+                edges.insert((id.clone(), dependency.clone()));
+                pending.push((dependency, false));
+            }
+        }
+
+        let kind = if scoped {
+            ComponentKind::Application
+        } else {
+            ComponentKind::Library
+        };
+
+        let mut sbom = Sbom::from_nodes(metadata, lockfile, Some((&package.id, kind)), &nodes);
+
+        // Only keep the edges that were followed, E.g. not the dev dependencies of a dependency.
+        for (id, dependencies) in sbom.dependencies.iter_mut() {
+            dependencies.retain(|dependency| edges.contains(&(id.clone(), dependency.clone())));
+        }
+
+        sbom
+    }
+
+    /// Returns the component with the given Package ID.
+    pub fn component(&self, id: &str) -> Option<&SbomComponent> {
+        self.components.iter().find(|component| component.id == id)
+    }
+}
+
+fn component(package: &Package, kind: ComponentKind, lockfile: Option<&Lockfile>) -> SbomComponent {
+    let source: Option<SourceId> = package
+        .source
+        .as_deref()
+        .and_then(|source| source.parse().ok());
+
+    let sha256 = lockfile.and_then(|lockfile| {
+        let id: PackageId = package.id.parse().ok()?;
+        lockfile.get(&id)?.checksum.clone()
+    });
+
+    SbomComponent {
+        id: package.id.clone(),
+        kind,
+        name: package.name.clone(),
+        version: package.version.clone(),
+        description: package.description.clone(),
+        // Old manifests separate licenses with slashes, E.g. "MIT/Apache-2.0".
+        license: package.license.as_ref().map(|license| {
+            license
+                .split('/')
+                .map(str::trim)
+                .collect::<Vec<_>>()
+                .join(" OR ")
+        }),
+        purl: purl(&package.name, &package.version, source.as_ref()),
+        download_url: source
+            .as_ref()
+            .and_then(|source| download_url(&package.name, &package.version, source)),
+        sha256,
+    }
+}
+
+/// The package URL of a crate, following the purl specification for the "cargo" type.
+fn purl(name: &str, version: &str, source: Option<&SourceId>) -> String {
+    let purl = format!("pkg:cargo/{}@{}", encode(name), encode(version));
+
+    match source {
+        Some(source) if source.is_crates_io() => purl,
+        Some(source) if source.is_registry() => {
+            format!("{purl}?repository_url={}", encode(&source.url))
+        }
+        Some(source) if source.is_git() => {
+            let vcs_url = match &source.precise {
+                Some(precise) => format!("git+{}@{precise}", source.url),
+                None => format!("git+{}", source.url),
+            };
+
+            format!("{purl}?vcs_url={}", encode(&vcs_url))
+        }
+        _ => purl,
+    }
+}
+
+fn download_url(name: &str, version: &str, source: &SourceId) -> Option<String> {
+    match &source.kind {
+        _ if source.is_crates_io() => Some(format!(
+            "https://crates.io/api/v1/crates/{name}/{version}/download"
+        )),
+        SourceKind::Git(_) => Some(match &source.precise {
+            Some(precise) => format!("git+{}@{precise}", source.url),
+            None => format!("git+{}", source.url),
+        }),
+        _ => None,
+    }
+}
+
+/// Percent-encodes the characters that have a meaning in a package URL.
+fn encode(value: &str) -> String {
+    value
+        .chars()
+        .map(|char| match char {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '.' | '_' | '~' | ':' | '/' => {
+                char.to_string()
+            }
+            char => {
+                let mut bytes = [0; 4];
+                char.encode_utf8(&mut bytes)
+                    .bytes()
+                    .map(|byte| format!("%{byte:02X}"))
+                    .collect()
+            }
+        })
+        .collect()
+}
+
+impl Cargo {
+    /// Builds the SBOM of the workspace, of a package or of a binary, see [SbomConfig].
+    ///
+    /// Checksums are read from the lockfile, and left out if there isn't one. Fails if the lockfile can't be parsed.
+    pub fn sbom(&mut self, config: SbomConfig) -> Result<Sbom> {
+        let metadata = self.metadata(MetadataConfig {
+            features: config.features.clone(),
+            filter_platform: config.target.clone(),
+            manifest_path: config.manifest_path.clone(),
+            no_deps: false,
+        })?;
+
+        let lockfile = match metadata.lockfile() {
+            Ok(lockfile) => Some(lockfile),
+            Err(ParsingError::Io(error)) if error.kind() == ErrorKind::NotFound => None,
+            Err(error) => return Err(error),
+        };
+
+        if config.package.is_none() && config.bin.is_none() {
+            return Ok(Sbom::from_metadata(&metadata, lockfile.as_ref()));
+        }
+
+        let package = select_package(
+            &metadata,
+            config.package.as_deref(),
+            config.manifest_path.as_deref(),
+        )?;
+
+        if let Some(bin) = &config.bin {
+            package
+                .targets
+                .iter()
+                .find(|target| &target.name == bin && target.kind.contains(&TargetKind::Bin))
+                .ok_or_else(|| {
+                    ParsingError::Selection(format!(
+                        "binary `{bin}` not found in `{}`",
+                        package.name
+                    ))
+                })?;
+        }
+
+        Ok(Sbom::scoped(
+            &metadata,
+            lockfile.as_ref(),
+            package,
+            config.bin.is_some(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cargo::metadata::{DepKindInfo, NodeDep, Resolve};
+    use std::path::PathBuf;
+
+    const CRATES_IO: &str = "registry+https://github.com/rust-lang/crates.io-index";
+
+    fn source(source: &str) -> SourceId {
+        source.parse().unwrap()
+    }
+
+    fn registry(name: &str, license: &str) -> Package {
+        Package {
+            id: format!("{CRATES_IO}#{name}@1.0.0"),
+            version: "1.0.0".to_string(),
+            license: Some(license.to_string()),
+            source: Some(CRATES_IO.to_string()),
+            ..Package::fixture(name)
+        }
+    }
+
+    fn node(package: &Package, deps: &[(&Package, Option<&str>)]) -> Node {
+        Node {
+            id: package.id.clone(),
+            dependencies: deps.iter().map(|(dep, _)| dep.id.clone()).collect(),
+            deps: deps
+                .iter()
+                .map(|(dep, kind)| NodeDep {
+                    name: dep.name.clone(),
+                    pkg: dep.id.clone(),
+                    dep_kinds: vec![DepKindInfo {
+                        kind: kind.map(str::to_string),
+                        target: None,
+                    }],
+                })
+                .collect(),
+            features: Vec::new(),
+        }
+    }
+
+    /// A package with a normal, a build and a dev dependency, and the lockfile with their checksums.
+    fn workspace() -> (Metadata, Lockfile) {
+        let app = Package::fixture("app");
+        let serde = registry("serde", "MIT/Apache-2.0");
+        let cc = registry("cc", "MIT");
+        let pretty = registry("pretty", "MIT");
+
+        let metadata = Metadata {
+            resolve: Some(Resolve {
+                nodes: vec![
+                    node(
+                        &app,
+                        &[(&serde, None), (&cc, Some("build")), (&pretty, Some("dev"))],
+                    ),
+                    node(&serde, &[]),
+                    node(&cc, &[]),
+                    node(&pretty, &[]),
+                ],
+                root: Some(app.id.clone()),
+            }),
+            workspace_members: vec![app.id.clone()],
+            packages: vec![app, serde, cc, pretty],
+            target_directory: PathBuf::from("/ws/target"),
+            version: 1,
+            workspace_root: PathBuf::from("/ws"),
+            workspace_metadata: None,
+        };
+
+        let lockfile = format!(
+            "version = 3\n\n\
+             [[package]]\nname = \"app\"\nversion = \"0.1.0\"\ndependencies = [\"cc\", \"pretty\", \"serde\"]\n\n\
+             [[package]]\nname = \"cc\"\nversion = \"1.0.0\"\nsource = \"{CRATES_IO}\"\nchecksum = \"cc00\"\n\n\
+             [[package]]\nname = \"pretty\"\nversion = \"1.0.0\"\nsource = \"{CRATES_IO}\"\nchecksum = \"ff00\"\n\n\
+             [[package]]\nname = \"serde\"\nversion = \"1.0.0\"\nsource = \"{CRATES_IO}\"\nchecksum = \"5e00\"\n"
+        );
+
+        (metadata, lockfile.parse().unwrap())
+    }
+
+    fn id(name: &str) -> String {
+        match name {
+            "app" => Package::fixture(name).id,
+            name => format!("{CRATES_IO}#{name}@1.0.0"),
+        }
+    }
+
+    #[test]
+    fn whole_workspace() {
+        let (metadata, lockfile) = workspace();
+        let sbom = Sbom::from_metadata(&metadata, Some(&lockfile));
+
+        assert_eq!(sbom.root, None);
+        assert_eq!(sbom.components.len(), 4);
+        assert_eq!(
+            sbom.dependencies[&id("app")],
+            vec![id("serde"), id("cc"), id("pretty")]
+        );
+
+        let app = sbom.component(&id("app")).unwrap();
+        assert_eq!(app.kind, ComponentKind::Library);
+        assert_eq!(app.sha256, None);
+        assert_eq!(app.download_url, None);
+
+        let serde = sbom.component(&id("serde")).unwrap();
+        assert_eq!(serde.sha256.as_deref(), Some("5e00"));
+        assert_eq!(serde.license.as_deref(), Some("MIT OR Apache-2.0"));
+        assert_eq!(serde.purl, "pkg:cargo/serde@1.0.0");
+        assert_eq!(
+            serde.download_url.as_deref(),
+            Some("https://crates.io/api/v1/crates/serde/1.0.0/download")
+        );
+
+        let sbom = Sbom::from_metadata(&metadata, None);
+        assert_eq!(sbom.component(&id("serde")).unwrap().sha256, None);
+    }
+
+    #[test]
+    fn scopes() {
+        let (metadata, lockfile) = workspace();
+        let app = &metadata.packages[0];
+
+        let package = Sbom::scoped(&metadata, Some(&lockfile), app, false);
+        assert_eq!(package.root, Some(id("app")));
+        assert_eq!(package.components.len(), 4);
+        assert_eq!(package.dependencies[&id("app")].len(), 3);
+        assert_eq!(
+            package.component(&id("app")).unwrap().kind,
+            ComponentKind::Library
+        );
+
+        // A binary doesn't link its dev and build dependencies.
+        let bin = Sbom::scoped(&metadata, Some(&lockfile), app, true);
+        let ids: Vec<&str> = bin
+            .components
+            .iter()
+            .map(|component| component.id.as_str())
+            .collect();
+        assert_eq!(ids, [id("app"), id("serde")]);
+        assert_eq!(
+            bin.dependencies,
+            BTreeMap::from([(id("app"), vec![id("serde")]), (id("serde"), Vec::new())])
+        );
+        assert_eq!(
+            bin.component(&id("app")).unwrap().kind,
+            ComponentKind::Application
+        );
+    }
+
+    #[test]
+    fn cyclonedx() {
+        let (metadata, lockfile) = workspace();
+        let sbom = Sbom {
+            created: OffsetDateTime::UNIX_EPOCH,
+            ..Sbom::scoped(&metadata, Some(&lockfile), &metadata.packages[0], true)
+        };
+        let document = sbom.to_cyclonedx();
+
+        assert_eq!(document.bom_format, "CycloneDX");
+        assert_eq!(document.spec_version, "1.5");
+        assert_eq!(document.metadata.timestamp, "1970-01-01T00:00:00Z");
+
+        let root = document.metadata.component.unwrap();
+        assert_eq!(root.kind, "application");
+        assert_eq!(root.bom_ref, Some(id("app")));
+
+        // The described package is only the metadata component.
+        assert_eq!(document.components.len(), 1);
+        let serde = &document.components[0];
+        assert_eq!(serde.kind, "library");
+        assert_eq!(serde.hashes.len(), 1);
+        assert_eq!(serde.hashes[0].alg, "SHA-256");
+        assert_eq!(serde.hashes[0].content, "5e00");
+        assert_eq!(serde.licenses[0].expression, "MIT OR Apache-2.0");
+
+        let app = document
+            .dependencies
+            .iter()
+            .find(|dependency| dependency.reference == id("app"))
+            .unwrap();
+        assert_eq!(app.depends_on, [id("serde")]);
+    }
+
+    #[test]
+    fn spdx() {
+        let (metadata, lockfile) = workspace();
+        let sbom = Sbom {
+            created: OffsetDateTime::UNIX_EPOCH,
+            ..Sbom::scoped(&metadata, Some(&lockfile), &metadata.packages[0], true)
+        };
+        let document = sbom.to_spdx();
+
+        assert_eq!(document.spdx_version, "SPDX-2.3");
+        assert_eq!(document.name, "app-0.1.0");
+        assert_eq!(document.creation_info.created, "1970-01-01T00:00:00Z");
+
+        let spdx_id = |name: &str| {
+            document
+                .packages
+                .iter()
+                .find(|package| package.name == name)
+                .map(|package| package.spdx_id.clone())
+                .unwrap()
+        };
+
+        let relationships: Vec<(&str, &str, &str)> = document
+            .relationships
+            .iter()
+            .map(|relationship| {
+                (
+                    relationship.spdx_element_id.as_str(),
+                    relationship.relationship_type.as_str(),
+                    relationship.related_spdx_element.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            relationships,
+            [
+                ("SPDXRef-DOCUMENT", "DESCRIBES", spdx_id("app").as_str()),
+                (
+                    spdx_id("app").as_str(),
+                    "DEPENDS_ON",
+                    spdx_id("serde").as_str()
+                ),
+            ]
+        );
+
+        let serde = document
+            .packages
+            .iter()
+            .find(|package| package.name == "serde")
+            .unwrap();
+        assert_eq!(serde.checksums[0].algorithm, "SHA256");
+        assert_eq!(serde.checksums[0].checksum_value, "5e00");
+        assert_eq!(serde.primary_package_purpose, "LIBRARY");
+
+        // The namespace only changes with the content of the document.
+        assert!(document
+            .document_namespace
+            .starts_with("https://spdx.org/spdxdocs/app-0.1.0-"));
+        assert_eq!(
+            document.document_namespace,
+            sbom.to_spdx().document_namespace
+        );
+
+        let later = Sbom {
+            created: OffsetDateTime::UNIX_EPOCH + time::Duration::days(1),
+            ..sbom.clone()
+        };
+        assert_ne!(
+            document.document_namespace,
+            later.to_spdx().document_namespace
+        );
+    }
+
+    #[test]
+    fn purls() {
+        assert_eq!(
+            purl(
+                "serde",
+                "1.0.0",
+                Some(&source(
+                    "registry+https://github.com/rust-lang/crates.io-index"
+                ))
+            ),
+            "pkg:cargo/serde@1.0.0"
+        );
+        assert_eq!(
+            purl(
+                "serde",
+                "1.0.0",
+                Some(&source("sparse+https://index.crates.io/"))
+            ),
+            "pkg:cargo/serde@1.0.0"
+        );
+        assert_eq!(
+            purl(
+                "private",
+                "0.1.0",
+                Some(&source("sparse+https://example.com/index/"))
+            ),
+            "pkg:cargo/private@0.1.0?repository_url=https://example.com/index/"
+        );
+        assert_eq!(
+            purl(
+                "fork",
+                "0.1.0",
+                Some(&source("git+https://github.com/a/fork?branch=main#0123abc"))
+            ),
+            "pkg:cargo/fork@0.1.0?vcs_url=git%2Bhttps://github.com/a/fork%400123abc"
+        );
+        assert_eq!(purl("local", "0.1.0", None), "pkg:cargo/local@0.1.0");
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(encode("1.0.0-rc.1+build.5"), "1.0.0-rc.1%2Bbuild.5");
+        assert_eq!(encode("a b?c#d&e=f"), "a%20b%3Fc%23d%26e%3Df");
+        assert_eq!(encode("é"), "%C3%A9");
+    }
+}
@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, hash::Hasher};
+
+use super::{cyclonedx::timestamp, ComponentKind, Sbom};
+use crate::cargo::hash::StableHasher;
+
+/// Used by SPDX for values that aren't known.
+const NOASSERTION: &str = "NOASSERTION";
+
+/// An SPDX 2.3 JSON document.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Spdx {
+    pub spdx_version: String,
+    pub data_license: String,
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    pub name: String,
+    /// A unique URI identifying the document.
+    pub document_namespace: String,
+    pub creation_info: SpdxCreationInfo,
+    pub packages: Vec<SpdxPackage>,
+    pub relationships: Vec<SpdxRelationship>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SpdxCreationInfo {
+    /// When the document was created, E.g. "2023-06-01T12:00:00Z".
+    pub created: String,
+    /// E.g. "Tool: payload-0.1.0".
+    pub creators: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxPackage {
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    pub name: String,
+    pub version_info: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// "APPLICATION" or "LIBRARY".
+    pub primary_package_purpose: String,
+    /// A download url or [NOASSERTION].
+    pub download_location: String,
+    pub license_concluded: String,
+    /// An SPDX license expression or [NOASSERTION].
+    pub license_declared: String,
+    pub copyright_text: String,
+    pub files_analyzed: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checksums: Vec<SpdxChecksum>,
+    pub external_refs: Vec<SpdxExternalRef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxChecksum {
+    /// The algorithm, E.g. "SHA256".
+    pub algorithm: String,
+    pub checksum_value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxExternalRef {
+    pub reference_category: String,
+    pub reference_type: String,
+    pub reference_locator: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxRelationship {
+    pub spdx_element_id: String,
+    /// E.g. "DESCRIBES" or "DEPENDS_ON".
+    pub relationship_type: String,
+    pub related_spdx_element: String,
+}
+
+impl Sbom {
+    /// Exports the SBOM as an SPDX document describing the root package, or every workspace member
+    /// for a whole workspace.
+    pub fn to_spdx(&self) -> Spdx {
+        // SPDX identifiers only allow letters, numbers, dots and dashes.
+        let ids: BTreeMap<&str, String> = self
+            .components
+            .iter()
+            .enumerate()
+            .map(|(index, component)| {
+                let name: String = component
+                    .name
+                    .chars()
+                    .map(|char| {
+                        if char.is_ascii_alphanumeric() {
+                            char
+                        } else {
+                            '-'
+                        }
+                    })
+                    .collect();
+
+                (
+                    component.id.as_str(),
+                    format!("SPDXRef-Package-{index}-{name}"),
+                )
+            })
+            .collect();
+
+        let root = self.root.as_deref().and_then(|root| self.component(root));
+        let name = match root {
+            Some(root) => format!("{}-{}", root.name, root.version),
+            None => "workspace".to_string(),
+        };
+
+        // The namespace must be unique for each document, so it depends on its content, hashed the same way
+        // on every platform and version of Rust so the same SBOM always gets the same namespace.
+        let mut hasher = StableHasher::new();
+        for (id, dependencies) in &self.dependencies {
+            for id in std::iter::once(id).chain(dependencies) {
+                hasher.write(id.as_bytes());
+                hasher.write_u8(0);
+            }
+            hasher.write_u8(0xff);
+        }
+        hasher.write(timestamp(self).as_bytes());
+
+        let described: Vec<&str> = match &self.root {
+            Some(root) => vec![root.as_str()],
+            None => self.workspace_members.iter().map(String::as_str).collect(),
+        };
+
+        let mut relationships: Vec<SpdxRelationship> = described
+            .iter()
+            .filter_map(|id| ids.get(id))
+            .map(|id| SpdxRelationship {
+                spdx_element_id: "SPDXRef-DOCUMENT".to_string(),
+                relationship_type: "DESCRIBES".to_string(),
+                related_spdx_element: id.clone(),
+            })
+            .collect();
+
+        for (id, dependencies) in &self.dependencies {
+            let Some(id) = ids.get(id.as_str()) else {
+                continue;
+            };
+
+            relationships.extend(
+                dependencies
+                    .iter()
+                    .filter_map(|dependency| ids.get(dependency.as_str()))
+                    .map(|dependency| SpdxRelationship {
+                        spdx_element_id: id.clone(),
+                        relationship_type: "DEPENDS_ON".to_string(),
+                        related_spdx_element: dependency.clone(),
+                    }),
+            );
+        }
+
+        Spdx {
+            spdx_version: "SPDX-2.3".to_string(),
+            data_license: "CC0-1.0".to_string(),
+            spdx_id: "SPDXRef-DOCUMENT".to_string(),
+            document_namespace: format!(
+                "https://spdx.org/spdxdocs/{name}-{:016x}",
+                hasher.finish()
+            ),
+            name,
+            creation_info: SpdxCreationInfo {
+                created: timestamp(self),
+                creators: vec![format!(
+                    "Tool: {}-{}",
+                    env!("CARGO_PKG_NAME"),
+                    env!("CARGO_PKG_VERSION")
+                )],
+            },
+            packages: self
+                .components
+                .iter()
+                .map(|component| SpdxPackage {
+                    spdx_id: ids[component.id.as_str()].clone(),
+                    name: component.name.clone(),
+                    version_info: component.version.clone(),
+                    description: component.description.clone(),
+                    primary_package_purpose: match component.kind {
+                        ComponentKind::Application => "APPLICATION",
+                        ComponentKind::Library => "LIBRARY",
+                    }
+                    .to_string(),
+                    download_location: component
+                        .download_url
+                        .clone()
+                        .unwrap_or_else(|| NOASSERTION.to_string()),
+                    license_concluded: NOASSERTION.to_string(),
+                    license_declared: component
+                        .license
+                        .clone()
+                        .unwrap_or_else(|| NOASSERTION.to_string()),
+                    copyright_text: NOASSERTION.to_string(),
+                    files_analyzed: false,
+                    checksums: component
+                        .sha256
+                        .iter()
+                        .map(|sha256| SpdxChecksum {
+                            algorithm: "SHA256".to_string(),
+                            checksum_value: sha256.clone(),
+                        })
+                        .collect(),
+                    external_refs: vec![SpdxExternalRef {
+                        reference_category: "PACKAGE-MANAGER".to_string(),
+                        reference_type: "purl".to_string(),
+                        reference_locator: component.purl.clone(),
+                    }],
+                })
+                .collect(),
+            relationships,
+        }
+    }
+}
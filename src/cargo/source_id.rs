@@ -0,0 +1,281 @@
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use super::{ParsingError, Result};
+
+/// The url of the crates.io git index.
+pub const CRATES_IO_INDEX: &str = "https://github.com/rust-lang/crates.io-index";
+/// The url of the crates.io sparse index.
+pub const CRATES_IO_SPARSE_INDEX: &str = "https://index.crates.io/";
+
+/// Where a package is retrieved from.
+///
+/// This is the parsed form of strings such as
+/// "registry+https://github.com/rust-lang/crates.io-index" or
+/// "git+https://github.com/rust-lang/cargo?branch=master#5e85ba14aaa20f8133863373404cb0af69eeef2c".
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SourceId {
+    /// The kind of source.
+    pub kind: SourceKind,
+    /// The url of the source, without the kind prefix, query or fragment.
+    pub url: String,
+    /// The precise revision this source was locked to, E.g. the git commit hash.
+    pub precise: Option<String>,
+}
+
+/// The kind of a [SourceId].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SourceKind {
+    /// A git repository, checked out at the given reference.
+    Git(GitReference),
+    /// A local path.
+    Path,
+    /// A remote registry accessed through a git index.
+    Registry,
+    /// A remote registry accessed through the sparse protocol.
+    SparseRegistry,
+    /// A local filesystem registry.
+    LocalRegistry,
+    /// A directory of vendored sources.
+    Directory,
+}
+
+/// The reference a git source is checked out at.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GitReference {
+    /// A specific branch.
+    Branch(String),
+    /// A specific tag.
+    Tag(String),
+    /// A specific revision.
+    Rev(String),
+    /// The default branch of the repository.
+    DefaultBranch,
+}
+
+impl SourceId {
+    /// Whether this is the crates.io registry, either through git or sparse.
+    pub fn is_crates_io(&self) -> bool {
+        match self.kind {
+            SourceKind::Registry => self.url.trim_end_matches('/') == CRATES_IO_INDEX,
+            SourceKind::SparseRegistry => self.url == CRATES_IO_SPARSE_INDEX,
+            _ => false,
+        }
+    }
+
+    /// Whether this is a registry of any kind.
+    pub fn is_registry(&self) -> bool {
+        matches!(
+            self.kind,
+            SourceKind::Registry | SourceKind::SparseRegistry | SourceKind::LocalRegistry
+        )
+    }
+
+    /// Whether this is a git source.
+    pub fn is_git(&self) -> bool {
+        matches!(self.kind, SourceKind::Git(_))
+    }
+
+    /// Whether this is a local path.
+    pub fn is_path(&self) -> bool {
+        self.kind == SourceKind::Path
+    }
+}
+
+impl FromStr for SourceId {
+    type Err = ParsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, url) = s
+            .split_once('+')
+            .ok_or_else(|| ParsingError::SourceId(s.to_string()))?;
+
+        match kind {
+            "git" => {
+                // The fragment is the precise commit and the query the requested reference.
+                let (url, precise) = match url.split_once('#') {
+                    Some((url, precise)) => (url, Some(precise.to_string())),
+                    None => (url, None),
+                };
+
+                let (url, reference) = match url.split_once('?') {
+                    Some((url, query)) => {
+                        let (key, value) = query
+                            .split_once('=')
+                            .ok_or_else(|| ParsingError::SourceId(s.to_string()))?;
+                        let value = percent_decode(value);
+
+                        let reference = match key {
+                            "branch" => GitReference::Branch(value),
+                            "tag" => GitReference::Tag(value),
+                            "rev" => GitReference::Rev(value),
+                            _ => return Err(ParsingError::SourceId(s.to_string())),
+                        };
+
+                        (url, reference)
+                    }
+                    None => (url, GitReference::DefaultBranch),
+                };
+
+                Ok(SourceId {
+                    kind: SourceKind::Git(reference),
+                    url: url.to_string(),
+                    precise,
+                })
+            }
+            _ => {
+                let kind = match kind {
+                    "path" => SourceKind::Path,
+                    "registry" => SourceKind::Registry,
+                    "sparse" => SourceKind::SparseRegistry,
+                    "local-registry" => SourceKind::LocalRegistry,
+                    "directory" => SourceKind::Directory,
+                    _ => return Err(ParsingError::SourceId(s.to_string())),
+                };
+
+                Ok(SourceId {
+                    kind,
+                    url: url.to_string(),
+                    precise: None,
+                })
+            }
+        }
+    }
+}
+
+impl Display for SourceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            SourceKind::Git(reference) => {
+                write!(f, "git+{}", self.url)?;
+
+                match reference {
+                    GitReference::Branch(branch) => {
+                        write!(f, "?branch={}", percent_encode(branch))?
+                    }
+                    GitReference::Tag(tag) => write!(f, "?tag={}", percent_encode(tag))?,
+                    GitReference::Rev(rev) => write!(f, "?rev={}", percent_encode(rev))?,
+                    GitReference::DefaultBranch => {}
+                }
+
+                if let Some(precise) = &self.precise {
+                    write!(f, "#{precise}")?;
+                }
+
+                Ok(())
+            }
+            SourceKind::Path => write!(f, "path+{}", self.url),
+            SourceKind::Registry => write!(f, "registry+{}", self.url),
+            SourceKind::SparseRegistry => write!(f, "sparse+{}", self.url),
+            SourceKind::LocalRegistry => write!(f, "local-registry+{}", self.url),
+            SourceKind::Directory => write!(f, "directory+{}", self.url),
+        }
+    }
+}
+
+/// Encodes query values the way lockfile version 4 does, E.g. "feature/foo" becomes "feature%2Ffoo".
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                char::from(byte).to_string()
+            }
+            byte => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Decodes the percent-encoded query values used by lockfile version 4.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = s
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry() {
+        let source: SourceId = "registry+https://github.com/rust-lang/crates.io-index"
+            .parse()
+            .unwrap();
+
+        assert_eq!(source.kind, SourceKind::Registry);
+        assert!(source.is_crates_io());
+        assert_eq!(
+            source.to_string(),
+            "registry+https://github.com/rust-lang/crates.io-index"
+        );
+    }
+
+    #[test]
+    fn sparse() {
+        let source: SourceId = "sparse+https://index.crates.io/".parse().unwrap();
+
+        assert_eq!(source.kind, SourceKind::SparseRegistry);
+        assert!(source.is_crates_io());
+    }
+
+    #[test]
+    fn git_round_trip() {
+        for id in [
+            "git+https://github.com/rust-lang/cargo",
+            "git+https://github.com/rust-lang/cargo#5e85ba14aaa20f8133863373404cb0af69eeef2c",
+            "git+https://github.com/rust-lang/cargo?branch=master#5e85ba14aaa20f8133863373404cb0af69eeef2c",
+            "git+https://github.com/rust-lang/cargo?tag=0.70.0",
+            "git+https://github.com/rust-lang/cargo?rev=5e85ba14",
+            "git+https://github.com/rust-lang/cargo?branch=feature%2Ffoo#5e85ba14aaa20f8133863373404cb0af69eeef2c",
+        ] {
+            assert_eq!(id.parse::<SourceId>().unwrap().to_string(), id);
+        }
+    }
+
+    #[test]
+    fn git_percent_encoded() {
+        let source: SourceId = "git+https://github.com/user/repo?tag=v1%2B2%20beta"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            source.kind,
+            SourceKind::Git(GitReference::Tag("v1+2 beta".to_string()))
+        );
+        assert_eq!(
+            source.to_string(),
+            "git+https://github.com/user/repo?tag=v1%2B2%20beta"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!("https://github.com/rust-lang/cargo"
+            .parse::<SourceId>()
+            .is_err());
+        assert!("svn+https://example.com".parse::<SourceId>().is_err());
+        assert!("git+https://example.com?commit=abc"
+            .parse::<SourceId>()
+            .is_err());
+    }
+}
@@ -0,0 +1,479 @@
+use std::collections::BTreeMap;
+
+use super::{
+    message::{Message, TimingInfo},
+    metadata::TargetKind,
+    unit_graph::{Mode, Unit},
+    PackageId, UnitGraph,
+};
+
+/// The timing of a single unit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnitTiming {
+    pub info: TimingInfo,
+    /// The index of the matching unit in the unit graph of the report, if any.
+    pub unit: Option<usize>,
+    /// The units that could start building once this one finished, as indices in the unit graph.
+    ///
+    /// Cargo doesn't emit these, a unit is counted as unlocked by the last of its dependencies
+    /// to finish building.
+    pub unlocked_units: Vec<usize>,
+}
+
+/// The time spent building the units of a package.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrateTiming {
+    /// The Package ID of the package.
+    pub package_id: String,
+    /// The sum of the durations of the units, in seconds.
+    pub duration: f64,
+    /// How many units of the package were built, E.g. the library and its build script.
+    pub units: usize,
+}
+
+/// The longest chain of units that had to be built one after the other.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CriticalPath {
+    /// Indices in the unit graph of the report, from the first unit built to the last.
+    pub units: Vec<usize>,
+    /// How long building the chain took, in seconds.
+    ///
+    /// This is the shortest the build could take with unlimited parallelism.
+    pub duration: f64,
+}
+
+/// The timings of a build, emitted with [timings](super::BuildConfig::timings) enabled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimingReport {
+    /// Array of timings, in the order the units finished building.
+    ///
+    /// Units that were already fresh don't have a timing.
+    pub units: Vec<UnitTiming>,
+    /// The unit graph of the build.
+    pub graph: UnitGraph,
+}
+
+#[derive(Clone, Copy)]
+struct Schedule {
+    finish: f64,
+    rmeta_finish: f64,
+    previous: Option<usize>,
+}
+
+impl TimingReport {
+    /// Collects the `timing-info` messages and links them to the units of the graph.
+    pub fn new(messages: &[Message], graph: UnitGraph) -> Self {
+        let mut units: Vec<UnitTiming> = Vec::new();
+        // The index of the timing of every unit of the graph, in the order they finished.
+        let mut finished = vec![None; graph.units.len()];
+
+        for message in messages {
+            if let Message::TimingInfo(info) = message {
+                let unit = find_unit(&graph, info, &finished);
+
+                if let Some(unit) = unit {
+                    finished[unit] = Some(units.len());
+                }
+
+                units.push(UnitTiming {
+                    info: info.clone(),
+                    unit,
+                    unlocked_units: Vec::new(),
+                });
+            }
+        }
+
+        for (unit, timing) in finished.iter().enumerate() {
+            if timing.is_none() {
+                continue;
+            }
+
+            let last_dependency = graph.units[unit]
+                .dependencies
+                .iter()
+                .filter_map(|dependency| finished[dependency.index])
+                .max();
+
+            if let Some(last_dependency) = last_dependency {
+                units[last_dependency].unlocked_units.push(unit);
+            }
+        }
+
+        TimingReport { units, graph }
+    }
+
+    /// Returns the timing of the unit at the given index of the unit graph.
+    pub fn timing(&self, unit: usize) -> Option<&UnitTiming> {
+        self.units.iter().find(|timing| timing.unit == Some(unit))
+    }
+
+    /// The sum of the durations of every unit, in seconds.
+    ///
+    /// Units are built in parallel, so this is usually longer than the build itself.
+    pub fn total_duration(&self) -> f64 {
+        self.units.iter().map(|timing| timing.info.duration).sum()
+    }
+
+    /// Returns every unit timing, from the slowest to the fastest.
+    pub fn slowest_units(&self) -> Vec<&UnitTiming> {
+        let mut units: Vec<_> = self.units.iter().collect();
+        units.sort_by(|a, b| b.info.duration.total_cmp(&a.info.duration));
+        units
+    }
+
+    /// Returns the time spent on every package, from the slowest to the fastest.
+    pub fn slowest_crates(&self) -> Vec<CrateTiming> {
+        let mut crates: BTreeMap<&str, CrateTiming> = BTreeMap::new();
+
+        for timing in &self.units {
+            let package_id = timing.info.package_id.as_str();
+            let entry = crates.entry(package_id).or_insert_with(|| CrateTiming {
+                package_id: package_id.to_string(),
+                duration: 0.0,
+                units: 0,
+            });
+
+            entry.duration += timing.info.duration;
+            entry.units += 1;
+        }
+
+        let mut crates: Vec<_> = crates.into_values().collect();
+        crates.sort_by(|a, b| b.duration.total_cmp(&a.duration));
+        crates
+    }
+
+    /// Computes the critical path of the build from the dependencies in the unit graph.
+    ///
+    /// Like cargo, units that don't need to link can start as soon as the metadata of their dependencies
+    /// is available. Fresh units count as instant.
+    pub fn critical_path(&self) -> CriticalPath {
+        let mut schedules = vec![None; self.graph.units.len()];
+
+        for unit in 0..self.graph.units.len() {
+            self.schedule(unit, &mut schedules);
+        }
+
+        let last = schedules
+            .iter()
+            .enumerate()
+            .filter_map(|(unit, schedule)| Some((unit, (*schedule)?)))
+            .max_by(|(_, a), (_, b)| a.finish.total_cmp(&b.finish));
+
+        let Some((last, schedule)) = last else {
+            return CriticalPath::default();
+        };
+
+        let mut units = vec![last];
+        let mut previous = schedule.previous;
+
+        while let Some(unit) = previous {
+            units.push(unit);
+            previous = schedules[unit].and_then(|schedule| schedule.previous);
+        }
+
+        units.reverse();
+
+        CriticalPath {
+            units,
+            duration: schedule.finish,
+        }
+    }
+
+    /// Computes when a unit would finish if every unit started as soon as possible.
+    fn schedule(&self, unit: usize, schedules: &mut Vec<Option<Schedule>>) -> Schedule {
+        if let Some(schedule) = schedules[unit] {
+            return schedule;
+        }
+
+        let pipelined = can_pipeline(&self.graph.units[unit]);
+        let mut start = 0.0;
+        let mut previous = None;
+
+        for dependency in &self.graph.units[unit].dependencies {
+            let dependency_schedule = self.schedule(dependency.index, schedules);
+            let ready = if pipelined {
+                dependency_schedule.rmeta_finish
+            } else {
+                dependency_schedule.finish
+            };
+
+            if previous.is_none() || ready > start {
+                start = ready;
+                previous = Some(dependency.index);
+            }
+        }
+
+        let info = self.timing(unit).map(|timing| &timing.info);
+        let duration = info.map_or(0.0, |info| info.duration);
+        let rmeta_time = info.and_then(|info| info.rmeta_time).unwrap_or(duration);
+
+        let schedule = Schedule {
+            finish: start + duration,
+            rmeta_finish: start + rmeta_time,
+            previous,
+        };
+
+        schedules[unit] = Some(schedule);
+
+        schedule
+    }
+}
+
+/// Whether a unit only needs the metadata of its dependencies to start building.
+fn can_pipeline(unit: &Unit) -> bool {
+    match unit.mode {
+        Mode::Check | Mode::Doc => true,
+        Mode::Build => unit
+            .target
+            .kind
+            .iter()
+            .all(|kind| matches!(kind, TargetKind::Lib | TargetKind::Rlib)),
+        _ => false,
+    }
+}
+
+/// Finds the unit of the graph a timing belongs to, skipping units that already have a timing.
+///
+/// The Package ID in the messages and in the unit graph can use different formats, so both are parsed.
+/// The messages don't include the platform or profile, so when a unit is built more than once, E.g. for
+/// the host and for the target, the one with the most dependencies that already finished is picked.
+fn find_unit(graph: &UnitGraph, info: &TimingInfo, finished: &[Option<usize>]) -> Option<usize> {
+    let package_id = info.package_id.parse::<PackageId>().ok();
+
+    let matches = |unit: &Unit| {
+        let same_package = match (&package_id, unit.pkg_id.parse::<PackageId>()) {
+            (Some(package_id), Ok(unit_package_id)) => *package_id == unit_package_id,
+            _ => info.package_id == unit.pkg_id,
+        };
+
+        same_package
+            && unit.mode == info.mode
+            && unit.target.name == info.target.name
+            && unit.target.kind == info.target.kind
+    };
+
+    graph
+        .units
+        .iter()
+        .enumerate()
+        .filter(|(index, unit)| finished[*index].is_none() && matches(unit))
+        .max_by_key(|(_, unit)| {
+            unit.dependencies
+                .iter()
+                .filter(|dependency| finished[dependency.index].is_some())
+                .count()
+        })
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    const A: &str = "path+file:///ws/a#0.1.0";
+    const B: &str = "path+file:///ws/b#0.1.0";
+    const C: &str = "path+file:///ws/c#0.1.0";
+
+    fn target(name: &str) -> Value {
+        json!({
+            "kind": ["lib"],
+            "crate_types": ["lib"],
+            "name": name,
+            "src_path": format!("/ws/{name}/src/lib.rs"),
+            "edition": "2021",
+            "doc": true,
+            "doctest": true,
+            "test": true,
+        })
+    }
+
+    fn unit(package_id: &str, name: &str, platform: Option<&str>, dependencies: &[usize]) -> Value {
+        json!({
+            "pkg_id": package_id,
+            "target": target(name),
+            "profile": {
+                "name": "dev",
+                "opt_level": "0",
+                "lto": "false",
+                "codegen_units": null,
+                "debuginfo": 2,
+                "debug_assertions": true,
+                "overflow_checks": true,
+                "rpath": false,
+                "incremental": false,
+                "panic": "unwind",
+            },
+            "platform": platform,
+            "mode": "build",
+            "features": [],
+            "dependencies": dependencies
+                .iter()
+                .map(|index| json!({"index": index, "extern_crate_name": "dependency", "public": null}))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    fn timing(package_id: &str, name: &str, duration: f64) -> Message {
+        pipelined_timing(package_id, name, duration, None)
+    }
+
+    /// A timing with the time the metadata of the unit was available.
+    fn pipelined_timing(
+        package_id: &str,
+        name: &str,
+        duration: f64,
+        rmeta_time: Option<f64>,
+    ) -> Message {
+        serde_json::from_value(json!({
+            "reason": "timing-info",
+            "package_id": package_id,
+            "target": target(name),
+            "mode": "build",
+            "duration": duration,
+            "rmeta_time": rmeta_time,
+        }))
+        .unwrap()
+    }
+
+    fn graph(units: Vec<Value>) -> UnitGraph {
+        serde_json::from_value(json!({
+            "version": 1,
+            "roots": [units.len() - 1],
+            "units": units,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn units() {
+        // `a` is built for the host, and for the target where it depends on `b`.
+        let graph: UnitGraph = serde_json::from_value(json!({
+            "version": 1,
+            "units": [
+                unit(A, "a", None, &[]),
+                unit(A, "a", Some("wasm32-unknown-unknown"), &[2]),
+                unit(B, "b", Some("wasm32-unknown-unknown"), &[]),
+                unit(C, "c", None, &[0]),
+            ],
+            "roots": [1, 3],
+        }))
+        .unwrap();
+
+        let messages = [
+            timing(B, "b", 1.0),
+            timing(A, "a", 2.0),
+            timing(A, "a", 3.0),
+            timing(C, "c", 4.0),
+        ];
+
+        let report = TimingReport::new(&messages, graph);
+        let linked: Vec<_> = report.units.iter().map(|timing| timing.unit).collect();
+        let unlocked: Vec<_> = report
+            .units
+            .iter()
+            .map(|timing| timing.unlocked_units.clone())
+            .collect();
+
+        assert_eq!(linked, [Some(2), Some(1), Some(0), Some(3)]);
+        assert_eq!(unlocked, [vec![1], vec![], vec![3], vec![]]);
+        assert_eq!(report.total_duration(), 10.0);
+        assert_eq!(report.critical_path().units, [0, 3]);
+        assert_eq!(report.critical_path().duration, 7.0);
+    }
+
+    #[test]
+    fn built_twice() {
+        // `a` is built for the host and for the target, both times without dependencies.
+        let graph = graph(vec![
+            unit(A, "a", None, &[]),
+            unit(A, "a", Some("wasm32-unknown-unknown"), &[]),
+            unit(B, "b", None, &[0, 1]),
+        ]);
+
+        let messages = [
+            timing(A, "a", 1.0),
+            timing(A, "a", 2.0),
+            timing(B, "b", 0.5),
+            // There is no third unit of `a` left.
+            timing(A, "a", 4.0),
+        ];
+
+        let report = TimingReport::new(&messages, graph);
+        let linked: Vec<_> = report.units.iter().map(|timing| timing.unit).collect();
+
+        // Each timing goes to a different unit, never twice to the same one.
+        assert_eq!(linked[..2].iter().flatten().count(), 2);
+        assert_ne!(linked[0], linked[1]);
+        assert_eq!(linked[2..], [Some(2), None]);
+
+        // `b` waited for the second build of `a`.
+        assert!(report.units[0].unlocked_units.is_empty());
+        assert_eq!(report.units[1].unlocked_units, [2]);
+
+        assert_eq!(
+            report.slowest_crates(),
+            [
+                CrateTiming {
+                    package_id: A.to_string(),
+                    duration: 7.0,
+                    units: 3,
+                },
+                CrateTiming {
+                    package_id: B.to_string(),
+                    duration: 0.5,
+                    units: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn pipelined() {
+        let graph = graph(vec![unit(A, "a", None, &[]), unit(B, "b", None, &[0])]);
+
+        // The metadata of `a` is ready after 1s, so `b` starts long before `a` finished.
+        let messages = [
+            pipelined_timing(A, "a", 4.0, Some(1.0)),
+            pipelined_timing(B, "b", 5.0, Some(2.0)),
+        ];
+
+        let critical_path = TimingReport::new(&messages, graph.clone()).critical_path();
+        assert_eq!(critical_path.units, [0, 1]);
+        assert_eq!(critical_path.duration, 6.0);
+
+        // Once `b` is shorter than what is left of `a`, only `a` is critical.
+        let messages = [
+            pipelined_timing(A, "a", 4.0, Some(1.0)),
+            pipelined_timing(B, "b", 2.0, Some(1.0)),
+        ];
+
+        let critical_path = TimingReport::new(&messages, graph).critical_path();
+        assert_eq!(critical_path.units, [0]);
+        assert_eq!(critical_path.duration, 4.0);
+    }
+
+    #[test]
+    fn unmatched() {
+        let graph = graph(vec![unit(A, "a", None, &[])]);
+        let messages = [timing(C, "c", 3.0), timing(A, "a", 1.0)];
+
+        let report = TimingReport::new(&messages, graph);
+
+        assert_eq!(report.units[0].unit, None);
+        assert!(report.units[0].unlocked_units.is_empty());
+        assert_eq!(report.units[1].unit, Some(0));
+        assert_eq!(report.timing(0).unwrap().info.duration, 1.0);
+
+        // It still took time, but isn't part of the graph the critical path goes through.
+        assert_eq!(report.total_duration(), 4.0);
+        assert_eq!(report.slowest_crates()[0].package_id, C);
+        assert_eq!(report.critical_path().units, [0]);
+        assert_eq!(report.critical_path().duration, 1.0);
+
+        let graph = serde_json::from_value(json!({ "version": 1, "units": [], "roots": [] }));
+        let empty = TimingReport::new(&messages, graph.unwrap());
+        assert!(empty.units.iter().all(|timing| timing.unit.is_none()));
+        assert_eq!(empty.critical_path(), CriticalPath::default());
+    }
+}
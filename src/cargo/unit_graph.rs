@@ -97,6 +97,9 @@ pub enum Mode {
     /// Test using `rustdoc`.
     #[serde(rename = "doctest")]
     Doctest,
+    /// Scrape examples for `rustdoc`.
+    #[serde(rename = "docscrape")]
+    Docscrape,
     /// Represents the execution of a build script.
     #[serde(rename = "run-custom-build")]
     RunCustomBuild,
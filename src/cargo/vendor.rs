@@ -0,0 +1,374 @@
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    env,
+    path::{Path, PathBuf},
+};
+
+use super::{Cargo, Hermetic, Result};
+
+#[derive(Debug, Default, Clone)]
+pub struct VendorConfig {
+    /// The directory to vendor the sources into, "vendor" if [None].
+    pub path: Option<PathBuf>,
+    /// Extra manifests whose dependencies are vendored too, passed as `--sync`.
+    pub sync: Vec<PathBuf>,
+    /// Don't delete the sources in the directory that are no longer needed.
+    pub no_delete: bool,
+    /// Always include the version in the directory names, passed as `--versioned-dirs`.
+    pub versioned_dirs: bool,
+    /// Use the `[source]` configuration instead of always fetching from the original sources.
+    pub respect_source_config: bool,
+    pub manifest_path: Option<PathBuf>,
+}
+
+/// The `[source]` tables cargo prints after vendoring, replacing the original sources with the vendored directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct VendoredSources {
+    /// The sources by name, E.g. "crates-io" and "vendored-sources".
+    #[serde(default)]
+    pub source: BTreeMap<String, SourceReplacement>,
+}
+
+/// A `[source.<name>]` table.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SourceReplacement {
+    /// The name of the source replacing this one.
+    pub replace_with: Option<String>,
+    /// The directory of vendored sources, made absolute by [vendor](Cargo::vendor).
+    pub directory: Option<PathBuf>,
+    /// The url of a registry index.
+    pub registry: Option<String>,
+    /// The url of a git repository.
+    pub git: Option<String>,
+    pub branch: Option<String>,
+    pub tag: Option<String>,
+    pub rev: Option<String>,
+}
+
+impl VendoredSources {
+    /// The directory the sources were vendored into.
+    pub fn directory(&self) -> Option<&Path> {
+        self.source
+            .values()
+            .find_map(|source| source.directory.as_deref())
+    }
+
+    /// The configuration as values for `--config`, E.g. `source.crates-io.replace-with="vendored-sources"`.
+    pub fn config_values(&self) -> Vec<String> {
+        let mut values = Vec::new();
+
+        for (name, source) in &self.source {
+            // Names like "git+https://..." have to be quoted.
+            let name = if name
+                .chars()
+                .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_')
+            {
+                name.clone()
+            } else {
+                string(name)
+            };
+
+            let fields = [
+                ("replace-with", source.replace_with.clone()),
+                (
+                    "directory",
+                    source
+                        .directory
+                        .as_ref()
+                        .map(|directory| directory.to_string_lossy().into_owned()),
+                ),
+                ("registry", source.registry.clone()),
+                ("git", source.git.clone()),
+                ("branch", source.branch.clone()),
+                ("tag", source.tag.clone()),
+                ("rev", source.rev.clone()),
+            ];
+
+            for (key, value) in fields {
+                if let Some(value) = value {
+                    values.push(format!("source.{name}.{key}={}", string(&value)));
+                }
+            }
+        }
+
+        values
+    }
+}
+
+/// A quoted and escaped TOML string.
+fn string(value: &str) -> String {
+    toml::Value::String(value.to_string()).to_string()
+}
+
+impl Hermetic {
+    /// Builds with the vendored sources instead of the original ones.
+    pub fn vendored(self, sources: &VendoredSources) -> Self {
+        sources
+            .config_values()
+            .into_iter()
+            .fold(self, |hermetic, value| hermetic.config(value))
+    }
+}
+
+impl Cargo {
+    /// Runs `cargo vendor`, returning the source replacement configuration to build with the vendored sources.
+    ///
+    /// This fails in [Hermetic] mode, which can't download anything: vendor with a [Cargo] that isn't hermetic,
+    /// then pass the result to [Hermetic::vendored].
+    pub fn vendor(&mut self, config: VendorConfig) -> Result<VendoredSources> {
+        let mut command = self.command(["vendor"]);
+
+        for manifest in &config.sync {
+            command.arg("--sync").arg(manifest);
+        }
+
+        if config.no_delete {
+            command.arg("--no-delete");
+        }
+
+        if config.versioned_dirs {
+            command.arg("--versioned-dirs");
+        }
+
+        if config.respect_source_config {
+            command.arg("--respect-source-config");
+        }
+
+        if let Some(manifest_path) = &config.manifest_path {
+            command.arg("--manifest-path").arg(manifest_path);
+        }
+
+        if let Some(path) = &config.path {
+            command.arg(path);
+        }
+
+        let stdout = self.exec(&mut command)?;
+
+        vendored_sources(std::str::from_utf8(&stdout)?, &env::current_dir()?)
+    }
+}
+
+/// Parses the configuration printed by `cargo vendor` that ran in the given directory.
+fn vendored_sources(stdout: &str, current_dir: &Path) -> Result<VendoredSources> {
+    let mut sources: VendoredSources = toml::from_str(stdout)?;
+
+    // Cargo prints the directory as given, relative to where it ran.
+    for source in sources.source.values_mut() {
+        if let Some(directory) = &mut source.directory {
+            *directory = current_dir.join(&*directory);
+        }
+    }
+
+    Ok(sources)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STDOUT: &str = r#"
+[source.crates-io]
+replace-with = "vendored-sources"
+
+[source."git+https://github.com/rust-lang/log?branch=master"]
+git = "https://github.com/rust-lang/log"
+branch = "master"
+replace-with = "vendored-sources"
+
+[source.vendored-sources]
+directory = "third-party/vendor"
+"#;
+
+    #[test]
+    fn parse() {
+        let sources = vendored_sources(STDOUT, Path::new("/ws")).unwrap();
+
+        assert_eq!(
+            sources.source.keys().collect::<Vec<_>>(),
+            [
+                "crates-io",
+                "git+https://github.com/rust-lang/log?branch=master",
+                "vendored-sources"
+            ]
+        );
+        assert_eq!(
+            sources.source["git+https://github.com/rust-lang/log?branch=master"],
+            SourceReplacement {
+                replace_with: Some("vendored-sources".to_string()),
+                git: Some("https://github.com/rust-lang/log".to_string()),
+                branch: Some("master".to_string()),
+                ..Default::default()
+            }
+        );
+
+        // Relative directories are resolved against where cargo ran, absolute ones are kept.
+        assert_eq!(
+            sources.directory(),
+            Some(Path::new("/ws/third-party/vendor"))
+        );
+        assert_eq!(
+            vendored_sources(STDOUT, Path::new("/elsewhere"))
+                .unwrap()
+                .directory(),
+            Some(Path::new("/elsewhere/third-party/vendor"))
+        );
+        assert_eq!(
+            vendored_sources(
+                "[source.vendored-sources]\ndirectory = \"/abs/vendor\"\n",
+                Path::new("/ws")
+            )
+            .unwrap()
+            .directory(),
+            Some(Path::new("/abs/vendor"))
+        );
+
+        assert_eq!(
+            vendored_sources("", Path::new("/ws")).unwrap(),
+            VendoredSources::default()
+        );
+    }
+
+    #[test]
+    fn config_values() {
+        let sources = vendored_sources(STDOUT, Path::new("/ws")).unwrap();
+
+        assert_eq!(
+            sources.config_values(),
+            [
+                r#"source.crates-io.replace-with="vendored-sources""#,
+                r#"source."git+https://github.com/rust-lang/log?branch=master".replace-with="vendored-sources""#,
+                r#"source."git+https://github.com/rust-lang/log?branch=master".git="https://github.com/rust-lang/log""#,
+                r#"source."git+https://github.com/rust-lang/log?branch=master".branch="master""#,
+                r#"source.vendored-sources.directory="/ws/third-party/vendor""#,
+            ]
+        );
+
+        // Values with quotes or backslashes, E.g. Windows paths, stay valid TOML strings.
+        for directory in [r#"C:\ws\"quoted"\vendor"#, r"C:\it's\vendor"] {
+            let sources = VendoredSources {
+                source: [(
+                    "vendored-sources".to_string(),
+                    SourceReplacement {
+                        directory: Some(PathBuf::from(directory)),
+                        ..Default::default()
+                    },
+                )]
+                .into(),
+            };
+            let values = sources.config_values();
+            let (key, value) = values[0].split_once('=').unwrap();
+
+            assert_eq!(key, "source.vendored-sources.directory");
+            assert_eq!(
+                toml::from_str::<BTreeMap<String, String>>(&format!("value = {value}")).unwrap()
+                    ["value"],
+                directory
+            );
+        }
+    }
+
+    /// Vendors a git dependency with a regular cargo, then builds with the vendored copy in hermetic mode.
+    #[cfg(all(unix, feature = "json"))]
+    #[test]
+    fn hermetic_build() {
+        use crate::cargo::{home::temp_dir, BuildConfig};
+        use std::{fs, process::Command};
+
+        let dir = temp_dir("vendor");
+        let dep = dir.join("dep");
+        let app = dir.join("app");
+
+        fs::create_dir_all(dep.join("src")).unwrap();
+        fs::write(
+            dep.join("Cargo.toml"),
+            "[package]\nname = \"dep\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+        )
+        .unwrap();
+        fs::write(
+            dep.join("src").join("lib.rs"),
+            "pub fn answer() -> u32 {\n    42\n}\n",
+        )
+        .unwrap();
+
+        for args in [
+            &["init", "-q"][..],
+            &["add", "."],
+            &[
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-q",
+                "-m",
+                "dep",
+            ],
+        ] {
+            let status = Command::new("git")
+                .args(args)
+                .current_dir(&dep)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        }
+
+        fs::create_dir_all(app.join("src")).unwrap();
+        fs::write(
+            app.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+                 [dependencies]\ndep = {{ git = \"file://{}\" }}\n\n[workspace]\n",
+                dep.display()
+            ),
+        )
+        .unwrap();
+        fs::write(
+            app.join("src").join("lib.rs"),
+            "pub fn answer() -> u32 {\n    dep::answer()\n}\n",
+        )
+        .unwrap();
+
+        let manifest_path = app.join("Cargo.toml");
+
+        let mut cargo = Cargo::new();
+        cargo.env("CARGO_HOME", dir.join("home"));
+        let sources = cargo.vendor(VendorConfig {
+            path: Some(app.join("vendor")),
+            manifest_path: Some(manifest_path.clone()),
+            ..Default::default()
+        });
+
+        let build = sources.as_ref().map(|sources| {
+            let mut hermetic = Cargo::new();
+            hermetic.hermetic(Some(Hermetic::new().unwrap().vendored(sources)));
+            hermetic.build(BuildConfig {
+                manifest_path: Some(manifest_path.clone()),
+                ..Default::default()
+            })
+        });
+
+        // Vendoring can't download anything in hermetic mode.
+        let mut hermetic = Cargo::new();
+        hermetic.hermetic(Some(Hermetic::new().unwrap()));
+        let hermetic_vendor = hermetic.vendor(VendorConfig {
+            path: Some(dir.join("vendor")),
+            manifest_path: Some(manifest_path),
+            ..Default::default()
+        });
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            sources.as_ref().unwrap().directory(),
+            Some(app.join("vendor").as_path())
+        );
+        assert!(build
+            .unwrap()
+            .unwrap()
+            .artifacts()
+            .any(|artifact| artifact.target.name == "dep"));
+        assert!(hermetic_vendor.is_err());
+    }
+}
@@ -1,88 +1,277 @@
 use semver::Version as SemverVersion;
-use std::str::FromStr;
+use std::{collections::BTreeMap, str::FromStr};
 use target_lexicon::Triple;
 use time::{format_description::well_known::Iso8601, Date};
 
-use super::{ParsingError, Result};
+use super::{Cargo, ParsingError, Result};
 
-/// Parsed output of running "cargo --version --verbose".
+/// Parsed output of running "cargo --version --verbose", or just "cargo --version".
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Version {
     /// Version of this release of cargo, E.g. "1.62.0".
     pub release: SemverVersion,
     /// The sha1 hash of the latest commit when this version of cargo was published.
     ///
-    /// May be missing if not built from git.
+    /// May be missing if not built from git, and is abbreviated when parsed from "cargo --version".
     pub commit_hash: Option<String>,
     /// The date of the latest commit when this version of cargo was published.
     ///
     /// May be missing if not built from git.
     pub commit_date: Option<Date>,
     /// The host target triple, E.g. "x86_64-unknown-linux-gnu".
-    pub host: Triple,
-    /// The version of the bundled libgit2.
-    pub libgit2: String,
-    /// The version of the bundled libcurl.
-    pub libcurl: String,
+    ///
+    /// Only printed with "--verbose".
+    pub host: Option<Triple>,
+    /// The version of the bundled libgit2, missing in some distribution builds.
+    pub libgit2: Option<String>,
+    /// The version of the bundled libcurl, missing in some distribution builds.
+    pub libcurl: Option<String>,
     /// The current operating system, E.g. "Arch Linux Rolling Release \[64-bit\]".
-    pub os: String,
+    pub os: Option<String>,
+    /// Every other "key: value" line, E.g. "ssl".
+    pub extra: BTreeMap<String, String>,
+}
+
+/// What a version of cargo supports, from [Version::capabilities].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Capabilities {
+    /// Registries using the sparse protocol, E.g. "sparse+https://index.crates.io/".
+    pub sparse_registries: bool,
+    /// Printing the unit graph with `--unit-graph`, which is unstable and only works on nightly.
+    pub unit_graph: bool,
+    /// Build script directives using the "cargo::" prefix.
+    pub namespaced_directives: bool,
+    /// The `cargo::rustc-check-cfg` build script directive.
+    pub check_cfg_directive: bool,
+    /// The `cargo::error` build script directive.
+    pub error_directive: bool,
+    /// Reading and writing version 4 lockfiles.
+    pub lockfile_v4: bool,
+    /// `cargo clean` printing a summary of the removed files.
+    pub clean_summary: bool,
 }
 
 impl FromStr for Version {
     type Err = ParsingError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut lines = s.lines();
+        let mut header = None;
+        let mut fields = BTreeMap::new();
 
-        // Search for a line starting with "release: ", if not found returns an error otherwise it parses it as a semver::Version.
-        let release = lines
-            .find_map(|line| line.strip_prefix("release: "))
-            .ok_or(ParsingError::Version("release"))?
-            .parse()?;
+        // The lines can come in any order, the header is the only one that isn't "key: value".
+        for line in s.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if let Some(rest) = line.strip_prefix("cargo ") {
+                header = Some(rest);
+            } else if let Some((key, value)) = line.split_once(':') {
+                fields.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
 
-        // Search for a line starting with "commit-hash: " and returns it still wrapped in an option.
-        let commit_hash = lines
-            .find_map(|line| line.strip_prefix("commit-hash: "))
-            .map(|line| line.to_string());
-
-        // Search for a line starting with "commit-date: ", parses it as a time::Date and returns it still wrapped in an option.
-        let commit_date = lines
-            .find_map(|line| line.strip_prefix("commit-date: "))
-            .map(|line| line.to_string())
-            .map(|commit_date| Date::parse(&commit_date, &Iso8601::DEFAULT).unwrap());
-
-        // Search for a line starting with "host: ", if not found returns an error otherwise it parses it as a target_lexicon::Triple.
-        let host = lines
-            .find_map(|line| line.strip_prefix("host: "))
-            .ok_or(ParsingError::Version("host"))?
+        // The header looks like "1.62.0 (a748cf5a3 2022-06-08)", the details are missing when not built from git.
+        let (header_release, header_hash, header_date) = match header {
+            Some(header) => {
+                let (release, details) = header.split_once(' ').unwrap_or((header, ""));
+                let mut details = details
+                    .trim_start_matches('(')
+                    .trim_end_matches(')')
+                    .split_whitespace();
+
+                (Some(release), details.next(), details.next())
+            }
+            None => (None, None, None),
+        };
+
+        let release = fields
+            .remove("release")
+            .as_deref()
+            .or(header_release)
+            .ok_or(ParsingError::Version("release"))?
             .parse()?;
 
-        // Search for a line starting with "libgit2: ", if not found returns an error otherwise returns it as a String.
-        let libgit2 = lines
-            .find_map(|line| line.strip_prefix("libgit2: "))
-            .map(|line| line.to_string())
-            .ok_or(ParsingError::Version("libgit2"))?;
+        let commit_hash = fields
+            .remove("commit-hash")
+            .or_else(|| header_hash.map(String::from));
 
-        // Search for a line starting with "libcurl: ", if not found returns an error otherwise returns it as a String.
-        let libcurl = lines
-            .find_map(|line| line.strip_prefix("libcurl: "))
-            .map(|line| line.to_string())
-            .ok_or(ParsingError::Version("libcurl"))?;
+        // An unexpected date format shouldn't make the whole version unusable.
+        let commit_date = fields
+            .remove("commit-date")
+            .as_deref()
+            .or(header_date)
+            .and_then(|date| Date::parse(date, &Iso8601::DEFAULT).ok());
 
-        // Search for a line starting with "os: ", if not found returns an error otherwise returns it as a String.
-        let os = lines
-            .find_map(|line| line.strip_prefix("os: "))
-            .map(|line| line.to_string())
-            .ok_or(ParsingError::Version("os"))?;
+        let host = fields.remove("host").map(|host| host.parse()).transpose()?;
 
         Ok(Version {
             release,
             commit_hash,
             commit_date,
             host,
-            libgit2,
-            libcurl,
-            os,
+            libgit2: fields.remove("libgit2"),
+            libcurl: fields.remove("libcurl"),
+            os: fields.remove("os"),
+            extra: fields,
         })
     }
 }
+
+impl Version {
+    /// Whether this is a nightly or development build, which supports unstable "-Z" flags.
+    pub fn is_nightly(&self) -> bool {
+        let pre = self.release.pre.as_str();
+        pre.contains("nightly") || pre.contains("dev")
+    }
+
+    /// Whether this is at least the given stable 1.x release.
+    ///
+    /// Pre-releases count as the release they precede, since nightly versions already have its features.
+    pub fn at_least(&self, minor: u64) -> bool {
+        (self.release.major, self.release.minor) >= (1, minor)
+    }
+
+    /// Returns what this version of cargo supports.
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            sparse_registries: self.at_least(68),
+            unit_graph: self.is_nightly() && self.at_least(50),
+            namespaced_directives: self.at_least(77),
+            check_cfg_directive: self.at_least(80),
+            error_directive: self.at_least(84),
+            lockfile_v4: self.at_least(78),
+            clean_summary: self.at_least(75),
+        }
+    }
+}
+
+impl Cargo {
+    /// Returns what the configured cargo supports, from its [version](Self::version).
+    pub fn capabilities(&mut self) -> Result<Capabilities> {
+        Ok(self.version()?.capabilities())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::Month;
+
+    const STABLE: &str = "cargo 1.95.0 (f2d3ce0bd 2026-03-21)
+release: 1.95.0
+commit-hash: f2d3ce0bd7f24a49f8f72d9000448f8838c4e850
+commit-date: 2026-03-21
+host: x86_64-unknown-linux-gnu
+libgit2: 1.9.2 (sys:0.20.4 vendored)
+libcurl: 8.15.0-DEV (sys:0.4.83+curl-8.15.0 vendored ssl:OpenSSL/3.5.4)
+ssl: OpenSSL 3.5.4 30 Sep 2025
+os: Debian 12.0.0 (bookworm) [64-bit]
+";
+
+    #[test]
+    fn stable() {
+        let version: Version = STABLE.parse().unwrap();
+
+        assert_eq!(version.release, SemverVersion::new(1, 95, 0));
+        assert_eq!(
+            version.commit_hash.as_deref(),
+            Some("f2d3ce0bd7f24a49f8f72d9000448f8838c4e850")
+        );
+        assert_eq!(
+            version.commit_date,
+            Some(Date::from_calendar_date(2026, Month::March, 21).unwrap())
+        );
+        assert_eq!(
+            version.host,
+            Some("x86_64-unknown-linux-gnu".parse().unwrap())
+        );
+        assert_eq!(
+            version.libgit2.as_deref(),
+            Some("1.9.2 (sys:0.20.4 vendored)")
+        );
+        assert!(version.libcurl.as_ref().unwrap().starts_with("8.15.0-DEV"));
+        assert_eq!(
+            version.os.as_deref(),
+            Some("Debian 12.0.0 (bookworm) [64-bit]")
+        );
+        assert_eq!(version.extra["ssl"], "OpenSSL 3.5.4 30 Sep 2025");
+        assert!(!version.is_nightly());
+        assert!(version.at_least(95));
+        assert!(!version.at_least(96));
+    }
+
+    #[test]
+    fn nightly() {
+        let version: Version = "cargo 1.97.0-nightly (4d1f98451 2026-05-15)
+release: 1.97.0-nightly
+commit-hash: 4d1f984518c77fad6eeef4f40153b002a659e662
+commit-date: 2026-05-15
+host: x86_64-unknown-linux-gnu
+libgit2: 1.9.2 (sys:0.20.4 vendored)
+libcurl: 8.20.0-DEV (sys:0.4.88+curl-8.20.0 vendored ssl:OpenSSL/3.6.2)
+os: Debian 12.0.0 (bookworm) [64-bit]
+"
+        .parse()
+        .unwrap();
+
+        assert!(version.is_nightly());
+        // Nightly versions already have the features of the release they precede.
+        assert!(version.at_least(97));
+        assert!(version.capabilities().unit_graph);
+    }
+
+    #[test]
+    fn permuted() {
+        let mut lines: Vec<&str> = STABLE.lines().collect();
+        lines.reverse();
+
+        assert_eq!(
+            lines.join("\n").parse::<Version>().unwrap(),
+            STABLE.parse::<Version>().unwrap()
+        );
+    }
+
+    #[test]
+    fn missing_fields() {
+        // E.g. a distribution build without the vendored libraries, not built from git.
+        let version: Version = "cargo 1.75.0\nrelease: 1.75.0\nhost: aarch64-unknown-linux-gnu\n"
+            .parse()
+            .unwrap();
+
+        assert_eq!(version.release, SemverVersion::new(1, 75, 0));
+        assert_eq!(version.commit_hash, None);
+        assert_eq!(version.commit_date, None);
+        assert_eq!(version.libgit2, None);
+        assert_eq!(version.libcurl, None);
+        assert_eq!(version.os, None);
+        assert!(version.extra.is_empty());
+
+        assert!(matches!(
+            "host: x86_64-unknown-linux-gnu".parse::<Version>(),
+            Err(ParsingError::Version("release"))
+        ));
+    }
+
+    #[test]
+    fn short() {
+        let version: Version = "cargo 1.95.0 (f2d3ce0bd 2026-03-21)\n".parse().unwrap();
+
+        assert_eq!(version.release, SemverVersion::new(1, 95, 0));
+        assert_eq!(version.commit_hash.as_deref(), Some("f2d3ce0bd"));
+        assert_eq!(
+            version.commit_date,
+            Some(Date::from_calendar_date(2026, Month::March, 21).unwrap())
+        );
+        assert_eq!(version.host, None);
+    }
+
+    #[test]
+    fn invalid_commit_date() {
+        // Parsing the date used to panic.
+        let version: Version =
+            "cargo 1.70.0-beta.3 (ec8a8a0ca 2023-04-25-beta)\ncommit-date: unknown\n"
+                .parse()
+                .unwrap();
+
+        assert_eq!(version.release.pre.as_str(), "beta.3");
+        assert_eq!(version.commit_hash.as_deref(), Some("ec8a8a0ca"));
+        assert_eq!(version.commit_date, None);
+    }
+}